@@ -0,0 +1,115 @@
+import { Orchestrator, Player } from "@holochain/tryorama";
+import { Installables } from "../types";
+import { delay } from "../utils";
+
+function sendMessage(message) {
+  return (conductor) => conductor.call("p2pmessage", "send_message", message);
+}
+
+function blockAgent(agent) {
+  return (conductor) => conductor.call("p2pmessage", "block_agent", agent);
+}
+
+function unblockAgent(agent) {
+  return (conductor) => conductor.call("p2pmessage", "unblock_agent", agent);
+}
+
+function listBlockedAgents() {
+  return (conductor) =>
+    conductor.call("p2pmessage", "list_blocked_agents", null);
+}
+
+function getLatestMessages(batch_size, exclude_blocked) {
+  return (conductor) =>
+    conductor.call("p2pmessage", "get_latest_messages_filtered", {
+      batch_size,
+      exclude_blocked,
+    });
+}
+
+const blocking = async (conductorConfig, installation: Installables) => {
+  let orchestrator = new Orchestrator();
+
+  orchestrator.registerScenario("Block and unblock an agent", async (s, t) => {
+    const [alice, bob]: Player[] = await s.players([
+      conductorConfig,
+      conductorConfig,
+    ]);
+
+    const [[alice_happ]] = await alice.installAgentsHapps(installation.one);
+    const [[bobby_happ]] = await bob.installAgentsHapps(installation.one);
+
+    const alice_cell = alice_happ.cells[0];
+    const bobby_cell = bobby_happ.cells[0];
+
+    const agent_pubkey_alice = alice_happ.agent;
+    const agent_pubkey_bobby = bobby_happ.agent;
+
+    const message_bobby = {
+      receiver: agent_pubkey_alice,
+      payload: {
+        type: "TEXT",
+        payload: { payload: "Hi, Alice!" },
+      },
+      replyTo: null,
+    };
+
+    const message_alice = {
+      receiver: agent_pubkey_bobby,
+      payload: {
+        type: "TEXT",
+        payload: { payload: "Hi, Bobby!" },
+      },
+      replyTo: null,
+    };
+
+    await sendMessage(message_bobby)(bobby_cell);
+    await delay(1000);
+
+    /*
+     * alice blocks bobby
+     */
+    const blocked = await blockAgent(agent_pubkey_bobby)(alice_cell);
+    await delay();
+    t.deepEqual(blocked, [agent_pubkey_bobby]);
+    t.deepEqual(await listBlockedAgents()(alice_cell), [agent_pubkey_bobby]);
+
+    // bobby's message is hidden when excluding blocked conversants
+    const latest_all = await getLatestMessages(10, false)(alice_cell);
+    const latest_unblocked = await getLatestMessages(10, true)(alice_cell);
+    t.equal(Object.keys(latest_all[1]).length, 1);
+    t.equal(Object.keys(latest_unblocked[1]).length, 0);
+
+    // alice cannot message bobby while he is blocked
+    try {
+      await sendMessage(message_alice)(alice_cell);
+      t.fail();
+    } catch (e) {
+      t.ok(e);
+    }
+
+    // bobby's messages are dropped on alice's side
+    await sendMessage(message_bobby)(bobby_cell);
+    await delay(1000);
+    const latest_after_block = await getLatestMessages(10, false)(alice_cell);
+    t.equal(Object.keys(latest_after_block[1]).length, 1);
+
+    /*
+     * alice unblocks bobby
+     */
+    const unblocked = await unblockAgent(agent_pubkey_bobby)(alice_cell);
+    await delay();
+    t.deepEqual(unblocked, []);
+
+    await sendMessage(message_alice)(alice_cell);
+    await delay(1000);
+    const latest_after_unblock = await getLatestMessages(10, false)(
+      alice_cell
+    );
+    t.equal(Object.keys(latest_after_unblock[1]).length, 2);
+  });
+
+  orchestrator.run();
+};
+
+export default blocking;
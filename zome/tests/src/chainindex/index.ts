@@ -0,0 +1,87 @@
+import { Orchestrator, Player } from "@holochain/tryorama";
+import { Installables } from "../types";
+import { delay, serializeHash } from "../utils";
+
+function sendMessage(message) {
+  return (conductor) => conductor.call("p2pmessage", "send_message", message);
+}
+
+function getLatestMessages(batch_size) {
+  return (conductor) =>
+    conductor.call("p2pmessage", "get_latest_messages", batch_size);
+}
+
+function getPreviousMessages(conversant, last_message) {
+  return (conductor) =>
+    conductor.call("p2pmessage", "get_previous_messages", {
+      conversant: conversant,
+      batch_size: 10,
+      payload_filter: { kinds: ["ALL"] },
+      last_fetched_timestamp: last_message[1].timeSent,
+      last_fetched_message_id: last_message[0],
+    });
+}
+
+// enough actions for the chain index to cover the older messages
+const MESSAGE_COUNT = 120;
+
+const chainIndex = async (conductorConfig, installation: Installables) => {
+  let orchestrator = new Orchestrator();
+
+  orchestrator.registerScenario(
+    "Page through an indexed conversation",
+    async (s, t) => {
+      const [alice, bob]: Player[] = await s.players([
+        conductorConfig,
+        conductorConfig,
+      ]);
+
+      const [[alice_happ]] = await alice.installAgentsHapps(installation.one);
+      const [[bobby_happ]] = await bob.installAgentsHapps(installation.one);
+
+      const alice_cell = alice_happ.cells[0];
+
+      const agent_pubkey_bobby = bobby_happ.agent;
+      const bobby_key = serializeHash(agent_pubkey_bobby);
+
+      let sent: any[] = [];
+      for (let i = 0; i < MESSAGE_COUNT; i++) {
+        sent.push(
+          await sendMessage({
+            receiver: agent_pubkey_bobby,
+            payload: { type: "TEXT", payload: { payload: `message ${i}` } },
+            replyTo: null,
+          })(alice_cell)
+        );
+      }
+      await delay(2000);
+
+      const [latest_messages, latest_contents] = await getLatestMessages(10)(
+        alice_cell
+      );
+      t.equal(latest_messages[bobby_key].length, 10);
+      t.equal(
+        latest_contents[latest_messages[bobby_key][0]][0].payload.payload
+          .payload,
+        `message ${MESSAGE_COUNT - 1}`
+      );
+
+      // the oldest messages are reached through the index
+      let last_message = sent[10];
+      const [previous_messages, previous_contents] =
+        await getPreviousMessages(agent_pubkey_bobby, last_message)(
+          alice_cell
+        );
+      t.equal(previous_messages[bobby_key].length, 10);
+      t.equal(
+        previous_contents[previous_messages[bobby_key][9]][0].payload.payload
+          .payload,
+        "message 0"
+      );
+    }
+  );
+
+  orchestrator.run();
+};
+
+export default chainIndex;
@@ -0,0 +1,139 @@
+import { Orchestrator, Player } from "@holochain/tryorama";
+import { Installables } from "../types";
+import { delay, serializeHash } from "../utils";
+
+function sendMessage(message) {
+  return (conductor) => conductor.call("p2pmessage", "send_message", message);
+}
+
+function deleteMessage(message_hash, scope) {
+  return (conductor) =>
+    conductor.call("p2pmessage", "delete_message", { message_hash, scope });
+}
+
+function getLatestMessages(batch_size) {
+  return (conductor) =>
+    conductor.call("p2pmessage", "get_latest_messages", batch_size);
+}
+
+function flushOutbox(receiver) {
+  return (conductor) => conductor.call("p2pmessage", "flush_outbox", receiver);
+}
+
+const deletion = async (conductorConfig, installation: Installables) => {
+  let orchestrator = new Orchestrator();
+
+  orchestrator.registerScenario("Delete messages", async (s, t) => {
+    const [alice, bob]: Player[] = await s.players([
+      conductorConfig,
+      conductorConfig,
+    ]);
+
+    const [[alice_happ]] = await alice.installAgentsHapps(installation.one);
+    const [[bobby_happ]] = await bob.installAgentsHapps(installation.one);
+
+    const alice_cell = alice_happ.cells[0];
+    const bobby_cell = bobby_happ.cells[0];
+
+    const agent_pubkey_bobby = bobby_happ.agent;
+
+    let retractionsFromSignal: any[] = [];
+    bob.setSignalHandler((signal) => {
+      if (signal.data.payload.name === "RECEIVE_P2P_MESSAGE_RETRACTION")
+        retractionsFromSignal.push(signal.data.payload.payload);
+    });
+
+    const message = (text) => ({
+      receiver: agent_pubkey_bobby,
+      payload: { type: "TEXT", payload: { payload: text } },
+      replyTo: null,
+    });
+
+    const [hash_1] = await sendMessage(message("Hello, Bobby"))(alice_cell);
+    await delay(1000);
+    const [hash_2] = await sendMessage(message("Oops, wrong chat"))(
+      alice_cell
+    );
+    await delay(1000);
+
+    /*
+     * alice deletes the first message for herself only
+     */
+    await deleteMessage(hash_1, "forMe")(alice_cell);
+    await delay(1000);
+
+    let alice_latest = await getLatestMessages(5)(alice_cell);
+    let bobby_latest = await getLatestMessages(5)(bobby_cell);
+    const key_1 = serializeHash(hash_1);
+    const key_2 = serializeHash(hash_2);
+
+    // tombstones are returned instead of vanishing
+    t.ok(alice_latest[1][key_1][0].deleted);
+    t.notOk(bobby_latest[1][key_1][0].deleted);
+
+    /*
+     * alice deletes the second message for everyone
+     */
+    await deleteMessage(hash_2, "forEveryone")(alice_cell);
+    await delay(1000);
+
+    bobby_latest = await getLatestMessages(5)(bobby_cell);
+    t.ok(bobby_latest[1][key_2][0].deleted);
+    t.equal(bobby_latest[1][key_2][0].payload.payload.payload, "");
+    t.equal(retractionsFromSignal.length, 1);
+
+    // bobby cannot retract alice's message
+    try {
+      await deleteMessage(hash_1, "forEveryone")(bobby_cell);
+      t.fail();
+    } catch (e) {
+      t.ok(e);
+    }
+  });
+
+  orchestrator.registerScenario(
+    "Delete a message for everyone while the receiver is offline",
+    async (s, t) => {
+      const [alice, bob]: Player[] = await s.players([
+        conductorConfig,
+        conductorConfig,
+      ]);
+
+      const [[alice_happ]] = await alice.installAgentsHapps(installation.one);
+      const [[bobby_happ]] = await bob.installAgentsHapps(installation.one);
+
+      const alice_cell = alice_happ.cells[0];
+      const bobby_cell = bobby_happ.cells[0];
+
+      const agent_pubkey_bobby = bobby_happ.agent;
+
+      const [message_hash] = await sendMessage({
+        receiver: agent_pubkey_bobby,
+        payload: { type: "TEXT", payload: { payload: "Oops, wrong chat" } },
+        replyTo: null,
+      })(alice_cell);
+      await delay(2000);
+
+      await bob.shutdown();
+
+      // the deletion is kept by alice and the retraction queued for bobby
+      await deleteMessage(message_hash, "forEveryone")(alice_cell);
+      const message_key = serializeHash(message_hash);
+      const alice_latest = await getLatestMessages(5)(alice_cell);
+      t.ok(alice_latest[1][message_key][0].deleted);
+
+      await bob.startup();
+      await delay(4000);
+
+      await flushOutbox(agent_pubkey_bobby)(alice_cell);
+      await delay(2000);
+
+      const bobby_latest = await getLatestMessages(5)(bobby_cell);
+      t.ok(bobby_latest[1][message_key][0].deleted);
+    }
+  );
+
+  orchestrator.run();
+};
+
+export default deletion;
@@ -0,0 +1,142 @@
+import { Orchestrator, Player } from "@holochain/tryorama";
+import { Installables } from "../types";
+import { delay, serializeHash } from "../utils";
+
+function sendMessage(message) {
+  return (conductor) => conductor.call("p2pmessage", "send_message", message);
+}
+
+function editMessage(message_hash, text) {
+  return (conductor) =>
+    conductor.call("p2pmessage", "edit_message", {
+      message_hash,
+      payload: { type: "TEXT", payload: { payload: text } },
+    });
+}
+
+function getMessageEdits(message_hash) {
+  return (conductor) =>
+    conductor.call("p2pmessage", "get_message_edits", message_hash);
+}
+
+function getLatestMessages(batch_size) {
+  return (conductor) =>
+    conductor.call("p2pmessage", "get_latest_messages", batch_size);
+}
+
+function flushOutbox(receiver) {
+  return (conductor) => conductor.call("p2pmessage", "flush_outbox", receiver);
+}
+
+const edits = async (conductorConfig, installation: Installables) => {
+  let orchestrator = new Orchestrator();
+
+  orchestrator.registerScenario("Edit a message", async (s, t) => {
+    const [alice, bob]: Player[] = await s.players([
+      conductorConfig,
+      conductorConfig,
+    ]);
+
+    const [[alice_happ]] = await alice.installAgentsHapps(installation.one);
+    const [[bobby_happ]] = await bob.installAgentsHapps(installation.one);
+
+    const alice_cell = alice_happ.cells[0];
+    const bobby_cell = bobby_happ.cells[0];
+
+    const agent_pubkey_bobby = bobby_happ.agent;
+
+    const [message_hash] = await sendMessage({
+      receiver: agent_pubkey_bobby,
+      payload: {
+        type: "TEXT",
+        payload: { payload: "Helo, Bobby" },
+      },
+      replyTo: null,
+    })(alice_cell);
+    await delay(1000);
+
+    const [edited_hash, edited_message] = await editMessage(
+      message_hash,
+      "Hello, Bobby"
+    )(alice_cell);
+    await delay(1000);
+
+    t.deepEqual(edited_hash, message_hash);
+    t.equal(edited_message.payload.payload.payload, "Hello, Bobby");
+    t.ok(edited_message.edited);
+
+    // both chains return the latest content
+    const alice_latest = await getLatestMessages(5)(alice_cell);
+    const bobby_latest = await getLatestMessages(5)(bobby_cell);
+    const message_key = serializeHash(message_hash);
+    t.equal(alice_latest[1][message_key][0].payload.payload.payload, "Hello, Bobby");
+    t.equal(bobby_latest[1][message_key][0].payload.payload.payload, "Hello, Bobby");
+    t.ok(bobby_latest[1][message_key][0].edited);
+
+    // bobby cannot edit alice's message
+    try {
+      await editMessage(message_hash, "Bye, Alice")(bobby_cell);
+      t.fail();
+    } catch (e) {
+      t.ok(e);
+    }
+
+    await editMessage(message_hash, "Hello, Bobby!")(alice_cell);
+    await delay(1000);
+
+    const history = await getMessageEdits(message_hash)(bobby_cell);
+    t.equal(history.length, 2);
+    t.equal(history[0][1].payload.payload.payload, "Hello, Bobby");
+    t.equal(history[1][1].payload.payload.payload, "Hello, Bobby!");
+  });
+
+  orchestrator.registerScenario(
+    "Edit a message while the receiver is offline",
+    async (s, t) => {
+      const [alice, bob]: Player[] = await s.players([
+        conductorConfig,
+        conductorConfig,
+      ]);
+
+      const [[alice_happ]] = await alice.installAgentsHapps(installation.one);
+      const [[bobby_happ]] = await bob.installAgentsHapps(installation.one);
+
+      const alice_cell = alice_happ.cells[0];
+      const bobby_cell = bobby_happ.cells[0];
+
+      const agent_pubkey_bobby = bobby_happ.agent;
+
+      const [message_hash] = await sendMessage({
+        receiver: agent_pubkey_bobby,
+        payload: { type: "TEXT", payload: { payload: "Helo, Bobby" } },
+        replyTo: null,
+      })(alice_cell);
+      await delay(2000);
+
+      await bob.shutdown();
+
+      // the edit is kept by alice and queued for bobby
+      const [, edited_message] = await editMessage(
+        message_hash,
+        "Hello, Bobby"
+      )(alice_cell);
+      t.equal(edited_message.payload.payload.payload, "Hello, Bobby");
+      t.ok(edited_message.edited);
+
+      await bob.startup();
+      await delay(4000);
+
+      await flushOutbox(agent_pubkey_bobby)(alice_cell);
+      await delay(2000);
+
+      const bobby_latest = await getLatestMessages(5)(bobby_cell);
+      const message_key = serializeHash(message_hash);
+      t.equal(bobby_latest[1][message_key][0].payload.payload.payload, "Hello, Bobby");
+      t.ok(bobby_latest[1][message_key][0].edited);
+    }
+  );
+
+  orchestrator.run();
+};
+
+export default edits;
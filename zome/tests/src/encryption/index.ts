@@ -0,0 +1,110 @@
+import { Orchestrator, Player } from "@holochain/tryorama";
+import { Installables } from "../types";
+import { delay, serializeHash } from "../utils";
+
+function enableEncryption(conversant) {
+  return (conductor) =>
+    conductor.call("p2pmessage", "enable_encryption", conversant);
+}
+
+function isEncrypted(conversant) {
+  return (conductor) =>
+    conductor.call("p2pmessage", "is_encrypted", conversant);
+}
+
+function sendMessage(message) {
+  return (conductor) => conductor.call("p2pmessage", "send_message", message);
+}
+
+function getPreviousMessages(conversant, kinds) {
+  return (conductor) =>
+    conductor.call("p2pmessage", "get_previous_messages", {
+      conversant: conversant,
+      batch_size: 10,
+      payload_filter: { kinds },
+      last_fetched_timestamp: null,
+      last_fetched_message_id: null,
+    });
+}
+
+function getLatestMessages(batch_size) {
+  return (conductor) =>
+    conductor.call("p2pmessage", "get_latest_messages", batch_size);
+}
+
+const encryption = async (conductorConfig, installation: Installables) => {
+  let orchestrator = new Orchestrator();
+
+  orchestrator.registerScenario("Encrypted conversation", async (s, t) => {
+    const [alice, bob]: Player[] = await s.players([
+      conductorConfig,
+      conductorConfig,
+    ]);
+
+    const [[alice_happ]] = await alice.installAgentsHapps(installation.one);
+    const [[bobby_happ]] = await bob.installAgentsHapps(installation.one);
+
+    const alice_cell = alice_happ.cells[0];
+    const bobby_cell = bobby_happ.cells[0];
+
+    const agent_pubkey_alice = alice_happ.agent;
+    const agent_pubkey_bobby = bobby_happ.agent;
+
+    let messagesFromSignal: any[] = [];
+    bob.setSignalHandler((signal) => {
+      if (signal.data.payload.name === "RECEIVE_P2P_MESSAGE")
+        messagesFromSignal.push(signal.data.payload.payload);
+    });
+
+    t.notOk(await isEncrypted(agent_pubkey_bobby)(alice_cell));
+
+    await enableEncryption(agent_pubkey_bobby)(alice_cell);
+    await delay(1000);
+
+    // the handshake leaves both conversants with each other's key
+    t.ok(await isEncrypted(agent_pubkey_bobby)(alice_cell));
+    t.ok(await isEncrypted(agent_pubkey_alice)(bobby_cell));
+
+    const [message_hash, message_data] = await sendMessage({
+      receiver: agent_pubkey_bobby,
+      payload: { type: "TEXT", payload: { payload: "For your eyes only" } },
+      replyTo: null,
+    })(alice_cell);
+    await delay(1000);
+    t.equal(message_data.payload.type, "TEXT");
+
+    // signals and getters return the decrypted payload
+    t.equal(messagesFromSignal.length, 1);
+    t.equal(
+      messagesFromSignal[0].message[0][1].payload.payload.payload,
+      "For your eyes only"
+    );
+
+    const key = serializeHash(message_hash);
+    const alice_latest = await getLatestMessages(5)(alice_cell);
+    const bobby_latest = await getLatestMessages(5)(bobby_cell);
+    t.equal(
+      alice_latest[1][key][0].payload.payload.payload,
+      "For your eyes only"
+    );
+    t.equal(
+      bobby_latest[1][key][0].payload.payload.payload,
+      "For your eyes only"
+    );
+
+    // a message bob can decrypt is indexed as text, not as encrypted
+    const bobby_key = serializeHash(agent_pubkey_bobby);
+    const [texts] = await getPreviousMessages(agent_pubkey_bobby, ["TEXT"])(
+      alice_cell
+    );
+    const [undecryptable] = await getPreviousMessages(agent_pubkey_bobby, [
+      "ENCRYPTED",
+    ])(alice_cell);
+    t.equal(texts[bobby_key].length, 1);
+    t.equal(undecryptable[bobby_key].length, 0);
+  });
+
+  orchestrator.run();
+};
+
+export default encryption;
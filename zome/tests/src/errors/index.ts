@@ -0,0 +1,81 @@
+import { Orchestrator, Player } from "@holochain/tryorama";
+import { Installables } from "../types";
+import { delay } from "../utils";
+
+function sendMessage(message) {
+  return (conductor) => conductor.call("p2pmessage", "send_message", message);
+}
+
+function blockAgent(agent) {
+  return (conductor) => conductor.call("p2pmessage", "block_agent", agent);
+}
+
+function unblockAgent(agent) {
+  return (conductor) => conductor.call("p2pmessage", "unblock_agent", agent);
+}
+
+function getFileChunk(file_hash, index) {
+  return (conductor) =>
+    conductor.call("p2pmessage", "get_file_chunk", { file_hash, index });
+}
+
+// resolves to the code of the error the call failed with
+async function errorCode(call) {
+  try {
+    await call;
+    return null;
+  } catch (e) {
+    const match = JSON.stringify(e).match(/\\"code\\": ?\\"([A-Z_]+)\\"/);
+    return match ? match[1] : null;
+  }
+}
+
+const errors = async (conductorConfig, installation: Installables) => {
+  let orchestrator = new Orchestrator();
+
+  orchestrator.registerScenario("Externs fail with codes", async (s, t) => {
+    const [alice, bob]: Player[] = await s.players([
+      conductorConfig,
+      conductorConfig,
+    ]);
+
+    const [[alice_happ]] = await alice.installAgentsHapps(installation.one);
+    const [[bobby_happ]] = await bob.installAgentsHapps(installation.one);
+
+    const alice_cell = alice_happ.cells[0];
+
+    const agent_pubkey_alice = alice_happ.agent;
+    const agent_pubkey_bobby = bobby_happ.agent;
+
+    const message = {
+      receiver: agent_pubkey_bobby,
+      payload: { type: "TEXT", payload: { payload: "Hello, Bobby" } },
+      replyTo: null,
+    };
+
+    t.equal(
+      await errorCode(blockAgent(agent_pubkey_alice)(alice_cell)),
+      "VALIDATION_FAILED"
+    );
+
+    await blockAgent(agent_pubkey_bobby)(alice_cell);
+    await delay(1000);
+
+    t.equal(await errorCode(sendMessage(message)(alice_cell)), "BLOCKED");
+
+    await unblockAgent(agent_pubkey_bobby)(alice_cell);
+    await delay(1000);
+
+    const [message_hash] = await sendMessage(message)(alice_cell);
+    await delay(1000);
+
+    t.equal(
+      await errorCode(getFileChunk(message_hash, 0)(alice_cell)),
+      "NOT_FOUND"
+    );
+  });
+
+  orchestrator.run();
+};
+
+export default errors;
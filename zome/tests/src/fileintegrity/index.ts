@@ -0,0 +1,91 @@
+import { Orchestrator, Player } from "@holochain/tryorama";
+import { Installables } from "../types";
+
+function sendMessage(message) {
+  return (conductor) => conductor.call("p2pmessage", "send_message", message);
+}
+
+function fileMessage(receiver, fileSize, fileType, fileBytes) {
+  return {
+    receiver: receiver,
+    payload: {
+      type: "FILE",
+      payload: {
+        metadata: {
+          fileName: "photo.png",
+          fileSize: fileSize,
+          fileType: fileType,
+        },
+        fileType: { type: "IMAGE", payload: { thumbnail: Buffer.alloc(4) } },
+        fileBytes: fileBytes,
+      },
+    },
+    replyTo: null,
+  };
+}
+
+const fileIntegrity = async (conductorConfig, installation: Installables) => {
+  let orchestrator = new Orchestrator();
+
+  orchestrator.registerScenario(
+    "Reject files not matching their metadata",
+    async (s, t) => {
+      const [alice, bob]: Player[] = await s.players([
+        conductorConfig,
+        conductorConfig,
+      ]);
+
+      const [[alice_happ]] = await alice.installAgentsHapps(installation.one);
+      const [[bobby_happ]] = await bob.installAgentsHapps(installation.one);
+
+      const alice_cell = alice_happ.cells[0];
+      const agent_pubkey_bobby = bobby_happ.agent;
+
+      const file_bytes = Buffer.alloc(1024, 3);
+
+      // the size given by the frontend has to match the bytes
+      try {
+        await sendMessage(
+          fileMessage(agent_pubkey_bobby, 2048, "image/png", file_bytes)
+        )(alice_cell);
+        t.fail();
+      } catch (e) {
+        t.ok(JSON.stringify(e).includes("INVALID_FILE"));
+      }
+
+      // and so does the kind of file
+      try {
+        await sendMessage(
+          fileMessage(agent_pubkey_bobby, 1024, "application/pdf", file_bytes)
+        )(alice_cell);
+        t.fail();
+      } catch (e) {
+        t.ok(JSON.stringify(e).includes("INVALID_FILE"));
+      }
+
+      // files over max_file_size in the dna properties are refused
+      try {
+        await sendMessage(
+          fileMessage(
+            agent_pubkey_bobby,
+            60 * 1024 * 1024,
+            "image/png",
+            file_bytes
+          )
+        )(alice_cell);
+        t.fail();
+      } catch (e) {
+        t.ok(JSON.stringify(e).includes("FILE_TOO_LARGE"));
+      }
+
+      const [_, message_data] = await sendMessage(
+        fileMessage(agent_pubkey_bobby, 1024, "image/png", file_bytes)
+      )(alice_cell);
+      t.equal(message_data.payload.payload.metadata.fileSize, 1024);
+    }
+  );
+
+  orchestrator.run();
+};
+
+export default fileIntegrity;
@@ -0,0 +1,140 @@
+import { Orchestrator, Player } from "@holochain/tryorama";
+import { Installables } from "../types";
+import { delay } from "../utils";
+
+function sendMessage(message) {
+  return (conductor) => conductor.call("p2pmessage", "send_message", message);
+}
+
+function getFileBytes(file_hashes) {
+  return (conductor) =>
+    conductor.call("p2pmessage", "get_file_bytes", file_hashes);
+}
+
+function getFileChunk(file_hash, index) {
+  return (conductor) =>
+    conductor.call("p2pmessage", "get_file_chunk", { file_hash, index });
+}
+
+function createGroup(name, members) {
+  return (conductor) =>
+    conductor.call("p2pmessage", "create_group", { name, members });
+}
+
+function sendGroupMessage(group_id, payload) {
+  return (conductor) =>
+    conductor.call("p2pmessage", "send_group_message", {
+      group_id,
+      payload,
+      reply_to: null,
+    });
+}
+
+// larger than two chunks of 1 MiB
+const FILE_SIZE = 2.5 * 1024 * 1024;
+
+const files = async (conductorConfig, installation: Installables) => {
+  let orchestrator = new Orchestrator();
+
+  orchestrator.registerScenario("Send a chunked file", async (s, t) => {
+    const [alice, bob]: Player[] = await s.players([
+      conductorConfig,
+      conductorConfig,
+    ]);
+
+    const [[alice_happ]] = await alice.installAgentsHapps(installation.one);
+    const [[bobby_happ]] = await bob.installAgentsHapps(installation.one);
+
+    const alice_cell = alice_happ.cells[0];
+    const bobby_cell = bobby_happ.cells[0];
+
+    const agent_pubkey_bobby = bobby_happ.agent;
+
+    let chunksFromSignal: any[] = [];
+    bob.setSignalHandler((signal) => {
+      if (signal.data.payload.name === "RECEIVE_P2P_FILE_CHUNK")
+        chunksFromSignal.push(signal.data.payload.payload);
+    });
+
+    const file_bytes = Buffer.alloc(FILE_SIZE, 7);
+    const [_, message_data] = await sendMessage({
+      receiver: agent_pubkey_bobby,
+      payload: {
+        type: "FILE",
+        payload: {
+          metadata: {
+            fileName: "holiday.mp4",
+            fileSize: FILE_SIZE,
+            fileType: "video/mp4",
+          },
+          fileType: { type: "OTHER" },
+          fileBytes: file_bytes,
+        },
+      },
+      replyTo: null,
+    })(alice_cell);
+    await delay(5000);
+
+    const metadata = message_data.payload.payload.metadata;
+    t.equal(metadata.chunks.length, 3);
+    t.equal(chunksFromSignal.length, 3);
+    t.equal(chunksFromSignal[2].received, 3);
+
+    // the receiver reassembles the whole file from its chunks
+    const bobby_files = await getFileBytes([metadata.fileHash])(bobby_cell);
+    t.equal(Object.keys(bobby_files).length, 1);
+
+    // or streams it chunk by chunk
+    const last_chunk = await getFileChunk(metadata.fileHash, 2)(bobby_cell);
+    t.equal(last_chunk.length, FILE_SIZE - 2 * 1024 * 1024);
+
+    try {
+      await getFileChunk(metadata.fileHash, 3)(bobby_cell);
+      t.fail();
+    } catch (e) {
+      t.ok(e);
+    }
+  });
+
+  orchestrator.registerScenario("Send a chunked file to a group", async (s, t) => {
+    const [alice, bob]: Player[] = await s.players([
+      conductorConfig,
+      conductorConfig,
+    ]);
+
+    const [[alice_happ]] = await alice.installAgentsHapps(installation.one);
+    const [[bobby_happ]] = await bob.installAgentsHapps(installation.one);
+
+    const alice_cell = alice_happ.cells[0];
+    const bobby_cell = bobby_happ.cells[0];
+
+    const group = await createGroup("Holiday", [bobby_happ.agent])(alice_cell);
+    await delay(1000);
+
+    const delivery = await sendGroupMessage(group.groupId, {
+      type: "FILE",
+      payload: {
+        metadata: {
+          fileName: "holiday.mp4",
+          fileSize: FILE_SIZE,
+          fileType: "video/mp4",
+        },
+        fileType: { type: "OTHER" },
+        fileBytes: Buffer.alloc(FILE_SIZE, 7),
+      },
+    })(alice_cell);
+    await delay(5000);
+
+    // group files take the same chunks as direct messages
+    const metadata = delivery.message.payload.payload.metadata;
+    t.equal(metadata.chunks.length, 3);
+    t.equal(delivery.undelivered.length, 0);
+
+    const bobby_files = await getFileBytes([metadata.fileHash])(bobby_cell);
+    t.equal(Object.keys(bobby_files).length, 1);
+  });
+
+  orchestrator.run();
+};
+
+export default files;
@@ -31,7 +31,7 @@ function getPreviousMessages(message, conversant) {
   let input = {
     conversant: conversant,
     batch_size: 5,
-    payload_type: "All",
+    payload_filter: { kinds: ["ALL"] },
     last_fetched_timestamp: message[0][1].timeSent,
     last_fetched_message_id: message[0][0],
   };
@@ -43,7 +43,7 @@ function getNextMessages(message, conversant) {
   let input = {
     conversant: conversant,
     batch_size: 5,
-    payload_type: "All",
+    payload_filter: { kinds: ["ALL"] },
     last_fetched_timestamp: message[0][1].timeSent,
     last_fetched_message_id: message[0][0],
   };
@@ -55,7 +55,7 @@ function getAdjacentMessages(message, conversant) {
   let input = {
     conversant: conversant,
     batch_size: 2,
-    payload_type: "All",
+    payload_filter: { kinds: ["ALL"] },
     last_fetched_timestamp: message[0][1].timeSent,
     last_fetched_message_id: message[0][0],
   };
@@ -0,0 +1,80 @@
+import { Orchestrator, Player } from "@holochain/tryorama";
+import { Installables } from "../types";
+import { delay, serializeHash } from "../utils";
+
+function sendMessage(message) {
+  return (conductor) => conductor.call("p2pmessage", "send_message", message);
+}
+
+function issueGrant(agent) {
+  return (conductor) => conductor.call("p2pmessage", "issue_grant", agent);
+}
+
+function revokeGrant(agent) {
+  return (conductor) => conductor.call("p2pmessage", "revoke_grant", agent);
+}
+
+// resolves to the code of the error the call failed with
+async function errorCode(call) {
+  try {
+    await call;
+    return null;
+  } catch (e) {
+    const match = JSON.stringify(e).match(/\\"code\\": ?\\"([A-Z_]+)\\"/);
+    return match ? match[1] : null;
+  }
+}
+
+const grants = async (conductorConfig, installation: Installables) => {
+  let orchestrator = new Orchestrator();
+
+  orchestrator.registerScenario("Issue and revoke grants", async (s, t) => {
+    const [alice, bob]: Player[] = await s.players([
+      conductorConfig,
+      conductorConfig,
+    ]);
+
+    const [[alice_happ]] = await alice.installAgentsHapps(installation.one);
+    const [[bobby_happ]] = await bob.installAgentsHapps(installation.one);
+
+    const alice_cell = alice_happ.cells[0];
+
+    const agent_pubkey_alice = alice_happ.agent;
+    const agent_pubkey_bobby = bobby_happ.agent;
+
+    t.equal(
+      await errorCode(issueGrant(agent_pubkey_alice)(alice_cell)),
+      "VALIDATION_FAILED"
+    );
+
+    // issuing twice keeps the first grant
+    const grant_hash = await issueGrant(agent_pubkey_bobby)(alice_cell);
+    const reissued_hash = await issueGrant(agent_pubkey_bobby)(alice_cell);
+    t.equal(serializeHash(reissued_hash), serializeHash(grant_hash));
+    await delay(1000);
+
+    await revokeGrant(agent_pubkey_bobby)(alice_cell);
+    await delay(1000);
+
+    t.equal(
+      await errorCode(revokeGrant(agent_pubkey_bobby)(alice_cell)),
+      "NOT_FOUND"
+    );
+
+    // the default unrestricted policy still lets bobby receive messages
+    const [message_hash, message_data] = await sendMessage({
+      receiver: agent_pubkey_bobby,
+      payload: { type: "TEXT", payload: { payload: "Hello, Bobby" } },
+      replyTo: null,
+    })(alice_cell);
+    t.ok(message_hash);
+    t.equal(
+      serializeHash(message_data.receiver),
+      serializeHash(agent_pubkey_bobby)
+    );
+  });
+
+  orchestrator.run();
+};
+
+export default grants;
@@ -0,0 +1,135 @@
+import { Orchestrator, Player } from "@holochain/tryorama";
+import { Installables } from "../types";
+import { delay, serializeHash } from "../utils";
+
+function createGroup(name, members) {
+  return (conductor) =>
+    conductor.call("p2pmessage", "create_group", { name, members });
+}
+
+function addGroupMember(group_id, member) {
+  return (conductor) =>
+    conductor.call("p2pmessage", "add_group_member", { group_id, member });
+}
+
+function removeGroupMember(group_id, member) {
+  return (conductor) =>
+    conductor.call("p2pmessage", "remove_group_member", { group_id, member });
+}
+
+function leaveGroup(group_id) {
+  return (conductor) => conductor.call("p2pmessage", "leave_group", group_id);
+}
+
+function getGroups() {
+  return (conductor) => conductor.call("p2pmessage", "get_groups", null);
+}
+
+function sendGroupMessage(group_id, text) {
+  return (conductor) =>
+    conductor.call("p2pmessage", "send_group_message", {
+      group_id,
+      payload: { type: "TEXT", payload: { payload: text } },
+      reply_to: null,
+    });
+}
+
+function readGroupMessages(group_id, message_hashes) {
+  return (conductor) =>
+    conductor.call("p2pmessage", "read_group_messages", {
+      group_id,
+      message_hashes,
+      timestamp: Date.now() * 1000,
+    });
+}
+
+function getLatestGroupMessages(batch_size) {
+  return (conductor) =>
+    conductor.call("p2pmessage", "get_latest_group_messages", batch_size);
+}
+
+const groups = async (conductorConfig, installation: Installables) => {
+  let orchestrator = new Orchestrator();
+
+  orchestrator.registerScenario("Group conversations", async (s, t) => {
+    const [alice, bob, carol]: Player[] = await s.players([
+      conductorConfig,
+      conductorConfig,
+      conductorConfig,
+    ]);
+
+    const [[alice_happ]] = await alice.installAgentsHapps(installation.one);
+    const [[bobby_happ]] = await bob.installAgentsHapps(installation.one);
+    const [[carol_happ]] = await carol.installAgentsHapps(installation.one);
+
+    const alice_cell = alice_happ.cells[0];
+    const bobby_cell = bobby_happ.cells[0];
+    const carol_cell = carol_happ.cells[0];
+
+    const agent_pubkey_bobby = bobby_happ.agent;
+    const agent_pubkey_carol = carol_happ.agent;
+
+    let groupMessagesFromSignal: any[] = [];
+    carol.setSignalHandler((signal) => {
+      if (signal.data.payload.name === "RECEIVE_GROUP_MESSAGE")
+        groupMessagesFromSignal.push(signal.data.payload.payload);
+    });
+
+    const group = await createGroup("Lunch", [agent_pubkey_bobby])(alice_cell);
+    await delay(1000);
+    t.equal(group.members.length, 2);
+
+    let bobby_groups = await getGroups()(bobby_cell);
+    t.equal(bobby_groups.length, 1);
+
+    // any member can add new members
+    await addGroupMember(group.groupId, agent_pubkey_carol)(bobby_cell);
+    await delay(1000);
+
+    const delivery = await sendGroupMessage(group.groupId, "Ramen?")(
+      alice_cell
+    );
+    await delay(1000);
+    t.equal(Object.keys(delivery.receipts).length, 2);
+    t.equal(delivery.undelivered.length, 0);
+    t.equal(groupMessagesFromSignal.length, 1);
+
+    await readGroupMessages(group.groupId, [delivery.messageHash])(
+      carol_cell
+    );
+    await delay(1000);
+
+    const key = serializeHash(group.groupId);
+    const message_key = serializeHash(delivery.messageHash);
+    let alice_latest = await getLatestGroupMessages(10)(alice_cell);
+    t.equal(alice_latest[0][key].length, 1);
+    // two delivered receipts and carol's read receipt
+    t.equal(alice_latest[1][message_key][1].length, 3);
+
+    // only the creator can remove members
+    try {
+      await removeGroupMember(group.groupId, agent_pubkey_carol)(bobby_cell);
+      t.fail();
+    } catch (e) {
+      t.ok(e);
+    }
+
+    await leaveGroup(group.groupId)(carol_cell);
+    await delay(1000);
+
+    const carol_groups = await getGroups()(carol_cell);
+    t.equal(carol_groups.length, 0);
+
+    // members who left no longer receive group messages
+    try {
+      await sendGroupMessage(group.groupId, "Still here?")(carol_cell);
+      t.fail();
+    } catch (e) {
+      t.ok(e);
+    }
+  });
+
+  orchestrator.run();
+};
+
+export default groups;
@@ -11,6 +11,28 @@ import receipts from "./receipts";
 import signals from "./signals";
 import pin from "./pin";
 import playground from "./playground";
+import blocking from "./blocking";
+import edits from "./edits";
+import deletion from "./deletion";
+import reactions from "./reactions";
+import groups from "./groups";
+import encryption from "./encryption";
+import files from "./files";
+import fileIntegrity from "./fileintegrity";
+import search from "./search";
+import chainIndex from "./chainindex";
+import payloadFilter from "./payloadfilter";
+import pinExpiry from "./pinexpiry";
+import errors from "./errors";
+import undelivered from "./undelivered";
+import summaries from "./summaries";
+import markRead from "./markread";
+import offlineReceipts from "./offlinereceipts";
+import grants from "./grants";
+import messageRequests from "./messagerequests";
+import provenance from "./provenance";
+import rateLimits from "./ratelimits";
+import typingState from "./typingstate";
 
 import { Installables } from "./types";
 
@@ -38,3 +60,25 @@ messaging(conductorConfig, installables);
 // signals(conductorConfig, installables); // 0 tests
 // pin(conductorConfig, installables); // pass
 // playground(conductorConfig, installables);
+blocking(conductorConfig, installables);
+edits(conductorConfig, installables);
+deletion(conductorConfig, installables);
+reactions(conductorConfig, installables);
+groups(conductorConfig, installables);
+encryption(conductorConfig, installables);
+files(conductorConfig, installables);
+fileIntegrity(conductorConfig, installables);
+search(conductorConfig, installables);
+chainIndex(conductorConfig, installables);
+payloadFilter(conductorConfig, installables);
+pinExpiry(conductorConfig, installables);
+errors(conductorConfig, installables);
+undelivered(conductorConfig, installables);
+summaries(conductorConfig, installables);
+markRead(conductorConfig, installables);
+offlineReceipts(conductorConfig, installables);
+grants(conductorConfig, installables);
+messageRequests(conductorConfig, installables);
+provenance(conductorConfig, installables);
+rateLimits(conductorConfig, installables);
+typingState(conductorConfig, installables);
@@ -0,0 +1,80 @@
+import { Orchestrator, Player } from "@holochain/tryorama";
+import { Installables } from "../types";
+import { delay, dateToTimestamp } from "../utils";
+
+function sendMessage(message) {
+  return (conductor) => conductor.call("p2pmessage", "send_message", message);
+}
+
+function markConversationRead(conversant, up_to) {
+  return (conductor) =>
+    conductor.call("p2pmessage", "mark_conversation_read", {
+      conversant: conversant,
+      up_to_timestamp: dateToTimestamp(up_to),
+    });
+}
+
+function textMessage(receiver, text) {
+  return {
+    receiver: receiver,
+    payload: { type: "TEXT", payload: { payload: text } },
+    replyTo: null,
+  };
+}
+
+const markRead = async (conductorConfig, installation: Installables) => {
+  let orchestrator = new Orchestrator();
+
+  orchestrator.registerScenario("Mark a conversation as read", async (s, t) => {
+    const [alice, bob]: Player[] = await s.players([
+      conductorConfig,
+      conductorConfig,
+    ]);
+
+    const [[alice_happ]] = await alice.installAgentsHapps(installation.one);
+    const [[bobby_happ]] = await bob.installAgentsHapps(installation.one);
+
+    const alice_cell = alice_happ.cells[0];
+    const bobby_cell = bobby_happ.cells[0];
+
+    const agent_pubkey_alice = alice_happ.agent;
+    const agent_pubkey_bobby = bobby_happ.agent;
+
+    await sendMessage(textMessage(agent_pubkey_bobby, "Hello"))(alice_cell);
+    await sendMessage(textMessage(agent_pubkey_bobby, "Bobby?"))(alice_cell);
+    await delay(1000);
+    const before_last = new Date();
+    await delay(1000);
+    await sendMessage(textMessage(agent_pubkey_bobby, "Coffee?"))(alice_cell);
+    await delay(2000);
+
+    // one receipt for both messages sent before the point
+    const first_receipts = await markConversationRead(
+      agent_pubkey_alice,
+      before_last
+    )(bobby_cell);
+    const first_receipt: any = Object.values(first_receipts)[0];
+    t.equal(Object.keys(first_receipts).length, 1);
+    t.equal(first_receipt.id.length, 2);
+    await delay(1000);
+
+    // messages marked before are not sent again
+    const second_receipts = await markConversationRead(
+      agent_pubkey_alice,
+      new Date()
+    )(bobby_cell);
+    const second_receipt: any = Object.values(second_receipts)[0];
+    t.equal(second_receipt.id.length, 1);
+    await delay(1000);
+
+    const third_receipts = await markConversationRead(
+      agent_pubkey_alice,
+      new Date()
+    )(bobby_cell);
+    t.equal(Object.keys(third_receipts).length, 0);
+  });
+
+  orchestrator.run();
+};
+
+export default markRead;
@@ -0,0 +1,85 @@
+import { Orchestrator, Player } from "@holochain/tryorama";
+import { Installables } from "../types";
+import { delay } from "../utils";
+
+function sendMessage(message) {
+  return (conductor) => conductor.call("p2pmessage", "send_message", message);
+}
+
+function listMessageRequests() {
+  return (conductor) =>
+    conductor.call("p2pmessage", "list_message_requests", null);
+}
+
+function acceptMessageRequest(sender) {
+  return (conductor) =>
+    conductor.call("p2pmessage", "accept_message_request", sender);
+}
+
+function declineMessageRequest(sender) {
+  return (conductor) =>
+    conductor.call("p2pmessage", "decline_message_request", sender);
+}
+
+function getConversationSummaries() {
+  return (conductor) =>
+    conductor.call("p2pmessage", "get_conversation_summaries", false);
+}
+
+// resolves to the code of the error the call failed with
+async function errorCode(call) {
+  try {
+    await call;
+    return null;
+  } catch (e) {
+    const match = JSON.stringify(e).match(/\\"code\\": ?\\"([A-Z_]+)\\"/);
+    return match ? match[1] : null;
+  }
+}
+
+// the example dna keeps message_requests off, first messages go to the inbox
+const messageRequests = async (conductorConfig, installation: Installables) => {
+  let orchestrator = new Orchestrator();
+
+  orchestrator.registerScenario("Message requests are off", async (s, t) => {
+    const [alice, bob]: Player[] = await s.players([
+      conductorConfig,
+      conductorConfig,
+    ]);
+
+    const [[alice_happ]] = await alice.installAgentsHapps(installation.one);
+    const [[bobby_happ]] = await bob.installAgentsHapps(installation.one);
+
+    const alice_cell = alice_happ.cells[0];
+    const bobby_cell = bobby_happ.cells[0];
+
+    const agent_pubkey_alice = alice_happ.agent;
+    const agent_pubkey_bobby = bobby_happ.agent;
+
+    await sendMessage({
+      receiver: agent_pubkey_bobby,
+      payload: { type: "TEXT", payload: { payload: "Nice to meet you" } },
+      replyTo: null,
+    })(alice_cell);
+    await delay(2000);
+
+    t.deepEqual(await listMessageRequests()(bobby_cell), []);
+
+    const summaries = await getConversationSummaries()(bobby_cell);
+    t.equal(summaries.length, 1);
+    t.equal(summaries[0].unreadCount, 1);
+
+    t.equal(
+      await errorCode(acceptMessageRequest(agent_pubkey_alice)(bobby_cell)),
+      "NOT_FOUND"
+    );
+    t.equal(
+      await errorCode(declineMessageRequest(agent_pubkey_alice)(bobby_cell)),
+      "NOT_FOUND"
+    );
+  });
+
+  orchestrator.run();
+};
+
+export default messageRequests;
@@ -0,0 +1,85 @@
+import { Orchestrator, Player } from "@holochain/tryorama";
+import { Installables } from "../types";
+import { delay, dateToTimestamp, serializeHash } from "../utils";
+
+function sendMessage(message) {
+  return (conductor) => conductor.call("p2pmessage", "send_message", message);
+}
+
+function readMessage(read_receipt_input) {
+  return (conductor) =>
+    conductor.call("p2pmessage", "read_message", read_receipt_input);
+}
+
+function getUndelivered() {
+  return (conductor) => conductor.call("p2pmessage", "get_undelivered", null);
+}
+
+function flushOutbox(receiver) {
+  return (conductor) => conductor.call("p2pmessage", "flush_outbox", receiver);
+}
+
+function getConversationSummaries() {
+  return (conductor) =>
+    conductor.call("p2pmessage", "get_conversation_summaries", false);
+}
+
+const offlineReceipts = async (conductorConfig, installation: Installables) => {
+  let orchestrator = new Orchestrator();
+
+  orchestrator.registerScenario(
+    "Read receipts to an offline sender",
+    async (s, t) => {
+      const [alice, bob]: Player[] = await s.players([
+        conductorConfig,
+        conductorConfig,
+      ]);
+
+      const [[alice_happ]] = await alice.installAgentsHapps(installation.one);
+      const [[bobby_happ]] = await bob.installAgentsHapps(installation.one);
+
+      const alice_cell = alice_happ.cells[0];
+      const bobby_cell = bobby_happ.cells[0];
+
+      const agent_pubkey_alice = alice_happ.agent;
+      const agent_pubkey_bobby = bobby_happ.agent;
+
+      const [message_hash] = await sendMessage({
+        receiver: agent_pubkey_bobby,
+        payload: { type: "TEXT", payload: { payload: "Hello, Bobby" } },
+        replyTo: null,
+      })(alice_cell);
+      await delay(2000);
+
+      await alice.shutdown();
+
+      // the read receipt is kept by bobby while alice is offline
+      const read_receipts = await readMessage({
+        message_hashes: [message_hash],
+        sender: agent_pubkey_alice,
+        timestamp: dateToTimestamp(new Date()),
+      })(bobby_cell);
+      const [receipt_hash] = Object.keys(read_receipts);
+      t.equal(read_receipts[receipt_hash].status.status, "read");
+
+      const undelivered = await getUndelivered()(bobby_cell);
+      const queued = undelivered.receipts.find(
+        (receipt) => serializeHash(receipt.receiptHash) === receipt_hash
+      );
+      t.equal(queued.status.outboxstatus, "pending");
+
+      await alice.startup();
+      await delay(4000);
+
+      await flushOutbox(agent_pubkey_alice)(bobby_cell);
+      await delay(2000);
+
+      const [summary] = await getConversationSummaries()(alice_cell);
+      t.equal(summary.lastOutgoingRead, true);
+    }
+  );
+
+  orchestrator.run();
+};
+
+export default offlineReceipts;
@@ -0,0 +1,108 @@
+import { Orchestrator, Player } from "@holochain/tryorama";
+import { Installables } from "../types";
+import { delay, serializeHash } from "../utils";
+
+function sendMessage(message) {
+  return (conductor) => conductor.call("p2pmessage", "send_message", message);
+}
+
+function getPreviousMessages(conversant, payload_filter) {
+  return (conductor) =>
+    conductor.call("p2pmessage", "get_previous_messages", {
+      conversant: conversant,
+      batch_size: 10,
+      payload_filter: payload_filter,
+      last_fetched_timestamp: null,
+      last_fetched_message_id: null,
+    });
+}
+
+function fileMessage(receiver, fileName, fileType, kind) {
+  return {
+    receiver: receiver,
+    payload: {
+      type: "FILE",
+      payload: {
+        metadata: { fileName: fileName, fileSize: 8, fileType: fileType },
+        fileType: kind,
+        fileBytes: Buffer.alloc(8, 2),
+      },
+    },
+    replyTo: null,
+  };
+}
+
+const payloadFilter = async (conductorConfig, installation: Installables) => {
+  let orchestrator = new Orchestrator();
+
+  orchestrator.registerScenario("Filter messages by payload", async (s, t) => {
+    const [alice, bob]: Player[] = await s.players([
+      conductorConfig,
+      conductorConfig,
+    ]);
+
+    const [[alice_happ]] = await alice.installAgentsHapps(installation.one);
+    const [[bobby_happ]] = await bob.installAgentsHapps(installation.one);
+
+    const alice_cell = alice_happ.cells[0];
+    const bobby_cell = bobby_happ.cells[0];
+
+    const agent_pubkey_alice = alice_happ.agent;
+    const agent_pubkey_bobby = bobby_happ.agent;
+    const bobby_key = serializeHash(agent_pubkey_bobby);
+
+    const [hello_hash] = await sendMessage({
+      receiver: agent_pubkey_bobby,
+      payload: { type: "TEXT", payload: { payload: "Hello" } },
+      replyTo: null,
+    })(alice_cell);
+    await sendMessage(
+      fileMessage(agent_pubkey_bobby, "cat.png", "image/png", {
+        type: "IMAGE",
+        payload: { thumbnail: Buffer.alloc(4) },
+      })
+    )(alice_cell);
+    await sendMessage(
+      fileMessage(agent_pubkey_bobby, "notes.pdf", "application/pdf", {
+        type: "OTHER",
+      })
+    )(alice_cell);
+    await delay(1000);
+    await sendMessage({
+      receiver: agent_pubkey_alice,
+      payload: { type: "TEXT", payload: { payload: "Hi" } },
+      replyTo: hello_hash,
+    })(bobby_cell);
+    await delay(1000);
+
+    const count = async (payload_filter) => {
+      const [messages] = await getPreviousMessages(
+        agent_pubkey_bobby,
+        payload_filter
+      )(alice_cell);
+      return messages[bobby_key].length;
+    };
+
+    t.equal(await count({}), 4);
+    t.equal(await count({ kinds: ["TEXT"] }), 2);
+    t.equal(await count({ kinds: ["MEDIA"] }), 1);
+    t.equal(await count({ kinds: ["FILE"] }), 2);
+    t.equal(await count({ kinds: ["MEDIA", "OTHER"] }), 2);
+    t.equal(await count({ mime_types: ["application/"] }), 1);
+    t.equal(await count({ has_reply: true }), 1);
+    t.equal(await count({ kinds: ["TEXT"], from_me: true }), 1);
+
+    try {
+      await getPreviousMessages(agent_pubkey_bobby, { kinds: ["STICKER"] })(
+        alice_cell
+      );
+      t.fail();
+    } catch (e) {
+      t.ok(e);
+    }
+  });
+
+  orchestrator.run();
+};
+
+export default payloadFilter;
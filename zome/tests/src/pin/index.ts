@@ -20,8 +20,7 @@ function pinMessage(message) {
   let message_input = {
     message_hashes: [message[0][0]],
     conversants: [message[0][1].receiver, message[0][1].author],
-    status: "Pinned",
-    timestamp: dateToTimestamp(timestamp),
+    status: { pinstatus: "pinned", timestamp: dateToTimestamp(timestamp) },
   };
   return (conductor) =>
     conductor.call("p2pmessage", "pin_message", message_input);
@@ -32,8 +31,7 @@ function unpinMessage(message) {
   let message_input = {
     message_hashes: [message[0][0]],
     conversants: [message[0][1].receiver, message[0][1].author],
-    status: "Unpinned",
-    timestamp: dateToTimestamp(timestamp),
+    status: { pinstatus: "unpinned", timestamp: dateToTimestamp(timestamp) },
   };
   return (conductor) =>
     conductor.call("p2pmessage", "pin_message", message_input);
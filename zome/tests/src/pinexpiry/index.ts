@@ -0,0 +1,108 @@
+import { Orchestrator, Player } from "@holochain/tryorama";
+import { Installables } from "../types";
+import { delay, dateToTimestamp, serializeHash } from "../utils";
+
+function sendMessage(message) {
+  return (conductor) => conductor.call("p2pmessage", "send_message", message);
+}
+
+function pinMessages(message_hashes, conversants, expires_in_ms) {
+  let timestamp = new Date();
+  let message_input = {
+    message_hashes: message_hashes,
+    conversants: conversants,
+    status: {
+      pinstatus: "pinned",
+      timestamp: dateToTimestamp(timestamp),
+      expires_at:
+        expires_in_ms === null
+          ? null
+          : dateToTimestamp(new Date(timestamp.getTime() + expires_in_ms)),
+    },
+  };
+  return (conductor) =>
+    conductor.call("p2pmessage", "pin_message", message_input);
+}
+
+function getPinnedMessages(conversant) {
+  return (conductor) =>
+    conductor.call("p2pmessage", "get_pinned_messages", conversant);
+}
+
+const pinExpiry = async (conductorConfig, installation: Installables) => {
+  let orchestrator = new Orchestrator();
+
+  orchestrator.registerScenario("Pin expiry and pin limit", async (s, t) => {
+    const [alice, bob]: Player[] = await s.players([
+      conductorConfig,
+      conductorConfig,
+    ]);
+
+    const [[alice_happ]] = await alice.installAgentsHapps(installation.one);
+    const [[bobby_happ]] = await bob.installAgentsHapps(installation.one);
+
+    const alice_cell = alice_happ.cells[0];
+
+    const agent_pubkey_alice = alice_happ.agent;
+    const agent_pubkey_bobby = bobby_happ.agent;
+    const conversants = [agent_pubkey_bobby, agent_pubkey_alice];
+
+    let message_hashes: any[] = [];
+    for (let i = 0; i < 51; i++) {
+      const [message_hash] = await sendMessage({
+        receiver: agent_pubkey_bobby,
+        payload: { type: "TEXT", payload: { payload: `Message ${i}` } },
+        replyTo: null,
+      })(alice_cell);
+      message_hashes.push(message_hash);
+    }
+    await delay(1000);
+
+    // a pin that expires before it was pinned is rejected
+    try {
+      await pinMessages([message_hashes[0]], conversants, -1000)(alice_cell);
+      t.fail();
+    } catch (e) {
+      t.ok(JSON.stringify(e).includes("INVALID_PIN"));
+    }
+
+    await pinMessages([message_hashes[0]], conversants, 3000)(alice_cell);
+    await delay(1000);
+
+    const [, pinned_before] = await getPinnedMessages(agent_pubkey_bobby)(
+      alice_cell
+    );
+    t.ok(
+      Object.keys(pinned_before).includes(serializeHash(message_hashes[0]))
+    );
+
+    await delay(3000);
+
+    const [, pinned_after] = await getPinnedMessages(agent_pubkey_bobby)(
+      alice_cell
+    );
+    t.notOk(
+      Object.keys(pinned_after).includes(serializeHash(message_hashes[0]))
+    );
+
+    // the default limit is 50 pins per conversation
+    try {
+      await pinMessages(message_hashes, conversants, null)(alice_cell);
+      t.fail();
+    } catch (e) {
+      t.ok(JSON.stringify(e).includes("PIN_LIMIT_EXCEEDED"));
+    }
+
+    await pinMessages(message_hashes.slice(1), conversants, null)(alice_cell);
+    await delay(1000);
+
+    const [, pinned_limit] = await getPinnedMessages(agent_pubkey_bobby)(
+      alice_cell
+    );
+    t.equal(Object.keys(pinned_limit).length, 50);
+  });
+
+  orchestrator.run();
+};
+
+export default pinExpiry;
@@ -0,0 +1,124 @@
+import { Orchestrator, Player } from "@holochain/tryorama";
+import { Installables } from "../types";
+import { delay, dateToTimestamp } from "../utils";
+
+function sendMessage(message) {
+  return (conductor) => conductor.call("p2pmessage", "send_message", message);
+}
+
+function receiveMessage(input) {
+  return (conductor) => conductor.call("p2pmessage", "receive_message", input);
+}
+
+function receiveReceipt(receipt) {
+  return (conductor) =>
+    conductor.call("p2pmessage", "receive_receipt", receipt);
+}
+
+function receiveEdit(edit) {
+  return (conductor) => conductor.call("p2pmessage", "receive_edit", edit);
+}
+
+function syncPins(pin) {
+  return (conductor) => conductor.call("p2pmessage", "sync_pins", pin);
+}
+
+// resolves to the code of the error the call failed with
+async function errorCode(call) {
+  try {
+    await call;
+    return null;
+  } catch (e) {
+    const match = JSON.stringify(e).match(/\\"code\\": ?\\"([A-Z_]+)\\"/);
+    return match ? match[1] : null;
+  }
+}
+
+const provenance = async (conductorConfig, installation: Installables) => {
+  let orchestrator = new Orchestrator();
+
+  orchestrator.registerScenario("Remote calls check caller", async (s, t) => {
+    const [alice, bob, carly]: Player[] = await s.players([
+      conductorConfig,
+      conductorConfig,
+      conductorConfig,
+    ]);
+
+    const [[alice_happ]] = await alice.installAgentsHapps(installation.one);
+    const [[bobby_happ]] = await bob.installAgentsHapps(installation.one);
+    const [[carly_happ]] = await carly.installAgentsHapps(installation.one);
+
+    const alice_cell = alice_happ.cells[0];
+    const carly_cell = carly_happ.cells[0];
+
+    const agent_pubkey_alice = alice_happ.agent;
+    const agent_pubkey_bobby = bobby_happ.agent;
+    const agent_pubkey_carly = carly_happ.agent;
+
+    const [message_hash] = await sendMessage({
+      receiver: agent_pubkey_bobby,
+      payload: { type: "TEXT", payload: { payload: "Hello, Bobby" } },
+      replyTo: null,
+    })(alice_cell);
+    await delay(2000);
+
+    // carly cannot pass a message off as one from bobby
+    const forged_message = {
+      author: agent_pubkey_bobby,
+      receiver: agent_pubkey_carly,
+      payload: { type: "TEXT", payload: { payload: "It's me, Bobby" } },
+      timeSent: dateToTimestamp(new Date()),
+      replyTo: null,
+    };
+    t.equal(
+      await errorCode(
+        receiveMessage({ message: forged_message, file: null })(carly_cell)
+      ),
+      "UNAUTHORIZED"
+    );
+
+    // alice is not the receiver of her own message to bobby
+    t.equal(
+      await errorCode(
+        receiveReceipt({
+          id: [message_hash],
+          status: { status: "read", timestamp: dateToTimestamp(new Date()) },
+        })(alice_cell)
+      ),
+      "UNAUTHORIZED"
+    );
+
+    // an edit of alice's message to bobby is for bobby only
+    t.equal(
+      await errorCode(
+        receiveEdit({
+          messageHash: message_hash,
+          author: agent_pubkey_alice,
+          receiver: agent_pubkey_bobby,
+          payload: { type: "TEXT", payload: { payload: "Hello again, Bobby" } },
+          timeEdited: dateToTimestamp(new Date()),
+        })(alice_cell)
+      ),
+      "UNAUTHORIZED"
+    );
+
+    // carly is not part of the conversation of the pin
+    t.equal(
+      await errorCode(
+        syncPins({
+          id: [message_hash],
+          conversants: [agent_pubkey_alice, agent_pubkey_bobby],
+          status: {
+            pinstatus: "pinned",
+            timestamp: dateToTimestamp(new Date()),
+          },
+        })(carly_cell)
+      ),
+      "UNAUTHORIZED"
+    );
+  });
+
+  orchestrator.run();
+};
+
+export default provenance;
@@ -0,0 +1,174 @@
+import { Orchestrator, Player } from "@holochain/tryorama";
+import path from "path";
+import { Installables } from "../types";
+import { delay, serializeHash } from "../utils";
+
+const p2pmessagedna = path.join(
+  __dirname,
+  "../../../p2pmessage.workdir.dna/p2pmessage.dna"
+);
+
+// low enough to go over within a test, the other properties keep their defaults
+const lowLimits = {
+  rate_limits: {
+    messages_per_minute: 2,
+    bytes_per_day: 16,
+    report_spam: true,
+  },
+};
+
+function sendMessage(message) {
+  return (conductor) => conductor.call("p2pmessage", "send_message", message);
+}
+
+function getRateLimits() {
+  return (conductor) => conductor.call("p2pmessage", "get_rate_limits", null);
+}
+
+function getSpamReports() {
+  return (conductor) => conductor.call("p2pmessage", "get_spam_reports", null);
+}
+
+function getUndelivered() {
+  return (conductor) => conductor.call("p2pmessage", "get_undelivered", null);
+}
+
+function getLatestMessages(batch_size) {
+  return (conductor) =>
+    conductor.call("p2pmessage", "get_latest_messages_filtered", {
+      batch_size,
+      exclude_blocked: false,
+    });
+}
+
+// the players of a scenario have to install the dna with the same properties
+async function installWithProperties(player: Player, properties) {
+  const dna_hash = await player.registerDna(
+    { path: p2pmessagedna },
+    undefined,
+    properties
+  );
+  const [[happ]] = await player.installAgentsHapps([[[dna_hash]]]);
+  return happ;
+}
+
+function textMessage(receiver, text) {
+  return {
+    receiver: receiver,
+    payload: { type: "TEXT", payload: { payload: text } },
+    replyTo: null,
+  };
+}
+
+// the failed outbox entry of the message on the side of the sender
+async function outboxStatus(conductor, message_hash) {
+  const undelivered = await getUndelivered()(conductor);
+  const message = undelivered.messages.find(
+    (message) =>
+      serializeHash(message.messageHash) === serializeHash(message_hash)
+  );
+  return message ? message.status : null;
+}
+
+const rateLimits = async (conductorConfig, installation: Installables) => {
+  let orchestrator = new Orchestrator();
+
+  orchestrator.registerScenario("Inbound rate limits", async (s, t) => {
+    const [alice, bob]: Player[] = await s.players([
+      conductorConfig,
+      conductorConfig,
+    ]);
+
+    const [[alice_happ]] = await alice.installAgentsHapps(installation.one);
+    const [[bobby_happ]] = await bob.installAgentsHapps(installation.one);
+
+    const alice_cell = alice_happ.cells[0];
+    const bobby_cell = bobby_happ.cells[0];
+
+    const agent_pubkey_bobby = bobby_happ.agent;
+
+    // the limits of the example dna
+    const limits = await getRateLimits()(bobby_cell);
+    t.equal(limits.messages_per_minute, 60);
+    t.equal(limits.bytes_per_day, 1073741824);
+    t.equal(limits.report_spam, false);
+
+    // a few messages stay well within the limits
+    for (let i = 0; i < 3; i++) {
+      await sendMessage({
+        receiver: agent_pubkey_bobby,
+        payload: { type: "TEXT", payload: { payload: `Hello ${i}` } },
+        replyTo: null,
+      })(alice_cell);
+    }
+    await delay(2000);
+
+    t.deepEqual(await getSpamReports()(bobby_cell), []);
+  });
+
+  orchestrator.registerScenario("Calls over a limit", async (s, t) => {
+    const [alice, bob]: Player[] = await s.players([
+      conductorConfig,
+      conductorConfig,
+    ]);
+
+    const alice_happ = await installWithProperties(alice, lowLimits);
+    const bobby_happ = await installWithProperties(bob, lowLimits);
+
+    const alice_cell = alice_happ.cells[0];
+    const bobby_cell = bobby_happ.cells[0];
+
+    const agent_pubkey_alice = alice_happ.agent;
+    const agent_pubkey_bobby = bobby_happ.agent;
+
+    const limits = await getRateLimits()(bobby_cell);
+    t.equal(limits.messages_per_minute, 2);
+    t.equal(limits.bytes_per_day, 16);
+    t.equal(limits.report_spam, true);
+
+    await sendMessage(textMessage(agent_pubkey_bobby, "Hello"))(alice_cell);
+    await delay(2000);
+
+    // 5 bytes are in, 16 more go over the bytes per day
+    const [too_large_hash] = await sendMessage(
+      textMessage(agent_pubkey_bobby, "x".repeat(16))
+    )(alice_cell);
+    await delay(2000);
+
+    const too_large = await outboxStatus(alice_cell, too_large_hash);
+    t.equal(too_large.outboxstatus, "failed");
+    t.ok(JSON.stringify(too_large).includes("RATE_LIMITED"));
+
+    await sendMessage(textMessage(agent_pubkey_bobby, "Hi"))(alice_cell);
+    await delay(2000);
+
+    // a third message within the minute goes over the messages per minute
+    const [too_many_hash] = await sendMessage(
+      textMessage(agent_pubkey_bobby, "Yo")
+    )(alice_cell);
+    await delay(2000);
+
+    const too_many = await outboxStatus(alice_cell, too_many_hash);
+    t.equal(too_many.outboxstatus, "failed");
+    t.ok(JSON.stringify(too_many).includes("RATE_LIMITED"));
+
+    // the failed calls were rolled back, only the messages within the limits are kept
+    const latest = await getLatestMessages(10)(bobby_cell);
+    t.equal(Object.keys(latest[1]).length, 2);
+
+    // while the reports were committed by calls of their own
+    const reports = await getSpamReports()(bobby_cell);
+    t.ok(reports.find((report) => report.limit === "bytesPerDay"));
+    t.ok(reports.find((report) => report.limit === "messagesPerMinute"));
+    t.ok(
+      reports.every(
+        (report) =>
+          serializeHash(report.sender) === serializeHash(agent_pubkey_alice)
+      )
+    );
+  });
+
+  orchestrator.run();
+};
+
+export default rateLimits;
@@ -0,0 +1,83 @@
+import { Orchestrator, Player } from "@holochain/tryorama";
+import { Installables } from "../types";
+import { delay, serializeHash } from "../utils";
+
+function sendMessage(message) {
+  return (conductor) => conductor.call("p2pmessage", "send_message", message);
+}
+
+function reactToMessage(message_hash, emoji) {
+  return (conductor) =>
+    conductor.call("p2pmessage", "react_to_message", { message_hash, emoji });
+}
+
+function removeReaction(message_hash, emoji) {
+  return (conductor) =>
+    conductor.call("p2pmessage", "remove_reaction", { message_hash, emoji });
+}
+
+function getLatestMessages(batch_size) {
+  return (conductor) =>
+    conductor.call("p2pmessage", "get_latest_messages", batch_size);
+}
+
+const reactions = async (conductorConfig, installation: Installables) => {
+  let orchestrator = new Orchestrator();
+
+  orchestrator.registerScenario("React to a message", async (s, t) => {
+    const [alice, bob]: Player[] = await s.players([
+      conductorConfig,
+      conductorConfig,
+    ]);
+
+    const [[alice_happ]] = await alice.installAgentsHapps(installation.one);
+    const [[bobby_happ]] = await bob.installAgentsHapps(installation.one);
+
+    const alice_cell = alice_happ.cells[0];
+    const bobby_cell = bobby_happ.cells[0];
+
+    const agent_pubkey_alice = alice_happ.agent;
+    const agent_pubkey_bobby = bobby_happ.agent;
+
+    let reactionsFromSignal: any[] = [];
+    alice.setSignalHandler((signal) => {
+      if (signal.data.payload.name === "SYNC_P2P_REACTIONS")
+        reactionsFromSignal.push(signal.data.payload.payload);
+    });
+
+    const [message_hash] = await sendMessage({
+      receiver: agent_pubkey_bobby,
+      payload: { type: "TEXT", payload: { payload: "Coffee later?" } },
+      replyTo: null,
+    })(alice_cell);
+    await delay(1000);
+
+    await reactToMessage(message_hash, "👍")(bobby_cell);
+    await delay(1000);
+    await reactToMessage(message_hash, "👍")(alice_cell);
+    await delay(1000);
+    t.equal(reactionsFromSignal.length, 1);
+
+    // the same agent cannot react twice with the same emoji
+    try {
+      await reactToMessage(message_hash, "👍")(bobby_cell);
+      t.fail();
+    } catch (e) {
+      t.ok(e);
+    }
+
+    const key = serializeHash(message_hash);
+    let alice_latest = await getLatestMessages(5)(alice_cell);
+    t.deepEqual(alice_latest[1][key][0].reactions["👍"].length, 2);
+
+    await removeReaction(message_hash, "👍")(bobby_cell);
+    await delay(1000);
+
+    alice_latest = await getLatestMessages(5)(alice_cell);
+    t.deepEqual(alice_latest[1][key][0].reactions["👍"], [agent_pubkey_alice]);
+  });
+
+  orchestrator.run();
+};
+
+export default reactions;
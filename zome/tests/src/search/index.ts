@@ -0,0 +1,151 @@
+import { Orchestrator, Player } from "@holochain/tryorama";
+import { Installables } from "../types";
+import { delay, serializeHash } from "../utils";
+
+function sendMessage(message) {
+  return (conductor) => conductor.call("p2pmessage", "send_message", message);
+}
+
+function searchMessages(filter) {
+  return (conductor) =>
+    conductor.call("p2pmessage", "search_messages", {
+      conversant: null,
+      payload_filter: {},
+      from: null,
+      until: null,
+      ...filter,
+    });
+}
+
+function enableEncryption(conversant) {
+  return (conductor) =>
+    conductor.call("p2pmessage", "enable_encryption", conversant);
+}
+
+function textMessage(receiver, text) {
+  return {
+    receiver: receiver,
+    payload: { type: "TEXT", payload: { payload: text } },
+    replyTo: null,
+  };
+}
+
+const search = async (conductorConfig, installation: Installables) => {
+  let orchestrator = new Orchestrator();
+
+  orchestrator.registerScenario("Search messages", async (s, t) => {
+    const [alice, bob, charlie]: Player[] = await s.players([
+      conductorConfig,
+      conductorConfig,
+      conductorConfig,
+    ]);
+
+    const [[alice_happ]] = await alice.installAgentsHapps(installation.one);
+    const [[bobby_happ]] = await bob.installAgentsHapps(installation.one);
+    const [[charlie_happ]] = await charlie.installAgentsHapps(
+      installation.one
+    );
+
+    const alice_cell = alice_happ.cells[0];
+    const bobby_cell = bobby_happ.cells[0];
+
+    const agent_pubkey_bobby = bobby_happ.agent;
+    const agent_pubkey_charlie = charlie_happ.agent;
+
+    await sendMessage(
+      textMessage(agent_pubkey_bobby, "Lunch at the ramen place?")
+    )(alice_cell);
+    await sendMessage(
+      textMessage(agent_pubkey_bobby, "The train is late again")
+    )(alice_cell);
+    await sendMessage(
+      textMessage(agent_pubkey_charlie, "Ramen tonight?")
+    )(alice_cell);
+    await sendMessage({
+      receiver: agent_pubkey_bobby,
+      payload: {
+        type: "FILE",
+        payload: {
+          metadata: {
+            fileName: "ramen-menu.txt",
+            fileSize: 16,
+            fileType: "text/plain",
+          },
+          fileType: { type: "OTHER" },
+          fileBytes: Buffer.alloc(16, 1),
+        },
+      },
+      replyTo: null,
+    })(alice_cell);
+    await delay(2000);
+
+    // words are matched case-insensitively by their beginning
+    const [ramen_messages, ramen_contents] = await searchMessages({
+      query: "RAM",
+    })(alice_cell);
+    t.equal(Object.keys(ramen_contents).length, 3);
+    t.equal(ramen_messages[serializeHash(agent_pubkey_bobby)].length, 2);
+    t.equal(ramen_messages[serializeHash(agent_pubkey_charlie)].length, 1);
+
+    // filtered by conversant and payload type
+    const [_, bobby_text_contents] = await searchMessages({
+      query: "ramen",
+      conversant: agent_pubkey_bobby,
+      payload_filter: { kinds: ["TEXT"] },
+    })(alice_cell);
+    t.equal(Object.keys(bobby_text_contents).length, 1);
+
+    // every word of the query has to be found
+    const [__, train_contents] = await searchMessages({
+      query: "late train",
+    })(bobby_cell);
+    t.equal(Object.keys(train_contents).length, 1);
+
+    const [___, no_contents] = await searchMessages({
+      query: "sushi",
+    })(alice_cell);
+    t.equal(Object.keys(no_contents).length, 0);
+  });
+
+  orchestrator.registerScenario("Search encrypted messages", async (s, t) => {
+    const [alice, bob]: Player[] = await s.players([
+      conductorConfig,
+      conductorConfig,
+    ]);
+
+    const [[alice_happ]] = await alice.installAgentsHapps(installation.one);
+    const [[bobby_happ]] = await bob.installAgentsHapps(installation.one);
+
+    const alice_cell = alice_happ.cells[0];
+    const bobby_cell = bobby_happ.cells[0];
+
+    const agent_pubkey_bobby = bobby_happ.agent;
+
+    await enableEncryption(agent_pubkey_bobby)(alice_cell);
+    await sendMessage(
+      textMessage(agent_pubkey_bobby, "The restaurant opens tomorrow")
+    )(alice_cell);
+    await delay(2000);
+
+    // both sides find the words once decrypted
+    const [_, alice_contents] = await searchMessages({
+      query: "restaurant",
+    })(alice_cell);
+    t.equal(Object.keys(alice_contents).length, 1);
+
+    const [__, bobby_contents] = await searchMessages({
+      query: "tomor",
+    })(bobby_cell);
+    t.equal(Object.keys(bobby_contents).length, 1);
+
+    // long words are looked up by their first letters and then matched in full
+    const [___, no_contents] = await searchMessages({
+      query: "restaurateur",
+    })(alice_cell);
+    t.equal(Object.keys(no_contents).length, 0);
+  });
+
+  orchestrator.run();
+};
+
+export default search;
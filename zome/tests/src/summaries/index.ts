@@ -0,0 +1,101 @@
+import { Orchestrator, Player } from "@holochain/tryorama";
+import { Installables } from "../types";
+import { delay, dateToTimestamp, serializeHash } from "../utils";
+
+function sendMessage(message) {
+  return (conductor) => conductor.call("p2pmessage", "send_message", message);
+}
+
+function readMessage(read_receipt_input) {
+  return (conductor) =>
+    conductor.call("p2pmessage", "read_message", read_receipt_input);
+}
+
+function getConversationSummaries() {
+  return (conductor) =>
+    conductor.call("p2pmessage", "get_conversation_summaries", false);
+}
+
+function textMessage(receiver, text) {
+  return {
+    receiver: receiver,
+    payload: { type: "TEXT", payload: { payload: text } },
+    replyTo: null,
+  };
+}
+
+const summaryOf = (summaries, agent) =>
+  summaries.find(
+    (summary) => serializeHash(summary.conversant) === serializeHash(agent)
+  );
+
+const summaries = async (conductorConfig, installation: Installables) => {
+  let orchestrator = new Orchestrator();
+
+  orchestrator.registerScenario("Conversation summaries", async (s, t) => {
+    const [alice, bob]: Player[] = await s.players([
+      conductorConfig,
+      conductorConfig,
+    ]);
+
+    const [[alice_happ]] = await alice.installAgentsHapps(installation.one);
+    const [[bobby_happ]] = await bob.installAgentsHapps(installation.one);
+
+    const alice_cell = alice_happ.cells[0];
+    const bobby_cell = bobby_happ.cells[0];
+
+    const agent_pubkey_alice = alice_happ.agent;
+    const agent_pubkey_bobby = bobby_happ.agent;
+
+    const [hello_hash] = await sendMessage(
+      textMessage(agent_pubkey_bobby, "Hello, Bobby")
+    )(alice_cell);
+    await delay(1000);
+    const [coffee_hash] = await sendMessage(
+      textMessage(agent_pubkey_bobby, "Coffee today?")
+    )(alice_cell);
+    await delay(1000);
+    await sendMessage(textMessage(agent_pubkey_alice, "Hi, Alice!"))(
+      bobby_cell
+    );
+    await delay(2000);
+
+    const bobby_unread = summaryOf(
+      await getConversationSummaries()(bobby_cell),
+      agent_pubkey_alice
+    );
+    t.equal(bobby_unread.unreadCount, 2);
+    t.equal(bobby_unread.lastMessage[1].payload.payload.payload, "Hi, Alice!");
+
+    const alice_unread = summaryOf(
+      await getConversationSummaries()(alice_cell),
+      agent_pubkey_bobby
+    );
+    t.equal(alice_unread.unreadCount, 1);
+    t.equal(alice_unread.lastOutgoingRead, false);
+
+    await readMessage({
+      message_hashes: [hello_hash, coffee_hash],
+      sender: agent_pubkey_alice,
+      timestamp: dateToTimestamp(new Date()),
+    })(bobby_cell);
+    await delay(2000);
+
+    const bobby_read = summaryOf(
+      await getConversationSummaries()(bobby_cell),
+      agent_pubkey_alice
+    );
+    t.equal(bobby_read.unreadCount, 0);
+
+    const alice_read = summaryOf(
+      await getConversationSummaries()(alice_cell),
+      agent_pubkey_bobby
+    );
+    t.equal(alice_read.lastOutgoingRead, true);
+    t.equal(alice_read.pinnedCount, 0);
+  });
+
+  orchestrator.run();
+};
+
+export default summaries;
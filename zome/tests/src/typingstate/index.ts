@@ -0,0 +1,105 @@
+import { Orchestrator, Player } from "@holochain/tryorama";
+import { Installables } from "../types";
+import { delay, serializeHash } from "../utils";
+
+function typing(typing_info) {
+  return (conductor) => conductor.call("p2pmessage", "typing", typing_info);
+}
+
+function getTypingStates() {
+  return (conductor) =>
+    conductor.call("p2pmessage", "get_typing_states", null);
+}
+
+const typingState = async (conductorConfig, installation: Installables) => {
+  let orchestrator = new Orchestrator();
+
+  orchestrator.registerScenario("Typing indicators expire", async (s, t) => {
+    const [alice, bob]: Player[] = await s.players([
+      conductorConfig,
+      conductorConfig,
+    ]);
+
+    const [[alice_happ]] = await alice.installAgentsHapps(installation.one);
+    const [[bobby_happ]] = await bob.installAgentsHapps(installation.one);
+
+    const alice_cell = alice_happ.cells[0];
+    const bobby_cell = bobby_happ.cells[0];
+
+    const agent_pubkey_alice = alice_happ.agent;
+    const agent_pubkey_bobby = bobby_happ.agent;
+
+    let typingSignals: any[] = [];
+    bob.setSignalHandler((signal) => {
+      if (signal.data.payload.name === "TYPING_P2P")
+        typingSignals.push(signal.data.payload.payload);
+    });
+
+    // the second call renews the same state within the throttle and is not sent
+    await typing({ agent: agent_pubkey_bobby, isTyping: true })(alice_cell);
+    await typing({ agent: agent_pubkey_bobby, isTyping: true })(alice_cell);
+    await delay(1000);
+
+    t.equal(typingSignals.length, 1);
+    t.equal(
+      serializeHash(typingSignals[0].agent),
+      serializeHash(agent_pubkey_alice)
+    );
+    t.equal(typingSignals[0].is_typing, true);
+    t.equal(typingSignals[0].ttl, 10000);
+    t.ok(typingSignals[0].timestamp);
+
+    const [typing_state] = await getTypingStates()(bobby_cell);
+    t.equal(
+      serializeHash(typing_state.agent),
+      serializeHash(agent_pubkey_alice)
+    );
+    t.equal(typing_state.isTyping, true);
+    t.ok(typing_state.lastSeenTyping);
+
+    // once the throttle passed the same state is sent again and renews the indicator
+    await delay(5000);
+    await typing({ agent: agent_pubkey_bobby, isTyping: true })(alice_cell);
+    await delay(1000);
+
+    t.equal(typingSignals.length, 2);
+    t.equal(typingSignals[1].is_typing, true);
+
+    const [renewed_state] = await getTypingStates()(bobby_cell);
+    t.equal(renewed_state.isTyping, true);
+    t.ok(renewed_state.lastSeenTyping > typing_state.lastSeenTyping);
+
+    // a stop is a change of state and is sent right away
+    await typing({ agent: agent_pubkey_bobby, isTyping: false })(alice_cell);
+    await delay(1000);
+
+    t.equal(typingSignals.length, 3);
+    t.equal(typingSignals[2].is_typing, false);
+
+    const [stopped_state] = await getTypingStates()(bobby_cell);
+    t.equal(stopped_state.isTyping, false);
+    t.equal(stopped_state.lastSeenTyping, renewed_state.lastSeenTyping);
+
+    // starting again right after waits for the throttle as well
+    await typing({ agent: agent_pubkey_bobby, isTyping: true })(alice_cell);
+    await delay(1000);
+    t.equal(typingSignals.length, 3);
+
+    await delay(4000);
+    await typing({ agent: agent_pubkey_bobby, isTyping: true })(alice_cell);
+    await delay(1000);
+
+    t.equal(typingSignals.length, 4);
+    t.equal(typingSignals[3].is_typing, true);
+
+    // a second stop shows nothing new and is not sent
+    await typing({ agent: agent_pubkey_bobby, isTyping: false })(alice_cell);
+    await typing({ agent: agent_pubkey_bobby, isTyping: false })(alice_cell);
+    await delay(1000);
+    t.equal(typingSignals.length, 5);
+  });
+
+  orchestrator.run();
+};
+
+export default typingState;
@@ -0,0 +1,75 @@
+import { Orchestrator, Player } from "@holochain/tryorama";
+import { Installables } from "../types";
+import { delay, serializeHash } from "../utils";
+
+function sendMessage(message) {
+  return (conductor) => conductor.call("p2pmessage", "send_message", message);
+}
+
+function getUndelivered() {
+  return (conductor) => conductor.call("p2pmessage", "get_undelivered", null);
+}
+
+function retryDelivery(message_hashes, receipt_hashes) {
+  return (conductor) =>
+    conductor.call("p2pmessage", "retry_delivery", {
+      message_hashes,
+      receipt_hashes,
+    });
+}
+
+const undelivered = async (conductorConfig, installation: Installables) => {
+  let orchestrator = new Orchestrator();
+
+  orchestrator.registerScenario("Retry undelivered messages", async (s, t) => {
+    const [alice, bob]: Player[] = await s.players([
+      conductorConfig,
+      conductorConfig,
+    ]);
+
+    const [[alice_happ]] = await alice.installAgentsHapps(installation.one);
+    const [[bobby_happ]] = await bob.installAgentsHapps(installation.one);
+
+    const alice_cell = alice_happ.cells[0];
+    const agent_pubkey_bobby = bobby_happ.agent;
+
+    await bob.shutdown();
+
+    const [message_hash] = await sendMessage({
+      receiver: agent_pubkey_bobby,
+      payload: { type: "TEXT", payload: { payload: "Are you there?" } },
+      replyTo: null,
+    })(alice_cell);
+    await delay(2000);
+
+    const undelivered_offline = await getUndelivered()(alice_cell);
+    const queued = undelivered_offline.messages.find(
+      (message) =>
+        serializeHash(message.messageHash) === serializeHash(message_hash)
+    );
+    t.ok(queued);
+    t.equal(queued.status.outboxstatus, "pending");
+
+    await bob.startup();
+    await delay(4000);
+
+    const results = await retryDelivery([message_hash], [])(alice_cell);
+    const result = results[serializeHash(message_hash)];
+    t.equal(result.type, "STATUS");
+    t.equal(result.payload.outboxstatus, "delivered");
+    await delay(2000);
+
+    // the delivered receipt from bobby takes the message off the list
+    const undelivered_online = await getUndelivered()(alice_cell);
+    t.notOk(
+      undelivered_online.messages.find(
+        (message) =>
+          serializeHash(message.messageHash) === serializeHash(message_hash)
+      )
+    );
+  });
+
+  orchestrator.run();
+};
+
+export default undelivered;
@@ -1 +1,2 @@
-pub mod message;
\ No newline at end of file
+pub mod group;
+pub mod message;
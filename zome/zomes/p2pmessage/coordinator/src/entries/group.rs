@@ -0,0 +1,12 @@
+pub mod add_group_member;
+pub mod create_group;
+pub mod get_group_messages;
+pub mod get_groups;
+pub mod helpers;
+pub mod leave_group;
+pub mod read_group_messages;
+pub mod receive_group_membership;
+pub mod receive_group_message;
+pub mod receive_group_receipt;
+pub mod remove_group_member;
+pub mod send_group_message;
@@ -0,0 +1,41 @@
+use hdk::prelude::*;
+
+use p2pmessage_coordinator_types::*;
+use p2pmessage_integrity_types::*;
+
+use crate::utils::error;
+
+use super::helpers::{commit_and_share_membership, get_group_from_chain, get_group_members};
+
+pub fn add_group_member_handler(group_member_input: GroupMemberInput) -> ExternResult<GroupData> {
+    let me = agent_info()?.agent_latest_pubkey;
+    let group = get_group_from_chain(group_member_input.group_id.clone())?;
+    let mut members = get_group_members(group_member_input.group_id.clone())?;
+
+    if !members.contains(&me) {
+        return error(P2PMessageError::Unauthorized(String::from(
+            "Sorry. Only members of a group can add new members.",
+        )));
+    }
+
+    if members.contains(&group_member_input.member) {
+        return error(P2PMessageError::ValidationFailed(String::from(
+            "Sorry. This agent is already a member of the group.",
+        )));
+    }
+
+    members.push(group_member_input.member.clone());
+
+    let membership = P2PGroupMembership {
+        group_id: group_member_input.group_id,
+        actor: me,
+        change: MembershipChange::AddMember {
+            member: group_member_input.member,
+        },
+        members: members.clone(),
+        timestamp: sys_time()?,
+    };
+
+    // the new member receives the full member list with this change
+    commit_and_share_membership(group, membership, members)
+}
@@ -0,0 +1,52 @@
+use hdk::prelude::*;
+
+use p2pmessage_coordinator_types::*;
+use p2pmessage_integrity_types::*;
+
+use crate::{entries::message::utils::this_zome_index, utils::error};
+
+use super::helpers::commit_and_share_membership;
+
+pub fn create_group_handler(create_group_input: CreateGroupInput) -> ExternResult<GroupData> {
+    let me = agent_info()?.agent_latest_pubkey;
+
+    if create_group_input.name.trim().is_empty() {
+        return error(P2PMessageError::ValidationFailed(String::from(
+            "Sorry. A group needs a name.",
+        )));
+    }
+
+    let mut members: Vec<AgentPubKey> = vec![me.clone()];
+    for member in create_group_input.members.into_iter() {
+        if !members.contains(&member) {
+            members.push(member);
+        }
+    }
+
+    let group = P2PGroup {
+        creator: me.clone(),
+        name: create_group_input.name,
+        time_created: sys_time()?,
+    };
+
+    let group_entry = Entry::App(group.clone().try_into()?);
+    host_call::<CreateInput, ActionHash>(
+        __hc__create_1,
+        CreateInput::new(
+            EntryDefLocation::app(this_zome_index()?, 9),
+            EntryVisibility::Private,
+            group_entry,
+            ChainTopOrdering::Relaxed,
+        ),
+    )?;
+
+    let membership = P2PGroupMembership {
+        group_id: hash_entry(&group)?,
+        actor: me,
+        change: MembershipChange::Create,
+        members: members.clone(),
+        timestamp: group.time_created,
+    };
+
+    commit_and_share_membership(group, membership, members)
+}
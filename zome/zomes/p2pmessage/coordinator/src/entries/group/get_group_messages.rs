@@ -0,0 +1,103 @@
+use hdk::prelude::*;
+use std::collections::HashMap;
+
+use p2pmessage_coordinator_types::*;
+use p2pmessage_integrity_types::*;
+
+use crate::entries::message::utils::this_zome_index;
+
+use super::helpers::{get_group_receipts, insert_group_message};
+
+pub fn get_latest_group_messages_handler(batch_size: u8) -> ExternResult<GroupMessageHashTables> {
+    let mut group_messages: HashMap<String, Vec<String>> = HashMap::new();
+    let mut message_contents: HashMap<String, (GroupMessageData, Vec<String>)> = HashMap::new();
+    let mut receipt_contents: HashMap<String, P2PGroupMessageReceipt> = HashMap::new();
+
+    for message in query_group_messages()?.into_iter() {
+        if let Ok(message_entry) = TryInto::<P2PGroupMessage>::try_into(message) {
+            let batch_full = match group_messages.get(&message_entry.group_id.to_string()) {
+                Some(messages) => messages.len() >= batch_size.into(),
+                None => false,
+            };
+            if batch_full {
+                continue;
+            }
+
+            let message_hash = hash_entry(&message_entry)?;
+            insert_group_message(
+                &mut group_messages,
+                &mut message_contents,
+                message_entry,
+                message_hash,
+            );
+        }
+    }
+
+    get_group_receipts(&mut message_contents, &mut receipt_contents)?;
+
+    Ok(GroupMessageHashTables(
+        group_messages,
+        message_contents,
+        receipt_contents,
+    ))
+}
+
+pub fn get_previous_group_messages_handler(
+    filter: GroupMessageFilterBatch,
+) -> ExternResult<GroupMessageHashTables> {
+    let mut group_messages: HashMap<String, Vec<String>> = HashMap::new();
+    group_messages.insert(filter.group_id.clone().to_string(), Vec::new());
+    let mut message_contents: HashMap<String, (GroupMessageData, Vec<String>)> = HashMap::new();
+    let mut receipt_contents: HashMap<String, P2PGroupMessageReceipt> = HashMap::new();
+
+    let filter_timestamp = match filter.last_fetched_timestamp {
+        Some(timestamp) => timestamp,
+        None => sys_time()?,
+    };
+
+    for message in query_group_messages()?.into_iter() {
+        if let Ok(message_entry) = TryInto::<P2PGroupMessage>::try_into(message) {
+            let message_hash = hash_entry(&message_entry)?;
+
+            if message_entry.group_id == filter.group_id
+                && message_entry.time_sent <= filter_timestamp
+                && filter.last_fetched_message_id.as_ref() != Some(&message_hash)
+            {
+                let current_batch_size = insert_group_message(
+                    &mut group_messages,
+                    &mut message_contents,
+                    message_entry,
+                    message_hash,
+                );
+
+                if current_batch_size >= filter.batch_size.into() {
+                    break;
+                }
+            }
+        }
+    }
+
+    get_group_receipts(&mut message_contents, &mut receipt_contents)?;
+
+    Ok(GroupMessageHashTables(
+        group_messages,
+        message_contents,
+        receipt_contents,
+    ))
+}
+
+// newest first
+fn query_group_messages() -> ExternResult<Vec<Record>> {
+    let mut queried_messages: Vec<Record> = query(
+        QueryFilter::new()
+            .entry_type(EntryType::App(AppEntryDef::new(
+                EntryDefIndex::from(11),
+                this_zome_index()?,
+                EntryVisibility::Private,
+            )))
+            .include_entries(true),
+    )?;
+    queried_messages.reverse();
+
+    Ok(queried_messages)
+}
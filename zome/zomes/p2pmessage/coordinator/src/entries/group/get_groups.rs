@@ -0,0 +1,29 @@
+use hdk::prelude::*;
+
+use p2pmessage_coordinator_types::*;
+
+use super::helpers::{get_group_from_chain, get_memberships_from_chain};
+
+pub fn get_groups_handler() -> ExternResult<Vec<GroupData>> {
+    let me = agent_info()?.agent_latest_pubkey;
+
+    let mut groups: Vec<GroupData> = Vec::new();
+    for (_, membership) in get_memberships_from_chain()?.into_iter() {
+        // groups this agent left or was removed from are not listed
+        if !membership.members.contains(&me) {
+            continue;
+        }
+
+        let group = get_group_from_chain(membership.group_id.clone())?;
+        groups.push(GroupData {
+            group_id: membership.group_id,
+            name: group.name,
+            creator: group.creator,
+            members: membership.members,
+            time_created: group.time_created,
+        });
+    }
+    groups.sort_by_key(|group| std::cmp::Reverse(group.time_created));
+
+    Ok(groups)
+}
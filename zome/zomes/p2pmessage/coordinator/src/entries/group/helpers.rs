@@ -0,0 +1,223 @@
+use hdk::prelude::*;
+use std::collections::HashMap;
+
+use p2pmessage_coordinator_types::*;
+use p2pmessage_integrity_types::*;
+
+use crate::{
+    entries::message::utils::this_zome_index,
+    utils::{call_agent, error},
+};
+
+pub fn get_group_from_chain(group_id: EntryHash) -> ExternResult<P2PGroup> {
+    let queried_groups: Vec<Record> = query(
+        QueryFilter::new()
+            .entry_type(EntryType::App(AppEntryDef::new(
+                EntryDefIndex::from(9),
+                this_zome_index()?,
+                EntryVisibility::Private,
+            )))
+            .include_entries(true),
+    )?;
+
+    for group in queried_groups.into_iter() {
+        if let Ok(group_entry) = TryInto::<P2PGroup>::try_into(group) {
+            if hash_entry(&group_entry)? == group_id {
+                return Ok(group_entry);
+            }
+        }
+    }
+
+    error(P2PMessageError::NotFound(String::from(
+        "Sorry. Group not found.",
+    )))
+}
+
+// the membership change committed last for every group on this chain
+pub fn get_memberships_from_chain() -> ExternResult<HashMap<String, P2PGroupMembership>> {
+    let queried_memberships: Vec<Record> = query(
+        QueryFilter::new()
+            .entry_type(EntryType::App(AppEntryDef::new(
+                EntryDefIndex::from(10),
+                this_zome_index()?,
+                EntryVisibility::Private,
+            )))
+            .include_entries(true),
+    )?;
+
+    let mut memberships: HashMap<String, P2PGroupMembership> = HashMap::new();
+    for membership in queried_memberships.into_iter() {
+        if let Ok(membership_entry) = TryInto::<P2PGroupMembership>::try_into(membership) {
+            memberships.insert(
+                membership_entry.group_id.clone().to_string(),
+                membership_entry,
+            );
+        }
+    }
+
+    Ok(memberships)
+}
+
+pub fn get_group_members(group_id: EntryHash) -> ExternResult<Vec<AgentPubKey>> {
+    match get_memberships_from_chain()?.remove(&group_id.to_string()) {
+        Some(membership) => Ok(membership.members),
+        None => Ok(Vec::new()),
+    }
+}
+
+pub fn get_group_data(group_id: EntryHash) -> ExternResult<GroupData> {
+    let group = get_group_from_chain(group_id.clone())?;
+    let members = get_group_members(group_id.clone())?;
+
+    Ok(GroupData {
+        group_id,
+        name: group.name,
+        creator: group.creator,
+        members,
+        time_created: group.time_created,
+    })
+}
+
+pub fn get_group_message_from_chain(message_hash: EntryHash) -> ExternResult<P2PGroupMessage> {
+    let queried_messages: Vec<Record> = query(
+        QueryFilter::new()
+            .entry_type(EntryType::App(AppEntryDef::new(
+                EntryDefIndex::from(11),
+                this_zome_index()?,
+                EntryVisibility::Private,
+            )))
+            .include_entries(true),
+    )?;
+
+    for message in queried_messages.into_iter() {
+        if let Ok(message_entry) = TryInto::<P2PGroupMessage>::try_into(message) {
+            if hash_entry(&message_entry)? == message_hash {
+                return Ok(message_entry);
+            }
+        }
+    }
+
+    error(P2PMessageError::NotFound(String::from(
+        "Sorry. Group message not found.",
+    )))
+}
+
+pub fn commit_group_receipt(receipt: P2PGroupMessageReceipt) -> ExternResult<EntryHash> {
+    let receipt_entry = Entry::App(receipt.clone().try_into()?);
+    host_call::<CreateInput, ActionHash>(
+        __hc__create_1,
+        CreateInput::new(
+            EntryDefLocation::app(this_zome_index()?, 12),
+            EntryVisibility::Private,
+            receipt_entry,
+            ChainTopOrdering::Relaxed,
+        ),
+    )?;
+
+    hash_entry(&receipt)
+}
+
+/*
+ * commits a membership change and sends it to every agent in recipients,
+ * agents who cannot be reached pick up the member list with the next change
+ */
+pub fn commit_and_share_membership(
+    group: P2PGroup,
+    membership: P2PGroupMembership,
+    recipients: Vec<AgentPubKey>,
+) -> ExternResult<GroupData> {
+    let membership_entry = Entry::App(membership.clone().try_into()?);
+    host_call::<CreateInput, ActionHash>(
+        __hc__create_1,
+        CreateInput::new(
+            EntryDefLocation::app(this_zome_index()?, 10),
+            EntryVisibility::Private,
+            membership_entry,
+            ChainTopOrdering::Relaxed,
+        ),
+    )?;
+
+    let me = agent_info()?.agent_latest_pubkey;
+    let receive_input = ReceiveGroupMembershipInput {
+        group: group.clone(),
+        membership: membership.clone(),
+    };
+
+    for recipient in recipients.into_iter().filter(|recipient| *recipient != me) {
+        let zome_call_response: ZomeCallResponse = call_agent(
+            recipient.clone(),
+            "receive_group_membership",
+            &receive_input,
+        )?;
+
+        if let ZomeCallResponse::Ok(_) = zome_call_response {
+            continue;
+        }
+        debug!("receive_group_membership failed for {:?}", recipient);
+    }
+
+    Ok(GroupData {
+        group_id: membership.group_id,
+        name: group.name,
+        creator: group.creator,
+        members: membership.members,
+        time_created: group.time_created,
+    })
+}
+
+pub fn insert_group_message(
+    group_messages: &mut HashMap<String, Vec<String>>,
+    message_contents: &mut HashMap<String, (GroupMessageData, Vec<String>)>,
+    message_entry: P2PGroupMessage,
+    message_hash: EntryHash,
+) -> usize {
+    let key = message_entry.group_id.clone().to_string();
+    let messages = group_messages.entry(key).or_default();
+    messages.push(message_hash.clone().to_string());
+
+    let message_data = GroupMessageData {
+        group_id: message_entry.group_id,
+        author: message_entry.author,
+        payload: message_entry.payload,
+        time_sent: message_entry.time_sent,
+        reply_to: message_entry.reply_to,
+    };
+    message_contents.insert(message_hash.to_string(), (message_data, Vec::new()));
+
+    messages.len()
+}
+
+pub fn get_group_receipts(
+    message_contents: &mut HashMap<String, (GroupMessageData, Vec<String>)>,
+    receipt_contents: &mut HashMap<String, P2PGroupMessageReceipt>,
+) -> ExternResult<()> {
+    let queried_receipts: Vec<Record> = query(
+        QueryFilter::new()
+            .entry_type(EntryType::App(AppEntryDef::new(
+                EntryDefIndex::from(12),
+                this_zome_index()?,
+                EntryVisibility::Private,
+            )))
+            .include_entries(true),
+    )?;
+
+    for receipt in queried_receipts.into_iter() {
+        if let Ok(receipt_entry) = TryInto::<P2PGroupMessageReceipt>::try_into(receipt) {
+            let receipt_hash = hash_entry(&receipt_entry)?;
+            let mut belongs_to_message = false;
+
+            for message_id in receipt_entry.id.iter() {
+                if let Some(message_content) = message_contents.get_mut(&message_id.to_string()) {
+                    message_content.1.push(receipt_hash.clone().to_string());
+                    belongs_to_message = true;
+                }
+            }
+
+            if belongs_to_message {
+                receipt_contents.insert(receipt_hash.to_string(), receipt_entry);
+            }
+        }
+    }
+
+    Ok(())
+}
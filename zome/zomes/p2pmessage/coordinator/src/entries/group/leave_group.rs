@@ -0,0 +1,36 @@
+use hdk::prelude::*;
+
+use p2pmessage_coordinator_types::*;
+use p2pmessage_integrity_types::*;
+
+use crate::utils::error;
+
+use super::helpers::{commit_and_share_membership, get_group_from_chain, get_group_members};
+
+pub fn leave_group_handler(group_id: EntryHash) -> ExternResult<GroupData> {
+    let me = agent_info()?.agent_latest_pubkey;
+    let group = get_group_from_chain(group_id.clone())?;
+    let previous_members = get_group_members(group_id.clone())?;
+
+    if !previous_members.contains(&me) {
+        return error(P2PMessageError::Unauthorized(String::from(
+            "Sorry. You are not a member of this group.",
+        )));
+    }
+
+    let members: Vec<AgentPubKey> = previous_members
+        .clone()
+        .into_iter()
+        .filter(|member| *member != me)
+        .collect();
+
+    let membership = P2PGroupMembership {
+        group_id,
+        actor: me,
+        change: MembershipChange::Leave,
+        members: members.clone(),
+        timestamp: sys_time()?,
+    };
+
+    commit_and_share_membership(group, membership, members)
+}
@@ -0,0 +1,72 @@
+use hdk::prelude::*;
+use std::collections::HashMap;
+
+use p2pmessage_coordinator_types::*;
+use p2pmessage_integrity_types::*;
+
+use crate::utils::{call_agent, error};
+
+use super::helpers::{commit_group_receipt, get_group_members, get_group_message_from_chain};
+
+pub fn read_group_messages_handler(
+    read_group_message_input: ReadGroupMessageInput,
+) -> ExternResult<HashMap<String, P2PGroupMessageReceipt>> {
+    let me = agent_info()?.agent_latest_pubkey;
+    let group_id = read_group_message_input.group_id;
+
+    if !get_group_members(group_id.clone())?.contains(&me) {
+        return error(P2PMessageError::Unauthorized(String::from(
+            "Sorry. You are not a member of this group.",
+        )));
+    }
+
+    // every author gets a read receipt for the messages they wrote
+    let mut authors: Vec<(AgentPubKey, Vec<EntryHash>)> = Vec::new();
+    for message_hash in read_group_message_input.message_hashes.iter() {
+        let message = get_group_message_from_chain(message_hash.clone())?;
+        if message.group_id != group_id {
+            return error(P2PMessageError::ValidationFailed(String::from(
+                "Sorry. The message does not belong to this group.",
+            )));
+        }
+        if message.author == me {
+            continue;
+        }
+        match authors
+            .iter_mut()
+            .find(|(author, _)| *author == message.author)
+        {
+            Some((_, message_hashes)) => message_hashes.push(message_hash.clone()),
+            None => authors.push((message.author, vec![message_hash.clone()])),
+        }
+    }
+
+    let receipt = P2PGroupMessageReceipt {
+        id: read_group_message_input.message_hashes,
+        group_id,
+        member: me,
+        status: Status::Read {
+            timestamp: read_group_message_input.timestamp,
+        },
+    };
+    let receipt_hash = commit_group_receipt(receipt.clone())?;
+
+    for (author, message_hashes) in authors.into_iter() {
+        let author_receipt = P2PGroupMessageReceipt {
+            id: message_hashes,
+            ..receipt.clone()
+        };
+        let zome_call_response: ZomeCallResponse =
+            call_agent(author.clone(), "receive_group_receipt", &author_receipt)?;
+
+        if let ZomeCallResponse::Ok(_) = zome_call_response {
+            continue;
+        }
+        debug!("receive_group_receipt failed for {:?}", author);
+    }
+
+    let mut receipt_contents: HashMap<String, P2PGroupMessageReceipt> = HashMap::new();
+    receipt_contents.insert(receipt_hash.to_string(), receipt);
+
+    Ok(receipt_contents)
+}
@@ -0,0 +1,124 @@
+use hdk::prelude::*;
+
+use p2pmessage_coordinator_types::*;
+use p2pmessage_integrity_types::*;
+
+use crate::{
+    entries::message::utils::this_zome_index, is_user_blocked::is_user_blocked, utils::error,
+};
+
+use super::helpers::{get_group_from_chain, get_group_members};
+
+pub fn receive_group_membership_handler(input: ReceiveGroupMembershipInput) -> ExternResult<()> {
+    let caller = call_info()?.provenance;
+    let me = agent_info()?.agent_latest_pubkey;
+    let group_id = input.membership.group_id.clone();
+
+    // membership changes from blocked agents are silently dropped
+    if is_user_blocked(caller.clone())? {
+        return Ok(());
+    }
+
+    if input.membership.actor != caller || hash_entry(&input.group)? != group_id {
+        return error(P2PMessageError::Unauthorized(String::from(
+            "Sorry. Membership changes can only be sent by the agent who made them.",
+        )));
+    }
+
+    let members = if get_group_from_chain(group_id.clone()).is_ok() {
+        // the change is applied to the members we know of, not to the list of the caller
+        let mut members = get_group_members(group_id.clone())?;
+        if !members.contains(&caller) {
+            return error(P2PMessageError::Unauthorized(String::from(
+                "Sorry. Only members of a group can change its members.",
+            )));
+        }
+
+        match input.membership.change {
+            MembershipChange::Create => {
+                return error(P2PMessageError::ValidationFailed(String::from(
+                    "Sorry. This group already exists.",
+                )))
+            }
+            MembershipChange::AddMember { ref member } => {
+                if !members.contains(member) {
+                    members.push(member.clone());
+                }
+            }
+            MembershipChange::RemoveMember { ref member } => {
+                if input.group.creator != caller {
+                    return error(P2PMessageError::Unauthorized(String::from(
+                        "Sorry. Only the creator of a group can remove members.",
+                    )));
+                }
+                members.retain(|agent| agent != member);
+            }
+            MembershipChange::Leave => members.retain(|agent| *agent != caller),
+        }
+        members
+    } else {
+        // the first change this agent hears about is the one that added them, so the
+        // member list of the caller is the only one there is
+        let adds_me = match input.membership.change {
+            MembershipChange::Create => input.group.creator == caller,
+            MembershipChange::AddMember { ref member } => *member == me,
+            _ => false,
+        };
+        if !adds_me
+            || !input.membership.members.contains(&me)
+            || !input.membership.members.contains(&caller)
+        {
+            return error(P2PMessageError::Unauthorized(String::from(
+                "Sorry. You are not a member of this group.",
+            )));
+        }
+
+        let group_entry = Entry::App(input.group.clone().try_into()?);
+        host_call::<CreateInput, ActionHash>(
+            __hc__create_1,
+            CreateInput::new(
+                EntryDefLocation::app(this_zome_index()?, 9),
+                EntryVisibility::Private,
+                group_entry,
+                ChainTopOrdering::Relaxed,
+            ),
+        )?;
+        input.membership.members.clone()
+    };
+
+    // changes are ordered by when they were committed here, the sender's clock is not used
+    let membership = P2PGroupMembership {
+        members: members.clone(),
+        timestamp: sys_time()?,
+        ..input.membership
+    };
+
+    let membership_entry = Entry::App(membership.try_into()?);
+    host_call::<CreateInput, ActionHash>(
+        __hc__create_1,
+        CreateInput::new(
+            EntryDefLocation::app(this_zome_index()?, 10),
+            EntryVisibility::Private,
+            membership_entry,
+            ChainTopOrdering::Relaxed,
+        ),
+    )?;
+
+    let signal = Signal::GroupMembership(GroupMembershipSignal {
+        group: GroupData {
+            group_id,
+            name: input.group.name,
+            creator: input.group.creator,
+            members,
+            time_created: input.group.time_created,
+        },
+    });
+
+    let signal_details = SignalDetails {
+        name: "RECEIVE_GROUP_MEMBERSHIP".to_string(),
+        payload: signal,
+    };
+    emit_signal(&signal_details)?;
+
+    Ok(())
+}
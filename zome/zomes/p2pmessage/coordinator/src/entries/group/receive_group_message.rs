@@ -0,0 +1,102 @@
+use hdk::prelude::*;
+
+use p2pmessage_coordinator_types::*;
+use p2pmessage_integrity_types::*;
+
+use crate::{
+    entries::message::utils::this_zome_index, is_user_blocked::is_user_blocked,
+    rate_limits::check_message_rate, utils::error, verify_file::received_file_error,
+};
+
+use super::helpers::{commit_group_receipt, get_group_members, get_group_message_from_chain};
+
+pub fn receive_group_message_handler(
+    input: ReceiveGroupMessageInput,
+) -> ExternResult<P2PGroupMessageReceipt> {
+    let caller = call_info()?.provenance;
+    let me = agent_info()?.agent_latest_pubkey;
+    let message_hash = hash_entry(&input.message)?;
+
+    let receipt = P2PGroupMessageReceipt {
+        id: vec![message_hash.clone()],
+        group_id: input.message.group_id.clone(),
+        member: me.clone(),
+        status: Status::Delivered {
+            timestamp: sys_time()?,
+        },
+    };
+
+    // the message is already on this chain, or the author is blocked
+    if get_group_message_from_chain(message_hash.clone()).is_ok()
+        || is_user_blocked(caller.clone())?
+    {
+        return Ok(receipt);
+    }
+
+    let members = get_group_members(input.message.group_id.clone())?;
+    if input.message.author != caller || !members.contains(&caller) || !members.contains(&me) {
+        return error(P2PMessageError::Unauthorized(String::from(
+            "Sorry. Only members of a group can send messages to it.",
+        )));
+    }
+    check_message_rate(&caller, &input.message.payload)?;
+
+    // the file is only kept when it is the one described by the message
+    let file = match &input.message.payload {
+        Payload::File { metadata, .. } => {
+            if let Some(reason) = received_file_error(metadata, input.file.as_ref())? {
+                return error(P2PMessageError::InvalidFile(reason.to_string()));
+            }
+            input.file
+        }
+        _ => None,
+    };
+
+    let message_entry = Entry::App(input.message.clone().try_into()?);
+    host_call::<CreateInput, ActionHash>(
+        __hc__create_1,
+        CreateInput::new(
+            EntryDefLocation::app(this_zome_index()?, 11),
+            EntryVisibility::Private,
+            message_entry,
+            ChainTopOrdering::Relaxed,
+        ),
+    )?;
+
+    if let Some(file) = file {
+        let file_entry = Entry::App(file.try_into()?);
+        host_call::<CreateInput, ActionHash>(
+            __hc__create_1,
+            CreateInput::new(
+                EntryDefLocation::app(this_zome_index()?, 3),
+                EntryVisibility::Private,
+                file_entry,
+                ChainTopOrdering::Relaxed,
+            ),
+        )?;
+    }
+
+    let receipt_hash = commit_group_receipt(receipt.clone())?;
+
+    let signal = Signal::GroupMessage(GroupMessageSignal {
+        message: (
+            message_hash,
+            GroupMessageData {
+                group_id: input.message.group_id,
+                author: input.message.author,
+                payload: input.message.payload,
+                time_sent: input.message.time_sent,
+                reply_to: input.message.reply_to,
+            },
+        ),
+        receipt: (receipt_hash, receipt.clone()),
+    });
+
+    let signal_details = SignalDetails {
+        name: "RECEIVE_GROUP_MESSAGE".to_string(),
+        payload: signal,
+    };
+    emit_signal(&signal_details)?;
+
+    Ok(receipt)
+}
@@ -0,0 +1,60 @@
+use hdk::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+use p2pmessage_coordinator_types::*;
+use p2pmessage_integrity_types::*;
+
+use crate::{chain_index::query_by_hashes, is_user_blocked::is_user_blocked, utils::error};
+
+use super::helpers::{commit_group_receipt, get_group_members};
+
+pub fn receive_group_receipt_handler(
+    receipt: P2PGroupMessageReceipt,
+) -> ExternResult<HashMap<String, P2PGroupMessageReceipt>> {
+    let caller = call_info()?.provenance;
+
+    // receipts from blocked agents are silently dropped
+    if is_user_blocked(caller.clone())? {
+        return Ok(HashMap::new());
+    }
+
+    if receipt.member != caller || !get_group_members(receipt.group_id.clone())?.contains(&caller) {
+        return error(P2PMessageError::Unauthorized(String::from(
+            "Sorry. Receipts can only be sent by the member who read the messages.",
+        )));
+    }
+
+    // every message of the receipt has to be one this agent sent to the group
+    let message_hashes: HashSet<EntryHash> = receipt.id.iter().cloned().collect();
+    let me = agent_info()?.agent_latest_pubkey;
+    let mut sent_messages: HashSet<EntryHash> = HashSet::new();
+    for message in query_by_hashes(11, message_hashes.clone())?.into_iter() {
+        if let Ok(message_entry) = TryInto::<P2PGroupMessage>::try_into(message) {
+            if message_entry.author == me && message_entry.group_id == receipt.group_id {
+                sent_messages.insert(hash_entry(&message_entry)?);
+            }
+        }
+    }
+    if message_hashes.is_empty() || sent_messages != message_hashes {
+        return error(P2PMessageError::Unauthorized(String::from(
+            "Sorry. Receipts can only be sent for messages sent to the group.",
+        )));
+    }
+
+    let receipt_hash = commit_group_receipt(receipt.clone())?;
+
+    let mut receipt_contents: HashMap<String, P2PGroupMessageReceipt> = HashMap::new();
+    receipt_contents.insert(receipt_hash.to_string(), receipt);
+
+    let signal = Signal::GroupMessageReceipt(GroupReceiptSignal {
+        receipt: receipt_contents.clone(),
+    });
+
+    let signal_details = SignalDetails {
+        name: "RECEIVE_GROUP_RECEIPT".to_string(),
+        payload: signal,
+    };
+    emit_signal(&signal_details)?;
+
+    Ok(receipt_contents)
+}
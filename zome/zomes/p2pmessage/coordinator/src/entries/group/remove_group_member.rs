@@ -0,0 +1,53 @@
+use hdk::prelude::*;
+
+use p2pmessage_coordinator_types::*;
+use p2pmessage_integrity_types::*;
+
+use crate::utils::error;
+
+use super::helpers::{commit_and_share_membership, get_group_from_chain, get_group_members};
+
+pub fn remove_group_member_handler(
+    group_member_input: GroupMemberInput,
+) -> ExternResult<GroupData> {
+    let me = agent_info()?.agent_latest_pubkey;
+    let group = get_group_from_chain(group_member_input.group_id.clone())?;
+    let previous_members = get_group_members(group_member_input.group_id.clone())?;
+
+    if group.creator != me || !previous_members.contains(&me) {
+        return error(P2PMessageError::Unauthorized(String::from(
+            "Sorry. Only the creator of a group can remove members.",
+        )));
+    }
+
+    if group_member_input.member == me {
+        return error(P2PMessageError::ValidationFailed(String::from(
+            "Sorry. Use leave_group to leave a group.",
+        )));
+    }
+
+    if !previous_members.contains(&group_member_input.member) {
+        return error(P2PMessageError::ValidationFailed(String::from(
+            "Sorry. This agent is not a member of the group.",
+        )));
+    }
+
+    let members: Vec<AgentPubKey> = previous_members
+        .clone()
+        .into_iter()
+        .filter(|member| *member != group_member_input.member)
+        .collect();
+
+    let membership = P2PGroupMembership {
+        group_id: group_member_input.group_id,
+        actor: me,
+        change: MembershipChange::RemoveMember {
+            member: group_member_input.member,
+        },
+        members,
+        timestamp: sys_time()?,
+    };
+
+    // the removed member is told as well so they stop sending to the group
+    commit_and_share_membership(group, membership, previous_members)
+}
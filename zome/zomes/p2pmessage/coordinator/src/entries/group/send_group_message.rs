@@ -0,0 +1,129 @@
+use hdk::prelude::*;
+use std::collections::HashMap;
+
+use p2pmessage_coordinator_types::*;
+use p2pmessage_integrity_types::*;
+
+use crate::{
+    entries::message::utils::this_zome_index,
+    file_chunks::{commit_file_chunk, send_group_file_chunks, split_file},
+    utils::{call_agent, error},
+    verify_file::verify_file_input,
+};
+
+use super::helpers::{commit_group_receipt, get_group_members};
+
+pub fn send_group_message_handler(
+    message_input: GroupMessageInput,
+) -> ExternResult<GroupMessageDelivery> {
+    let me = agent_info()?.agent_latest_pubkey;
+    let members = get_group_members(message_input.group_id.clone())?;
+
+    if !members.contains(&me) {
+        return error(P2PMessageError::Unauthorized(String::from(
+            "Sorry. You are not a member of this group.",
+        )));
+    }
+
+    let (payload, file, chunks) = match message_input.payload {
+        PayloadInput::Text { payload } => (Payload::Text { payload }, None, Vec::new()),
+        PayloadInput::File {
+            metadata,
+            file_type,
+            file_bytes,
+        } => {
+            verify_file_input(&metadata, &file_type, &file_bytes)?;
+            let p2pfile = P2PFileBytes(file_bytes);
+            let file_hash = hash_entry(&p2pfile)?;
+            let chunks = split_file(&p2pfile.0);
+            let payload = Payload::File {
+                metadata: FileMetadata {
+                    file_name: metadata.file_name,
+                    file_size: metadata.file_size,
+                    file_type: metadata.file_type,
+                    file_hash,
+                    chunks: chunks
+                        .iter()
+                        .map(hash_entry)
+                        .collect::<ExternResult<Vec<EntryHash>>>()?,
+                },
+                file_type,
+            };
+            // a file that fits in one chunk goes whole with the message
+            match chunks.len() {
+                0 | 1 => (payload, Some(p2pfile), chunks),
+                _ => (payload, None, chunks),
+            }
+        }
+    };
+
+    let message = P2PGroupMessage {
+        group_id: message_input.group_id,
+        author: me.clone(),
+        payload,
+        time_sent: sys_time()?,
+        reply_to: message_input.reply_to,
+    };
+    let message_hash = hash_entry(&message)?;
+
+    let message_entry = Entry::App(message.clone().try_into()?);
+    host_call::<CreateInput, ActionHash>(
+        __hc__create_1,
+        CreateInput::new(
+            EntryDefLocation::app(this_zome_index()?, 11),
+            EntryVisibility::Private,
+            message_entry,
+            ChainTopOrdering::Relaxed,
+        ),
+    )?;
+
+    for chunk in chunks.into_iter() {
+        commit_file_chunk(chunk)?;
+    }
+
+    let receive_input = ReceiveGroupMessageInput {
+        message: message.clone(),
+        file,
+    };
+
+    // fan out to every other member, each member answers with its own receipt
+    let mut receipts: HashMap<String, P2PGroupMessageReceipt> = HashMap::new();
+    let mut undelivered: Vec<AgentPubKey> = Vec::new();
+    for member in members.into_iter().filter(|member| *member != me) {
+        let zome_call_response: ZomeCallResponse =
+            call_agent(member.clone(), "receive_group_message", &receive_input)?;
+
+        match zome_call_response {
+            ZomeCallResponse::Ok(extern_io) => {
+                let receipt: Result<P2PGroupMessageReceipt, SerializedBytesError> =
+                    extern_io.decode();
+                match receipt {
+                    // members the chunks of the file did not reach count as undelivered
+                    Ok(receipt) if receipt.member == member => {
+                        if !send_group_file_chunks(&member, message_hash.clone(), &message)? {
+                            undelivered.push(member);
+                            continue;
+                        }
+                        let receipt_hash = commit_group_receipt(receipt.clone())?;
+                        receipts.insert(receipt_hash.to_string(), receipt);
+                    }
+                    _ => undelivered.push(member),
+                }
+            }
+            _ => undelivered.push(member),
+        }
+    }
+
+    Ok(GroupMessageDelivery {
+        message_hash,
+        message: GroupMessageData {
+            group_id: message.group_id,
+            author: message.author,
+            payload: message.payload,
+            time_sent: message.time_sent,
+            reply_to: message.reply_to,
+        },
+        receipts,
+        undelivered,
+    })
+}
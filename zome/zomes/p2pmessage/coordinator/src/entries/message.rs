@@ -1,20 +1,42 @@
+pub mod block_agent;
+pub mod cap_grants;
+pub mod chain_index;
 pub mod commit_message_to_receiver_chain;
-pub mod commit_receipt_to_sender_chain;
+pub mod delete_message;
+pub mod edit_message;
+pub mod encryption;
+pub mod file_chunks;
 pub mod get_adjacent_messages;
+pub mod get_conversation_summaries;
 pub mod get_file_bytes;
 pub mod get_latest_messages;
 pub mod get_messages_by_agent_by_timestamp;
 pub mod get_next_messages;
 pub mod get_pinned_messages;
 pub mod get_previous_messages;
+pub mod get_undelivered;
 pub mod helpers;
 pub mod init;
+pub mod is_user_blocked;
+pub mod mark_conversation_read;
+pub mod message_requests;
+pub mod outbox;
 pub mod pin_message;
+pub mod post_commit;
+pub mod rate_limits;
+pub mod react_to_message;
 pub mod read_message;
+pub mod receive_edit;
 pub mod receive_message;
 pub mod receive_receipt;
+pub mod retract_message;
+pub mod retry_delivery;
+pub mod search_messages;
 pub mod send_message;
 pub mod send_message_with_timestamp;
 pub mod sync_pins;
+pub mod sync_reactions;
 pub mod typing;
-pub mod utils;
\ No newline at end of file
+pub mod unblock_agent;
+pub mod utils;
+pub mod verify_file;
@@ -0,0 +1,28 @@
+use hdk::prelude::*;
+
+use p2pmessage_coordinator_types::*;
+use p2pmessage_integrity_types::*;
+
+use crate::{
+    is_user_blocked::{commit_block_status, get_blocked_agents_from_chain, is_user_blocked},
+    utils::error,
+};
+
+pub fn block_agent_handler(agent: AgentPubKey) -> ExternResult<Vec<AgentPubKey>> {
+    if agent == agent_info()?.agent_latest_pubkey {
+        return error(P2PMessageError::ValidationFailed(String::from(
+            "Sorry. You cannot block yourself.",
+        )));
+    }
+
+    if !is_user_blocked(agent.clone())? {
+        commit_block_status(
+            agent,
+            BlockStatus::Blocked {
+                timestamp: sys_time()?,
+            },
+        )?;
+    }
+
+    get_blocked_agents_from_chain()
+}
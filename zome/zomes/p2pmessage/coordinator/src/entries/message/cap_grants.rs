@@ -0,0 +1,234 @@
+use hdk::prelude::*;
+use std::collections::{BTreeSet, HashSet};
+
+use p2pmessage_coordinator_types::*;
+use p2pmessage_integrity_types::*;
+
+use super::utils::{decode_error, error};
+
+/*
+ * CAPABILITY GRANTS
+ * the cap_grant_policy dna property decides who may call the remote functions of the zome,
+ * assigned grants can also be issued to and revoked from single agents at runtime
+ */
+
+// functions other agents call on this cell
+pub const REMOTE_FUNCTIONS: [&str; 13] = [
+    "receive_message",
+    "receive_receipt",
+    "receive_edit",
+    "retract_message",
+    "typing",
+    "sync_pins",
+    "sync_reactions",
+    "receive_group_membership",
+    "receive_group_message",
+    "receive_group_receipt",
+    "exchange_keys",
+    "get_missing_file_chunks",
+    "receive_file_chunk",
+];
+
+const ISSUED_GRANT_TAG: &str = "p2pmessage_agent_grant";
+
+pub fn create_policy_grants() -> ExternResult<()> {
+    match get_properties()?.cap_grant_policy {
+        CapGrantPolicy::Unrestricted => create_function_grants(CapAccess::Unrestricted),
+        CapGrantPolicy::Transferable { secret } => {
+            create_function_grants(CapAccess::Transferable {
+                secret: shared_cap_secret(&secret)?,
+            })
+        }
+        CapGrantPolicy::Assigned { contacts } => {
+            let me = agent_info()?.agent_latest_pubkey;
+            for contact in contacts.into_iter().map(AgentPubKey::from) {
+                if contact != me {
+                    issue_grant_handler(contact)?;
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+fn create_function_grants(access: CapAccess) -> ExternResult<()> {
+    let zome_name: ZomeName = zome_info()?.name;
+
+    for function in REMOTE_FUNCTIONS.iter() {
+        let mut granted_function = BTreeSet::new();
+        granted_function.insert((zome_name.clone(), (*function).into()));
+
+        create_cap_grant(CapGrantEntry {
+            tag: (*function).into(),
+            access: access.clone(),
+            functions: GrantedFunctions::Listed(granted_function),
+        })?;
+    }
+
+    Ok(())
+}
+
+pub fn issue_grant_handler(agent: AgentPubKey) -> ExternResult<ActionHash> {
+    if agent == agent_info()?.agent_latest_pubkey {
+        return error(P2PMessageError::ValidationFailed(String::from(
+            "Sorry. You cannot issue a grant to yourself.",
+        )));
+    }
+
+    // issuing twice keeps the grant the agent may already hold a claim for
+    if let Some((grant_hash, _grant)) = get_issued_grants(&agent)?.into_iter().next() {
+        return Ok(grant_hash);
+    }
+
+    let zome_name: ZomeName = zome_info()?.name;
+    let mut granted_functions = BTreeSet::new();
+    for function in REMOTE_FUNCTIONS.iter() {
+        granted_functions.insert((zome_name.clone(), (*function).into()));
+    }
+
+    let mut assignees = BTreeSet::new();
+    assignees.insert(agent);
+
+    create_cap_grant(CapGrantEntry {
+        tag: ISSUED_GRANT_TAG.into(),
+        access: CapAccess::Assigned {
+            secret: generate_cap_secret()?,
+            assignees,
+        },
+        functions: GrantedFunctions::Listed(granted_functions),
+    })
+}
+
+pub fn revoke_grant_handler(agent: AgentPubKey) -> ExternResult<()> {
+    let issued_grants = get_issued_grants(&agent)?;
+    if issued_grants.is_empty() {
+        return error(P2PMessageError::NotFound(String::from(
+            "Sorry. No grant has been issued to this agent.",
+        )));
+    }
+
+    for (grant_hash, _grant) in issued_grants.into_iter() {
+        delete_cap_grant(grant_hash)?;
+    }
+
+    Ok(())
+}
+
+// only the assignee of a grant gets its secret, the grant checks the provenance again on use
+pub fn request_cap_claim_handler() -> ExternResult<Option<CapSecret>> {
+    let provenance = call_info()?.provenance;
+
+    for (_grant_hash, grant) in get_issued_grants(&provenance)?.into_iter() {
+        if let CapAccess::Assigned { secret, .. } = grant.access {
+            return Ok(Some(secret));
+        }
+    }
+
+    Ok(None)
+}
+
+// the secret to call the remote functions of an agent with, refresh skips the stored claim
+pub fn cap_secret_for(agent: &AgentPubKey, refresh: bool) -> ExternResult<Option<CapSecret>> {
+    match get_properties()?.cap_grant_policy {
+        CapGrantPolicy::Unrestricted => Ok(None),
+        CapGrantPolicy::Transferable { secret } => Ok(Some(shared_cap_secret(&secret)?)),
+        CapGrantPolicy::Assigned { .. } => {
+            if !refresh {
+                if let Some(secret) = get_claim_from_chain(agent)? {
+                    return Ok(Some(secret));
+                }
+            }
+            request_cap_claim(agent)
+        }
+    }
+}
+
+// an agent that cannot be reached or has no grant for us is left to fail on the actual call
+fn request_cap_claim(agent: &AgentPubKey) -> ExternResult<Option<CapSecret>> {
+    match call_remote(
+        agent.clone(),
+        zome_info()?.name,
+        "request_cap_claim".into(),
+        None,
+        (),
+    )? {
+        ZomeCallResponse::Ok(extern_io) => match extern_io.decode::<Option<CapSecret>>() {
+            Ok(Some(secret)) => {
+                create_cap_claim(CapClaimEntry::new(
+                    ISSUED_GRANT_TAG.into(),
+                    agent.clone(),
+                    secret,
+                ))?;
+                Ok(Some(secret))
+            }
+            Ok(None) => Ok(None),
+            Err(e) => decode_error(e),
+        },
+        _ => Ok(None),
+    }
+}
+
+fn get_claim_from_chain(agent: &AgentPubKey) -> ExternResult<Option<CapSecret>> {
+    let mut queried_claims: Vec<Record> = query(
+        QueryFilter::new()
+            .entry_type(EntryType::CapClaim)
+            .include_entries(true),
+    )?;
+
+    queried_claims.reverse();
+
+    for record in queried_claims.into_iter() {
+        if let Some(Entry::CapClaim(claim)) = record.entry().as_option() {
+            if claim.grantor() == agent {
+                return Ok(Some(*claim.secret()));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+// live grants this agent has issued to the given agent
+fn get_issued_grants(agent: &AgentPubKey) -> ExternResult<Vec<(ActionHash, CapGrantEntry)>> {
+    let deleted_grants: HashSet<ActionHash> =
+        query(QueryFilter::new().action_type(ActionType::Delete))?
+            .into_iter()
+            .filter_map(|record| match record.action() {
+                Action::Delete(delete) => Some(delete.deletes_address.clone()),
+                _ => None,
+            })
+            .collect();
+
+    let queried_grants: Vec<Record> = query(
+        QueryFilter::new()
+            .entry_type(EntryType::CapGrant)
+            .include_entries(true),
+    )?;
+
+    let mut issued_grants: Vec<(ActionHash, CapGrantEntry)> = Vec::new();
+    for record in queried_grants.into_iter() {
+        if deleted_grants.contains(record.action_address()) {
+            continue;
+        }
+        if let Some(Entry::CapGrant(grant)) = record.entry().as_option() {
+            if let CapAccess::Assigned { assignees, .. } = &grant.access {
+                if grant.tag == ISSUED_GRANT_TAG && assignees.contains(agent) {
+                    issued_grants.push((record.action_address().clone(), grant.clone()));
+                }
+            }
+        }
+    }
+
+    Ok(issued_grants)
+}
+
+// every agent derives the same cap secret from the secret in the dna properties
+fn shared_cap_secret(secret: &str) -> ExternResult<CapSecret> {
+    let hashed_secret: Vec<u8> = hash_blake2b(secret.as_bytes().to_vec(), 64)?;
+    match <[u8; 64]>::try_from(hashed_secret) {
+        Ok(bytes) => Ok(CapSecret::from(bytes)),
+        Err(_) => error(P2PMessageError::ValidationFailed(String::from(
+            "Sorry. The shared secret of the grant policy could not be derived.",
+        ))),
+    }
+}
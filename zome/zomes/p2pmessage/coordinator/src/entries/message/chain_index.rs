@@ -0,0 +1,809 @@
+use hdk::prelude::*;
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use p2pmessage_coordinator_types::*;
+use p2pmessage_integrity_types::*;
+
+use crate::{encryption::decrypt_payload, rate_limits::payload_size};
+
+use super::utils::this_zome_index;
+
+/*
+ * CHAIN INDEX
+ * every CHAIN_INDEX_BATCH_SIZE actions are split into P2PIndexBucket entries, one per
+ * conversation and day of time_sent, listed in a P2PChainIndex directory of their own.
+ * receipts, edits, reactions, deletions and pins go to the buckets of the messages they are
+ * about. getters load only the buckets they need, entries looked up by hash are queried by
+ * their entry hash
+ */
+
+pub const CHAIN_INDEX_BATCH_SIZE: u32 = 100;
+
+const DAY: i64 = 24 * 60 * 60 * 1_000_000;
+
+// messages ordered by time_sent, everything else in chain order
+#[derive(Default)]
+pub struct IndexRows {
+    pub messages: Vec<IndexedMessage>,
+    pub receipts: Vec<IndexedReceipt>,
+    pub edits: Vec<IndexedChange>,
+    pub reactions: Vec<IndexedChange>,
+    pub deletions: Vec<IndexedChange>,
+    pub pins: Vec<IndexedChange>,
+}
+
+pub struct ChainIndex {
+    buckets: Vec<IndexBucketRef>,
+    // the actions committed after the last indexed batch
+    unindexed: IndexRows,
+}
+
+impl ChainIndex {
+    // agents we sent a message to or received one from
+    pub fn conversants(&self) -> HashSet<AgentPubKey> {
+        self.buckets
+            .iter()
+            .map(|bucket| bucket.conversant.clone())
+            .chain(
+                self.unindexed
+                    .messages
+                    .iter()
+                    .map(|row| row.conversant.clone()),
+            )
+            .collect()
+    }
+
+    // the days of time_sent that have messages in the conversation
+    pub fn days(&self, conversant: &AgentPubKey) -> BTreeSet<i64> {
+        self.buckets
+            .iter()
+            .filter(|bucket| bucket.conversant == *conversant)
+            .map(|bucket| bucket.day)
+            .chain(
+                self.unindexed
+                    .messages
+                    .iter()
+                    .filter(|row| row.conversant == *conversant)
+                    .map(|row| day_of(row.time_sent)),
+            )
+            .collect()
+    }
+
+    /*
+     * the rows of the selected conversations and days, messages ordered by time_sent,
+     * receipts and changes not indexed yet are all returned and left to the caller to match
+     */
+    pub fn load(&self, select: impl Fn(&AgentPubKey, i64) -> bool) -> ExternResult<IndexRows> {
+        self.load_where(
+            |bucket| select(&bucket.conversant, bucket.day),
+            |row| select(&row.conversant, day_of(row.time_sent)),
+        )
+    }
+
+    // the messages of the sender committed here after `since`, in no particular order
+    pub fn load_received_since(
+        &self,
+        sender: &AgentPubKey,
+        since: Timestamp,
+    ) -> ExternResult<Vec<IndexedMessage>> {
+        let rows = self.load_where(
+            |bucket| bucket.conversant == *sender && bucket.last_committed > since,
+            |row| row.conversant == *sender,
+        )?;
+
+        Ok(rows
+            .messages
+            .into_iter()
+            .filter(|row| !row.from_me && row.time_committed > since)
+            .collect())
+    }
+
+    // the file messages carrying the file, found through the file hashes of the buckets
+    pub fn load_file(&self, file_hash: &EntryHash) -> ExternResult<Vec<IndexedMessage>> {
+        let rows = self.load_where(
+            |bucket| bucket.files.contains(file_hash),
+            |row| row.file_hash.as_ref() == Some(file_hash),
+        )?;
+
+        Ok(rows
+            .messages
+            .into_iter()
+            .filter(|row| row.file_hash.as_ref() == Some(file_hash))
+            .collect())
+    }
+
+    fn load_where(
+        &self,
+        select_bucket: impl Fn(&IndexBucketRef) -> bool,
+        select_unindexed: impl Fn(&IndexedMessage) -> bool,
+    ) -> ExternResult<IndexRows> {
+        let bucket_hashes: HashSet<EntryHash> = self
+            .buckets
+            .iter()
+            .filter(|bucket| select_bucket(bucket))
+            .map(|bucket| bucket.bucket.clone())
+            .collect();
+
+        let mut buckets: Vec<P2PIndexBucket> = Vec::new();
+        for record in query_by_hashes(16, bucket_hashes)?.into_iter() {
+            if let Ok(bucket_entry) = TryInto::<P2PIndexBucket>::try_into(record) {
+                buckets.push(bucket_entry);
+            }
+        }
+        buckets.sort_by_key(|bucket| bucket.first_seq);
+
+        let mut rows = IndexRows::default();
+        let mut receipt_hashes: HashSet<EntryHash> = HashSet::new();
+        let mut change_hashes: HashSet<EntryHash> = HashSet::new();
+        for bucket in buckets.into_iter() {
+            rows.messages.extend(bucket.messages);
+            // a receipt covering messages of several buckets has a row in each of them
+            for row in bucket.receipts.into_iter() {
+                if receipt_hashes.insert(row.hash.clone()) {
+                    rows.receipts.push(row);
+                }
+            }
+            extend_changes(&mut rows.edits, &mut change_hashes, bucket.edits);
+            extend_changes(&mut rows.reactions, &mut change_hashes, bucket.reactions);
+            extend_changes(&mut rows.deletions, &mut change_hashes, bucket.deletions);
+            extend_changes(&mut rows.pins, &mut change_hashes, bucket.pins);
+        }
+        rows.messages.extend(
+            self.unindexed
+                .messages
+                .iter()
+                .filter(|row| select_unindexed(row))
+                .cloned(),
+        );
+        rows.receipts.extend(
+            self.unindexed
+                .receipts
+                .iter()
+                .filter(|row| !receipt_hashes.contains(&row.hash))
+                .cloned(),
+        );
+        let unindexed = &self.unindexed;
+        extend_changes(&mut rows.edits, &mut change_hashes, unindexed.edits.clone());
+        extend_changes(&mut rows.reactions, &mut change_hashes, unindexed.reactions.clone());
+        extend_changes(&mut rows.deletions, &mut change_hashes, unindexed.deletions.clone());
+        extend_changes(&mut rows.pins, &mut change_hashes, unindexed.pins.clone());
+        rows.messages.sort_by_key(|row| (row.time_sent, row.seq));
+
+        Ok(rows)
+    }
+
+    /*
+     * the rows of a conversation newest first, loaded a day at a time from the day of
+     * `from` back in time, or forward in time, until `enough` holds for the rows so far
+     */
+    pub fn load_conversation(
+        &self,
+        conversant: &AgentPubKey,
+        from: Timestamp,
+        forward: bool,
+        enough: impl Fn(&[IndexedMessage]) -> bool,
+    ) -> ExternResult<Vec<IndexedMessage>> {
+        let from_day = day_of(from);
+        let mut days: Vec<i64> = self
+            .days(conversant)
+            .into_iter()
+            .filter(|day| match forward {
+                true => *day >= from_day,
+                false => *day <= from_day,
+            })
+            .collect();
+        if !forward {
+            days.reverse();
+        }
+
+        let mut rows: Vec<IndexedMessage> = Vec::new();
+        for day in days.into_iter() {
+            let mut day_rows = self
+                .load(|agent, bucket_day| agent == conversant && bucket_day == day)?
+                .messages;
+            rows.append(&mut day_rows);
+            if enough(&rows) {
+                break;
+            }
+        }
+        rows.sort_by_key(|row| std::cmp::Reverse((row.time_sent, row.seq)));
+
+        Ok(rows)
+    }
+}
+
+fn extend_changes(
+    rows: &mut Vec<IndexedChange>,
+    change_hashes: &mut HashSet<EntryHash>,
+    changes: Vec<IndexedChange>,
+) {
+    for row in changes.into_iter() {
+        if change_hashes.insert(row.hash.clone()) {
+            rows.push(row);
+        }
+    }
+}
+
+pub fn day_of(timestamp: Timestamp) -> i64 {
+    timestamp.as_micros().div_euclid(DAY)
+}
+
+// the buckets of every directory and the rows of the actions committed after them
+pub fn get_chain_index() -> ExternResult<ChainIndex> {
+    let (first_unindexed_seq, buckets) = get_directory()?;
+
+    Ok(ChainIndex {
+        buckets,
+        // also covers entries committed earlier in the same zome call
+        unindexed: index_actions(first_unindexed_seq, u32::MAX)?,
+    })
+}
+
+/*
+ * commits the buckets of every full batch of actions not indexed yet and a new directory,
+ * called before a zome call commits anything so the indexed seqs can no longer move
+ */
+pub fn index_chain() -> ExternResult<()> {
+    let head_seq = agent_info()?.chain_head.1;
+    let (mut first_seq, _buckets) = get_directory()?;
+    if first_seq + CHAIN_INDEX_BATCH_SIZE - 1 > head_seq {
+        return Ok(());
+    }
+
+    // chains written before the index existed catch up in one go
+    let mut buckets: Vec<IndexBucketRef> = Vec::new();
+    while first_seq + CHAIN_INDEX_BATCH_SIZE - 1 <= head_seq {
+        let last_seq = first_seq + CHAIN_INDEX_BATCH_SIZE - 1;
+        for bucket in split_into_buckets(first_seq, last_seq)?.into_iter() {
+            buckets.push(bucket_ref(&bucket)?);
+            commit_index_entry(16, Entry::App(bucket.try_into()?))?;
+        }
+
+        first_seq = last_seq + 1;
+    }
+
+    let directory = P2PChainIndex {
+        last_seq: first_seq - 1,
+        buckets,
+        replaced: Vec::new(),
+    };
+    commit_index_entry(15, Entry::App(directory.try_into()?))?;
+
+    Ok(())
+}
+
+/*
+ * rows of the conversation that could not be decrypted when they were indexed are
+ * indexed again, a new directory lists their buckets in place of the old ones
+ */
+pub fn reindex_encrypted(conversant: &AgentPubKey) -> ExternResult<()> {
+    let me = agent_info()?.agent_latest_pubkey;
+    let (first_unindexed_seq, buckets) = get_directory()?;
+    if first_unindexed_seq == 0 {
+        return Ok(());
+    }
+    let bucket_hashes: HashSet<EntryHash> = buckets
+        .iter()
+        .filter(|bucket| bucket.conversant == *conversant)
+        .map(|bucket| bucket.bucket.clone())
+        .collect();
+
+    let mut directory = P2PChainIndex {
+        last_seq: first_unindexed_seq - 1,
+        buckets: Vec::new(),
+        replaced: Vec::new(),
+    };
+    for record in query_by_hashes(16, bucket_hashes)?.into_iter() {
+        let (old_hash, mut bucket) = match (
+            record.action().entry_hash().cloned(),
+            TryInto::<P2PIndexBucket>::try_into(record),
+        ) {
+            (Some(old_hash), Ok(bucket)) => (old_hash, bucket),
+            _ => continue,
+        };
+        let encrypted_rows: Vec<IndexedMessage> = bucket
+            .messages
+            .iter()
+            .filter(|row| row.payload_type == IndexedPayloadKind::Encrypted)
+            .cloned()
+            .collect();
+        if encrypted_rows.is_empty() {
+            continue;
+        }
+
+        let mut decrypted_rows: HashMap<EntryHash, IndexedMessage> = HashMap::new();
+        for message in get_message_records(&encrypted_rows)?.into_iter() {
+            let seq = message.action().action_seq();
+            let time_committed = message.action().timestamp();
+            if let (Some(hash), Ok(message_entry)) = (
+                message.action().entry_hash().cloned(),
+                TryInto::<P2PMessage>::try_into(message),
+            ) {
+                let payload =
+                    decrypt_payload(message_entry.payload.clone(), &message_entry.author)?;
+                if let Payload::Encrypted { .. } = payload {
+                    continue;
+                }
+                decrypted_rows.insert(
+                    hash.clone(),
+                    message_row(seq, time_committed, hash, &message_entry, &payload, &me),
+                );
+            }
+        }
+        if decrypted_rows.is_empty() {
+            continue;
+        }
+
+        for row in bucket.messages.iter_mut() {
+            if let Some(decrypted_row) = decrypted_rows.remove(&row.hash) {
+                // the seq indexed is kept, it lies in the batch of the bucket
+                *row = IndexedMessage {
+                    seq: row.seq,
+                    ..decrypted_row
+                };
+            }
+        }
+        directory.buckets.push(bucket_ref(&bucket)?);
+        directory.replaced.push(old_hash);
+        commit_index_entry(16, Entry::App(bucket.try_into()?))?;
+    }
+    if directory.buckets.is_empty() {
+        return Ok(());
+    }
+
+    commit_index_entry(15, Entry::App(directory.try_into()?))?;
+
+    Ok(())
+}
+
+// the one matcher behind the payload filters of every getter
+pub fn matches_payload_filter(row: &IndexedMessage, filter: &PayloadFilter) -> bool {
+    let kind_matches = filter.kinds.is_empty()
+        || filter.kinds.iter().any(|kind| match kind {
+            PayloadType::Text => row.payload_type == IndexedPayloadKind::Text,
+            PayloadType::File => matches!(
+                row.payload_type,
+                IndexedPayloadKind::Media | IndexedPayloadKind::Other
+            ),
+            PayloadType::Media => row.payload_type == IndexedPayloadKind::Media,
+            PayloadType::Other => row.payload_type == IndexedPayloadKind::Other,
+            PayloadType::Encrypted => row.payload_type == IndexedPayloadKind::Encrypted,
+            PayloadType::All => true,
+        });
+    let mime_type_matches = filter.mime_types.is_empty()
+        || row.mime_type.as_ref().is_some_and(|mime_type| {
+            filter
+                .mime_types
+                .iter()
+                .any(|prefix| mime_type.starts_with(prefix.as_str()))
+        });
+
+    kind_matches
+        && mime_type_matches
+        && filter
+            .has_reply
+            .is_none_or(|has_reply| row.has_reply == has_reply)
+        && filter
+            .from_me
+            .is_none_or(|from_me| row.from_me == from_me)
+}
+
+// undecryptable payloads get a row of their own until the keys to read them are known
+pub fn message_row(
+    seq: u32,
+    time_committed: Timestamp,
+    hash: EntryHash,
+    message_entry: &P2PMessage,
+    payload: &Payload,
+    me: &AgentPubKey,
+) -> IndexedMessage {
+    let (payload_type, mime_type, file_hash) = match payload {
+        Payload::Text { .. } => (IndexedPayloadKind::Text, None, None),
+        Payload::Encrypted { .. } => (IndexedPayloadKind::Encrypted, None, None),
+        Payload::File {
+            metadata,
+            file_type,
+        } => {
+            let file_hash = Some(metadata.file_hash.clone());
+            match file_type {
+                FileType::Image { .. } | FileType::Video { .. } => (
+                    IndexedPayloadKind::Media,
+                    Some(metadata.file_type.clone()),
+                    file_hash,
+                ),
+                FileType::Other => (
+                    IndexedPayloadKind::Other,
+                    Some(metadata.file_type.clone()),
+                    file_hash,
+                ),
+            }
+        }
+    };
+
+    IndexedMessage {
+        seq,
+        hash,
+        conversant: conversant_of(message_entry, me),
+        time_sent: message_entry.time_sent,
+        payload_type,
+        mime_type,
+        has_reply: message_entry.reply_to.is_some(),
+        from_me: message_entry.author == *me,
+        size: payload_size(payload),
+        time_committed,
+        file_hash,
+    }
+}
+
+// records of the entries with these hashes, in chain order
+pub fn query_by_hashes(index: u8, hashes: HashSet<EntryHash>) -> ExternResult<Vec<Record>> {
+    if hashes.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    query(
+        QueryFilter::new()
+            .entry_type(EntryType::App(AppEntryDef::new(
+                EntryDefIndex::from(index),
+                this_zome_index()?,
+                EntryVisibility::Private,
+            )))
+            .entry_hashes(hashes)
+            .include_entries(true),
+    )
+}
+
+/*
+ * the seq of the first action committed after `since`. action timestamps never go back
+ * along the chain, so it is found by halving the seq range one action at a time
+ */
+pub fn first_seq_since(since: Timestamp) -> ExternResult<u32> {
+    let (mut first_seq, mut last_seq) = (0, agent_info()?.chain_head.1 + 1);
+    while first_seq < last_seq {
+        let seq = first_seq + (last_seq - first_seq) / 2;
+        let committed_since = query(
+            QueryFilter::new().sequence_range(ChainQueryFilterRange::ActionSeqRange(seq, seq)),
+        )?
+        .first()
+        .is_none_or(|record| record.action().timestamp() > since);
+        if committed_since {
+            last_seq = seq;
+        } else {
+            first_seq = seq + 1;
+        }
+    }
+
+    Ok(first_seq)
+}
+
+// records of the entry type committed after `since`, the older part of the chain is not read
+pub fn query_since(index: u8, since: Timestamp) -> ExternResult<Vec<Record>> {
+    query_entry_type(index, first_seq_since(since)?, u32::MAX)
+}
+
+// records of the rows, in the order of the rows
+pub fn get_message_records(rows: &[IndexedMessage]) -> ExternResult<Vec<Record>> {
+    get_indexed_records(
+        0,
+        rows.iter().map(|row| (row.seq, row.hash.clone())).collect(),
+    )
+}
+
+pub fn get_receipt_records(rows: &[IndexedReceipt]) -> ExternResult<Vec<Record>> {
+    get_indexed_records(
+        1,
+        rows.iter().map(|row| (row.seq, row.hash.clone())).collect(),
+    )
+}
+
+// records of edit, reaction, deletion or pin rows, index is the entry type of the rows
+pub fn get_change_records(index: u8, rows: &[IndexedChange]) -> ExternResult<Vec<Record>> {
+    get_indexed_records(
+        index,
+        rows.iter().map(|row| (row.seq, row.hash.clone())).collect(),
+    )
+}
+
+/*
+ * one query over the seq range of the rows, a record only answers a row with the same
+ * entry hash. entries committed with relaxed ordering can end up at another seq than
+ * the one indexed, those rows are looked up by hash alone
+ */
+fn get_indexed_records(index: u8, rows: Vec<(u32, EntryHash)>) -> ExternResult<Vec<Record>> {
+    let (first_seq, last_seq) = match (
+        rows.iter().map(|(seq, _hash)| *seq).min(),
+        rows.iter().map(|(seq, _hash)| *seq).max(),
+    ) {
+        (Some(first_seq), Some(last_seq)) => (first_seq, last_seq),
+        _ => return Ok(Vec::new()),
+    };
+    let hashes: HashSet<EntryHash> = rows.iter().map(|(_seq, hash)| hash.clone()).collect();
+
+    let mut records: HashMap<EntryHash, Record> = HashMap::new();
+    let queried_records: Vec<Record> = query(
+        QueryFilter::new()
+            .sequence_range(ChainQueryFilterRange::ActionSeqRange(first_seq, last_seq))
+            .entry_type(EntryType::App(AppEntryDef::new(
+                EntryDefIndex::from(index),
+                this_zome_index()?,
+                EntryVisibility::Private,
+            )))
+            .entry_hashes(hashes.clone())
+            .include_entries(true),
+    )?;
+    for record in queried_records.into_iter() {
+        if let Some(hash) = record.action().entry_hash().cloned() {
+            records.insert(hash, record);
+        }
+    }
+
+    let moved_hashes: HashSet<EntryHash> = hashes
+        .into_iter()
+        .filter(|hash| !records.contains_key(hash))
+        .collect();
+    for record in query_by_hashes(index, moved_hashes)?.into_iter() {
+        if let Some(hash) = record.action().entry_hash().cloned() {
+            records.insert(hash, record);
+        }
+    }
+
+    Ok(rows
+        .into_iter()
+        .filter_map(|(_seq, hash)| records.get(&hash).cloned())
+        .collect())
+}
+
+fn conversant_of(message_entry: &P2PMessage, me: &AgentPubKey) -> AgentPubKey {
+    if message_entry.author == *me {
+        message_entry.receiver.clone()
+    } else {
+        message_entry.author.clone()
+    }
+}
+
+/*
+ * the buckets in use after every directory was applied in chain order, and the first seq
+ * the directories do not cover. directories only hold what changed, so they stay small
+ */
+fn get_directory() -> ExternResult<(u32, Vec<IndexBucketRef>)> {
+    let queried_directories: Vec<Record> = query(
+        QueryFilter::new()
+            .entry_type(EntryType::App(AppEntryDef::new(
+                EntryDefIndex::from(15),
+                this_zome_index()?,
+                EntryVisibility::Private,
+            )))
+            .include_entries(true),
+    )?;
+
+    let mut first_unindexed_seq = 0;
+    let mut buckets: Vec<IndexBucketRef> = Vec::new();
+    for record in queried_directories.into_iter() {
+        if let Ok(directory) = TryInto::<P2PChainIndex>::try_into(record) {
+            buckets.retain(|bucket| !directory.replaced.contains(&bucket.bucket));
+            buckets.extend(directory.buckets);
+            first_unindexed_seq = std::cmp::max(first_unindexed_seq, directory.last_seq + 1);
+        }
+    }
+
+    Ok((first_unindexed_seq, buckets))
+}
+
+fn bucket_ref(bucket: &P2PIndexBucket) -> ExternResult<IndexBucketRef> {
+    Ok(IndexBucketRef {
+        conversant: bucket.conversant.clone(),
+        day: bucket.day,
+        bucket: hash_entry(bucket)?,
+        last_committed: bucket
+            .messages
+            .iter()
+            .map(|row| row.time_committed)
+            .max()
+            .unwrap_or(Timestamp::from_micros(0)),
+        files: bucket
+            .messages
+            .iter()
+            .filter_map(|row| row.file_hash.clone())
+            .collect(),
+    })
+}
+
+// receipts and changes go to the bucket of every message they are about
+fn split_into_buckets(first_seq: u32, last_seq: u32) -> ExternResult<Vec<P2PIndexBucket>> {
+    let me = agent_info()?.agent_latest_pubkey;
+    let rows = index_actions(first_seq, last_seq)?;
+
+    let mut message_keys: HashMap<EntryHash, (AgentPubKey, i64)> = rows
+        .messages
+        .iter()
+        .map(|row| {
+            (
+                row.hash.clone(),
+                (row.conversant.clone(), day_of(row.time_sent)),
+            )
+        })
+        .collect();
+    let earlier_messages: HashSet<EntryHash> = rows
+        .receipts
+        .iter()
+        .map(|row| &row.messages)
+        .chain(rows.edits.iter().map(|row| &row.messages))
+        .chain(rows.reactions.iter().map(|row| &row.messages))
+        .chain(rows.deletions.iter().map(|row| &row.messages))
+        .chain(rows.pins.iter().map(|row| &row.messages))
+        .flatten()
+        .filter(|message_id| !message_keys.contains_key(*message_id))
+        .cloned()
+        .collect();
+    for record in query_by_hashes(0, earlier_messages)?.into_iter() {
+        if let (Some(hash), Ok(message_entry)) = (
+            record.action().entry_hash().cloned(),
+            TryInto::<P2PMessage>::try_into(record),
+        ) {
+            message_keys.insert(
+                hash,
+                (
+                    conversant_of(&message_entry, &me),
+                    day_of(message_entry.time_sent),
+                ),
+            );
+        }
+    }
+
+    let new_bucket = |key: &(AgentPubKey, i64)| P2PIndexBucket {
+        conversant: key.0.clone(),
+        day: key.1,
+        first_seq,
+        last_seq,
+        messages: Vec::new(),
+        receipts: Vec::new(),
+        edits: Vec::new(),
+        reactions: Vec::new(),
+        deletions: Vec::new(),
+        pins: Vec::new(),
+    };
+    let mut buckets: HashMap<(AgentPubKey, i64), P2PIndexBucket> = HashMap::new();
+    for row in rows.messages.into_iter() {
+        let key = (row.conversant.clone(), day_of(row.time_sent));
+        buckets
+            .entry(key.clone())
+            .or_insert_with(|| new_bucket(&key))
+            .messages
+            .push(row);
+    }
+    let keys_of = |messages: &[EntryHash]| -> HashSet<(AgentPubKey, i64)> {
+        messages
+            .iter()
+            .filter_map(|message_id| message_keys.get(message_id).cloned())
+            .collect()
+    };
+    for row in rows.receipts.into_iter() {
+        for key in keys_of(&row.messages).into_iter() {
+            buckets
+                .entry(key.clone())
+                .or_insert_with(|| new_bucket(&key))
+                .receipts
+                .push(row.clone());
+        }
+    }
+    let changes = [
+        (6, rows.edits),
+        (8, rows.reactions),
+        (7, rows.deletions),
+        (2, rows.pins),
+    ];
+    for (index, change_rows) in changes.into_iter() {
+        for row in change_rows.into_iter() {
+            for key in keys_of(&row.messages).into_iter() {
+                let bucket = buckets
+                    .entry(key.clone())
+                    .or_insert_with(|| new_bucket(&key));
+                match index {
+                    6 => bucket.edits.push(row.clone()),
+                    8 => bucket.reactions.push(row.clone()),
+                    7 => bucket.deletions.push(row.clone()),
+                    _ => bucket.pins.push(row.clone()),
+                }
+            }
+        }
+    }
+
+    Ok(buckets.into_values().collect())
+}
+
+fn index_actions(first_seq: u32, last_seq: u32) -> ExternResult<IndexRows> {
+    let me = agent_info()?.agent_latest_pubkey;
+    let mut rows = IndexRows::default();
+
+    for record in query_entry_type(0, first_seq, last_seq)?.into_iter() {
+        let seq = record.action().action_seq();
+        let time_committed = record.action().timestamp();
+        if let (Some(hash), Ok(message_entry)) = (
+            record.action().entry_hash().cloned(),
+            TryInto::<P2PMessage>::try_into(record),
+        ) {
+            let payload = decrypt_payload(message_entry.payload.clone(), &message_entry.author)?;
+            rows.messages.push(message_row(
+                seq,
+                time_committed,
+                hash,
+                &message_entry,
+                &payload,
+                &me,
+            ));
+        }
+    }
+
+    for record in query_entry_type(1, first_seq, last_seq)?.into_iter() {
+        let seq = record.action().action_seq();
+        if let (Some(hash), Ok(receipt_entry)) = (
+            record.action().entry_hash().cloned(),
+            TryInto::<P2PMessageReceipt>::try_into(record),
+        ) {
+            rows.receipts.push(IndexedReceipt {
+                seq,
+                hash,
+                messages: receipt_entry.id,
+            });
+        }
+    }
+
+    rows.edits = index_changes(6, first_seq, last_seq, |edit: P2PMessageEdit| {
+        vec![edit.message_hash]
+    })?;
+    rows.reactions = index_changes(8, first_seq, last_seq, |reaction: P2PMessageReaction| {
+        vec![reaction.message_hash]
+    })?;
+    rows.deletions = index_changes(7, first_seq, last_seq, |deletion: P2PMessageDeletion| {
+        vec![deletion.message_hash]
+    })?;
+    rows.pins = index_changes(2, first_seq, last_seq, |pin: P2PMessagePin| pin.id)?;
+
+    Ok(rows)
+}
+
+fn index_changes<T: TryFrom<Record, Error = WasmError>>(
+    index: u8,
+    first_seq: u32,
+    last_seq: u32,
+    messages_of: impl Fn(T) -> Vec<EntryHash>,
+) -> ExternResult<Vec<IndexedChange>> {
+    let mut rows: Vec<IndexedChange> = Vec::new();
+    for record in query_entry_type(index, first_seq, last_seq)?.into_iter() {
+        let seq = record.action().action_seq();
+        if let (Some(hash), Ok(entry)) = (record.action().entry_hash().cloned(), T::try_from(record)) {
+            rows.push(IndexedChange {
+                seq,
+                hash,
+                messages: messages_of(entry),
+            });
+        }
+    }
+
+    Ok(rows)
+}
+
+fn query_entry_type(index: u8, first_seq: u32, last_seq: u32) -> ExternResult<Vec<Record>> {
+    query(
+        QueryFilter::new()
+            .sequence_range(ChainQueryFilterRange::ActionSeqRange(first_seq, last_seq))
+            .entry_type(EntryType::App(AppEntryDef::new(
+                EntryDefIndex::from(index),
+                this_zome_index()?,
+                EntryVisibility::Private,
+            )))
+            .include_entries(true),
+    )
+}
+
+fn commit_index_entry(index: u8, entry: Entry) -> ExternResult<ActionHash> {
+    host_call::<CreateInput, ActionHash>(
+        __hc__create_1,
+        CreateInput::new(
+            EntryDefLocation::app(this_zome_index()?, index),
+            EntryVisibility::Private,
+            entry,
+            ChainTopOrdering::Relaxed,
+        ),
+    )
+}
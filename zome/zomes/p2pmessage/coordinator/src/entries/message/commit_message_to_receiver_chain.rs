@@ -4,8 +4,9 @@ use p2pmessage_coordinator_types::*;
 use p2pmessage_integrity_types::*;
 
 use crate::{
+    encryption::{decrypt_payload, encrypt_file},
     helpers::{get_file_from_chain, get_message_from_chain},
-    utils::error,
+    utils::{call_agent, decode_error, error, zome_call_error},
 };
 
 pub fn commit_message_to_receiver_chain_handler(
@@ -14,24 +15,7 @@ pub fn commit_message_to_receiver_chain_handler(
     let message = get_message_from_chain(message_hash.clone())?;
 
     if agent_info()?.agent_latest_pubkey == message.author.clone() {
-        let receive_input = ReceiveMessageInput {
-            message: message.clone(),
-            file: match message.payload {
-                Payload::Text { .. } => None,
-                Payload::File { ref metadata, .. } => {
-                    let file_bytes = get_file_from_chain(metadata.to_owned().file_hash)?;
-                    Some(file_bytes)
-                }
-            },
-        };
-
-        let receive_call_result: ZomeCallResponse = call_remote(
-            message.receiver.clone(),
-            zome_info()?.name,
-            "receive_message".into(),
-            None,
-            &receive_input,
-        )?;
+        let receive_call_result = call_receive_message(message)?;
 
         match receive_call_result {
             ZomeCallResponse::Ok(extern_io) => {
@@ -39,22 +23,42 @@ pub fn commit_message_to_receiver_chain_handler(
                     extern_io.decode();
                 match received_receipt_result {
                     Ok(received_receipt) => return Ok(received_receipt),
-                    Err(e) => return Err(wasm_error!(WasmErrorInner::Guest(String::from(e)))),
+                    Err(e) => return decode_error(e),
                 }
             }
-            ZomeCallResponse::Unauthorized(..) => {
-                return error("Sorry, something went wrong. [Authorization error]");
-            }
-            ZomeCallResponse::NetworkError(_e) => {
-                return error("Sorry, something went wrong. [Network error]");
-            }
-            ZomeCallResponse::CountersigningSession(_e) => {
-                return error("Sorry, something went wrong. [Countersigning error]");
-            }
+            failed => return zome_call_error(failed),
         }
     }
 
-    return error(
+    error(P2PMessageError::Unauthorized(String::from(
         "Sorry. Was not able to commit the message to receiver's chain. Something went wrong.",
-    );
+    )))
+}
+
+pub fn call_receive_message(message: P2PMessage) -> ExternResult<ZomeCallResponse> {
+    // chunked files follow the message, see file_chunks.rs
+    let file = match decrypt_payload(message.payload.clone(), &message.author)? {
+        Payload::File { ref metadata, .. } if metadata.chunks.len() <= 1 => {
+            let file_bytes = get_file_from_chain(metadata.to_owned().file_hash)?;
+            Some(file_bytes)
+        }
+        _ => None,
+    };
+
+    // file bytes of encrypted messages travel encrypted as well
+    let encrypted_file = match file {
+        Some(ref file_bytes) => encrypt_file(file_bytes.clone(), &message)?,
+        None => None,
+    };
+
+    let receive_input = ReceiveMessageInput {
+        message: message.clone(),
+        file: match encrypted_file {
+            Some(_) => None,
+            None => file,
+        },
+        encrypted_file,
+    };
+
+    call_agent(message.receiver.clone(), "receive_message", &receive_input)
 }
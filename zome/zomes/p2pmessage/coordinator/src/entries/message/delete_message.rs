@@ -0,0 +1,68 @@
+use hdk::prelude::*;
+use std::collections::HashMap;
+
+use p2pmessage_coordinator_types::*;
+use p2pmessage_integrity_types::*;
+
+use crate::{
+    helpers::{get_deletions_of_messages, get_message_from_chain, message_key},
+    outbox::deliver_change_handler,
+    utils::error,
+};
+
+use super::utils::this_zome_index;
+
+pub fn delete_message_handler(
+    delete_message_input: DeleteMessageInput,
+) -> ExternResult<HashMap<String, P2PMessageDeletion>> {
+    let message_hash = delete_message_input.message_hash;
+    let message = get_message_from_chain(message_hash.clone())?;
+    let me = agent_info()?.agent_latest_pubkey;
+
+    let deletions = get_deletions_of_messages(&message_key(&message_hash, &message)?)?;
+    match deletions.get(&message_hash.clone().to_string()) {
+        Some(previous_deletion)
+            if previous_deletion.scope == DeleteScope::ForEveryone
+                || delete_message_input.scope == DeleteScope::ForMe =>
+        {
+            return error(P2PMessageError::ValidationFailed(String::from(
+                "Sorry. This message has already been deleted.",
+            )));
+        }
+        _ => (),
+    }
+
+    let deletion = P2PMessageDeletion {
+        message_hash,
+        deleted_by: me.clone(),
+        scope: delete_message_input.scope,
+        time_deleted: sys_time()?,
+    };
+
+    if deletion.scope == DeleteScope::ForEveryone && message.author != me {
+        return error(P2PMessageError::Unauthorized(String::from(
+            "Sorry. Only the author of a message can delete it for everyone.",
+        )));
+    }
+
+    let deletion_entry = Entry::App(deletion.clone().try_into()?);
+    let deletion_hash = host_call::<CreateInput, ActionHash>(
+        __hc__create_1,
+        CreateInput::new(
+            EntryDefLocation::app(this_zome_index()?, 7),
+            EntryVisibility::Private,
+            deletion_entry,
+            ChainTopOrdering::Relaxed,
+        ),
+    )?;
+
+    // message self. an unreachable receiver gets the retraction from the outbox later
+    if deletion.scope == DeleteScope::ForEveryone && message.receiver != me {
+        deliver_change_handler(hash_entry(&deletion)?)?;
+    }
+
+    let mut deletion_contents: HashMap<String, P2PMessageDeletion> = HashMap::new();
+    deletion_contents.insert(deletion_hash.to_string(), deletion);
+
+    Ok(deletion_contents)
+}
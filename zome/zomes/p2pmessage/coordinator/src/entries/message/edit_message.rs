@@ -0,0 +1,99 @@
+use hdk::prelude::*;
+use std::collections::HashMap;
+
+use p2pmessage_coordinator_types::*;
+use p2pmessage_integrity_types::*;
+
+use crate::{
+    encryption::{decrypt_message, encrypt_payload},
+    helpers::{
+        get_deletions_of_messages, get_edits, get_message_from_chain, get_replies,
+        insert_message, insert_reply, message_key,
+    },
+    outbox::deliver_change_handler,
+    search_messages::index_messages,
+    utils::error,
+};
+
+use super::utils::this_zome_index;
+
+pub fn edit_message_handler(
+    edit_message_input: EditMessageInput,
+) -> ExternResult<(EntryHash, P2PMessageData)> {
+    let message_hash = edit_message_input.message_hash;
+    let message = decrypt_message(get_message_from_chain(message_hash.clone())?)?;
+
+    if message.author != agent_info()?.agent_latest_pubkey {
+        return error(P2PMessageError::Unauthorized(String::from(
+            "Sorry. Only the author of a message can edit it.",
+        )));
+    }
+
+    if get_deletions_of_messages(&message_key(&message_hash, &message)?)?
+        .contains_key(&message_hash.clone().to_string())
+    {
+        return error(P2PMessageError::ValidationFailed(String::from(
+            "Sorry. A deleted message cannot be edited.",
+        )));
+    }
+
+    let payload = match (edit_message_input.payload, message.payload.clone()) {
+        (PayloadInput::Text { payload }, Payload::Text { .. }) => Payload::Text { payload },
+        _ => {
+            return error(P2PMessageError::ValidationFailed(String::from(
+                "Sorry. Only the text of a text message can be edited.",
+            )))
+        }
+    };
+
+    // the search index covers the earlier calls only, this one has not committed yet
+    let _res = index_messages();
+
+    let edit = P2PMessageEdit {
+        message_hash: message_hash.clone(),
+        author: message.author.clone(),
+        receiver: message.receiver.clone(),
+        payload: encrypt_payload(payload, message.receiver.clone())?,
+        time_edited: sys_time()?,
+    };
+
+    let edit_entry = Entry::App(edit.clone().try_into()?);
+    let edit_hash = hash_entry(&edit)?;
+    host_call::<CreateInput, ActionHash>(
+        __hc__create_1,
+        CreateInput::new(
+            EntryDefLocation::app(this_zome_index()?, 6),
+            EntryVisibility::Private,
+            edit_entry,
+            ChainTopOrdering::Relaxed,
+        ),
+    )?;
+
+    // message self. an unreachable receiver gets the edit from the outbox later
+    if message.author != message.receiver {
+        deliver_change_handler(edit_hash)?;
+    }
+
+    // build the message data the same way the getters do
+    let mut agent_messages: HashMap<String, Vec<String>> = HashMap::new();
+    let mut message_contents: HashMap<String, (P2PMessageData, Vec<String>)> = HashMap::new();
+    let mut reply_pairs: HashMap<String, Vec<String>> = HashMap::new();
+
+    insert_reply(&mut reply_pairs, message.clone(), message_hash.clone());
+    insert_message(
+        &mut agent_messages,
+        &mut message_contents,
+        message.clone(),
+        message_hash.clone(),
+        message.receiver.clone(),
+    )?;
+    get_replies(&mut reply_pairs, &mut message_contents)?;
+    get_edits(&mut message_contents)?;
+
+    match message_contents.remove(&message_hash.clone().to_string()) {
+        Some((message_data, _)) => Ok((message_hash, message_data)),
+        None => error(P2PMessageError::NotFound(String::from(
+            "Sorry. Message entry for hash not found.",
+        ))),
+    }
+}
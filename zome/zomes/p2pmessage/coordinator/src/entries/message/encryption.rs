@@ -0,0 +1,264 @@
+use hdk::prelude::*;
+use std::collections::HashMap;
+
+use p2pmessage_coordinator_types::*;
+use p2pmessage_integrity_types::*;
+
+use crate::{
+    chain_index::reindex_encrypted,
+    is_user_blocked::is_user_blocked,
+    search_messages::reindex_decrypted,
+    utils::{call_agent, decode_error, error, zome_call_error},
+};
+
+use super::utils::this_zome_index;
+
+/*
+ * KEY EXCHANGE
+ * both conversants keep their own x25519 key and the key of the other on their chain,
+ * messages are encrypted once both keys are known
+ */
+
+pub fn enable_encryption_handler(conversant: AgentPubKey) -> ExternResult<P2PEncryptionKey> {
+    let me = agent_info()?.agent_latest_pubkey;
+
+    if conversant == me {
+        return error(P2PMessageError::ValidationFailed(String::from(
+            "Sorry. Messages to yourself are not encrypted.",
+        )));
+    }
+
+    if is_user_blocked(conversant.clone())? {
+        return error(P2PMessageError::Blocked(String::from(
+            "Sorry. You have blocked this agent.",
+        )));
+    }
+
+    let own_key = get_or_create_own_key()?;
+
+    let zome_call_response: ZomeCallResponse =
+        call_agent(conversant.clone(), "exchange_keys", &own_key)?;
+
+    match zome_call_response {
+        ZomeCallResponse::Ok(extern_io) => {
+            let conversant_key_result: Result<P2PEncryptionKey, SerializedBytesError> =
+                extern_io.decode();
+            match conversant_key_result {
+                Ok(conversant_key) if conversant_key.agent == conversant => {
+                    commit_key(conversant_key.clone())?;
+                    // the index only lags behind, see chain_index.rs
+                    let _res = reindex_encrypted(&conversant);
+                    let _res = reindex_decrypted();
+                    Ok(conversant_key)
+                }
+                Ok(_) => error(P2PMessageError::ValidationFailed(String::from(
+                    "Sorry. The key returned does not belong to this agent.",
+                ))),
+                Err(e) => decode_error(e),
+            }
+        }
+        failed => zome_call_error(failed),
+    }
+}
+
+pub fn exchange_keys_handler(key: P2PEncryptionKey) -> ExternResult<P2PEncryptionKey> {
+    let caller = call_info()?.provenance;
+
+    if is_user_blocked(caller.clone())? {
+        return error(P2PMessageError::Blocked(String::from(
+            "Sorry. The key exchange was declined.",
+        )));
+    }
+
+    if key.agent != caller {
+        return error(P2PMessageError::Unauthorized(String::from(
+            "Sorry. Keys can only be exchanged by the agent they belong to.",
+        )));
+    }
+
+    let own_key = get_or_create_own_key()?;
+    commit_key(key)?;
+    let _res = reindex_encrypted(&caller);
+    let _res = reindex_decrypted();
+
+    Ok(own_key)
+}
+
+pub fn is_conversation_encrypted(conversant: AgentPubKey) -> ExternResult<bool> {
+    let keys = get_keys_from_chain()?;
+    let me = agent_info()?.agent_latest_pubkey;
+
+    Ok(keys.contains_key(&me.to_string()) && keys.contains_key(&conversant.to_string()))
+}
+
+// latest key per agent
+pub fn get_keys_from_chain() -> ExternResult<HashMap<String, P2PEncryptionKey>> {
+    let queried_keys: Vec<Record> = query(
+        QueryFilter::new()
+            .entry_type(EntryType::App(AppEntryDef::new(
+                EntryDefIndex::from(13),
+                this_zome_index()?,
+                EntryVisibility::Private,
+            )))
+            .include_entries(true),
+    )?;
+
+    let mut keys: HashMap<String, P2PEncryptionKey> = HashMap::new();
+    for record in queried_keys.into_iter() {
+        if let Ok(key_entry) = TryInto::<P2PEncryptionKey>::try_into(record) {
+            keys.insert(key_entry.agent.clone().to_string(), key_entry);
+        } else {
+            continue;
+        }
+    }
+
+    Ok(keys)
+}
+
+fn get_or_create_own_key() -> ExternResult<P2PEncryptionKey> {
+    let me = agent_info()?.agent_latest_pubkey;
+
+    if let Some(own_key) = get_keys_from_chain()?.remove(&me.to_string()) {
+        return Ok(own_key);
+    }
+
+    // the secret half stays in the keystore
+    let own_key = P2PEncryptionKey {
+        agent: me,
+        key: create_x25519_keypair()?,
+        time_created: sys_time()?,
+    };
+    commit_key(own_key.clone())?;
+
+    Ok(own_key)
+}
+
+fn commit_key(key: P2PEncryptionKey) -> ExternResult<ActionHash> {
+    let key_entry = Entry::App(key.try_into()?);
+    host_call::<CreateInput, ActionHash>(
+        __hc__create_1,
+        CreateInput::new(
+            EntryDefLocation::app(this_zome_index()?, 13),
+            EntryVisibility::Private,
+            key_entry,
+            ChainTopOrdering::Relaxed,
+        ),
+    )
+}
+
+/*
+ * PAYLOAD ENCRYPTION
+ */
+
+// payloads to conversants without an exchanged key are left as they are
+pub fn encrypt_payload(payload: Payload, receiver: AgentPubKey) -> ExternResult<Payload> {
+    let me = agent_info()?.agent_latest_pubkey;
+    let mut keys = get_keys_from_chain()?;
+
+    match (
+        keys.remove(&me.to_string()),
+        keys.remove(&receiver.to_string()),
+    ) {
+        (Some(own_key), Some(receiver_key)) if receiver != me => {
+            let serialized_payload = SerializedBytes::try_from(payload)
+                .map_err(|e| wasm_error!(WasmErrorInner::Serialize(e)))?;
+            let data = x_25519_x_salsa20_poly1305_encrypt(
+                own_key.key,
+                receiver_key.key,
+                XSalsa20Poly1305Data::from(serialized_payload.bytes().to_vec()),
+            )?;
+
+            Ok(Payload::Encrypted {
+                sender_key: own_key.key,
+                receiver_key: receiver_key.key,
+                data,
+            })
+        }
+        _ => Ok(payload),
+    }
+}
+
+/*
+ * the author decrypts with the sender key and the receiver with the receiver key,
+ * payloads that cannot be decrypted are returned still encrypted
+ */
+pub fn decrypt_payload(payload: Payload, author: &AgentPubKey) -> ExternResult<Payload> {
+    if let Payload::Encrypted {
+        ref sender_key,
+        ref receiver_key,
+        ref data,
+    } = payload
+    {
+        let (own_key, other_key) = if *author == agent_info()?.agent_latest_pubkey {
+            (*sender_key, *receiver_key)
+        } else {
+            (*receiver_key, *sender_key)
+        };
+
+        if let Some(decrypted) =
+            x_25519_x_salsa20_poly1305_decrypt(own_key, other_key, data.clone())?
+        {
+            let serialized_payload =
+                SerializedBytes::from(UnsafeBytes::from(decrypted.as_ref().to_vec()));
+            if let Ok(decrypted_payload) = Payload::try_from(serialized_payload) {
+                return Ok(decrypted_payload);
+            }
+        }
+    }
+
+    Ok(payload)
+}
+
+pub fn decrypt_message(message: P2PMessage) -> ExternResult<P2PMessage> {
+    let payload = decrypt_payload(message.payload.clone(), &message.author)?;
+
+    Ok(P2PMessage {
+        payload,
+        ..message
+    })
+}
+
+// file bytes are only encrypted while they travel to the receiver
+pub fn encrypt_file(
+    file: P2PFileBytes,
+    message: &P2PMessage,
+) -> ExternResult<Option<XSalsa20Poly1305EncryptedData>> {
+    match message.payload {
+        Payload::Encrypted {
+            ref sender_key,
+            ref receiver_key,
+            ..
+        } => Ok(Some(x_25519_x_salsa20_poly1305_encrypt(
+            *sender_key,
+            *receiver_key,
+            XSalsa20Poly1305Data::from(file.0.bytes().to_vec()),
+        )?)),
+        _ => Ok(None),
+    }
+}
+
+pub fn decrypt_file(
+    encrypted_file: XSalsa20Poly1305EncryptedData,
+    message: &P2PMessage,
+) -> ExternResult<P2PFileBytes> {
+    if let Payload::Encrypted {
+        ref sender_key,
+        ref receiver_key,
+        ..
+    } = message.payload
+    {
+        if let Some(decrypted) = x_25519_x_salsa20_poly1305_decrypt(
+            *receiver_key,
+            *sender_key,
+            encrypted_file,
+        )? {
+            return Ok(P2PFileBytes(SerializedBytes::from(UnsafeBytes::from(
+                decrypted.as_ref().to_vec(),
+            ))));
+        }
+    }
+
+    error(P2PMessageError::DecodeFailure(String::from(
+        "Sorry. The file could not be decrypted.",
+    )))
+}
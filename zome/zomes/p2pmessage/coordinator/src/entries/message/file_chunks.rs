@@ -0,0 +1,445 @@
+use hdk::prelude::*;
+use std::collections::{hash_map, HashMap, HashSet};
+
+use p2pmessage_coordinator_types::*;
+use p2pmessage_integrity_types::*;
+
+use crate::{
+    chain_index::{get_chain_index, get_message_records, query_by_hashes},
+    encryption::{decrypt_file, decrypt_payload, encrypt_file},
+    helpers::{get_file_from_chain, get_message_from_chain},
+    is_user_blocked::is_user_blocked,
+    message_requests::get_queued_message,
+    utils::{call_agent, decode_error, error, error_reason},
+    verify_file::{received_file_error, verify_received_file},
+};
+
+use super::utils::this_zome_index;
+
+/*
+ * CHUNKED FILE TRANSFER
+ * files larger than one chunk are stored as several P2PFileBytes entries listed in the
+ * FileMetadata of the message, the chunks follow the message in separate remote calls.
+ * messages of an open request take their chunks too, the file is reassembled from them
+ * like any other once the request is accepted. group messages send their chunks the same
+ * way, unencrypted like the rest of the group message
+ */
+
+pub const FILE_CHUNK_SIZE: usize = 1024 * 1024;
+
+// the number of chunks split_file makes of a file of file_size bytes
+pub fn chunk_count(file_size: usize) -> usize {
+    file_size.div_ceil(FILE_CHUNK_SIZE)
+}
+
+// the size split_file gives the chunk at index
+pub fn chunk_size(file_size: usize, index: usize) -> usize {
+    std::cmp::min(
+        FILE_CHUNK_SIZE,
+        file_size.saturating_sub(index * FILE_CHUNK_SIZE),
+    )
+}
+
+// the message a received chunk belongs to
+enum ChunkedMessage {
+    Direct(P2PMessage),
+    Group(P2PGroupMessage),
+}
+
+impl ChunkedMessage {
+    fn author(&self) -> &AgentPubKey {
+        match self {
+            ChunkedMessage::Direct(message) => &message.author,
+            ChunkedMessage::Group(message) => &message.author,
+        }
+    }
+
+    fn payload(&self) -> ExternResult<Payload> {
+        match self {
+            ChunkedMessage::Direct(message) => {
+                decrypt_payload(message.payload.clone(), &message.author)
+            }
+            ChunkedMessage::Group(message) => Ok(message.payload.clone()),
+        }
+    }
+}
+
+pub fn split_file(file_bytes: &SerializedBytes) -> Vec<P2PFileBytes> {
+    file_bytes
+        .bytes()
+        .chunks(FILE_CHUNK_SIZE)
+        .map(|chunk| P2PFileBytes(SerializedBytes::from(UnsafeBytes::from(chunk.to_vec()))))
+        .collect()
+}
+
+pub fn commit_file_chunk(chunk: P2PFileBytes) -> ExternResult<ActionHash> {
+    let chunk_entry = Entry::App(chunk.try_into()?);
+    host_call::<CreateInput, ActionHash>(
+        __hc__create_1,
+        CreateInput::new(
+            EntryDefLocation::app(this_zome_index()?, 3),
+            EntryVisibility::Private,
+            chunk_entry,
+            ChainTopOrdering::Relaxed,
+        ),
+    )
+}
+
+/*
+ * chunks the receiver does not have yet, returns false if the transfer has to be resumed
+ * later and fails for good once the receiver found the file does not match its metadata
+ */
+pub fn send_file_chunks(message_hash: EntryHash, message: &P2PMessage) -> ExternResult<bool> {
+    let metadata = match decrypt_payload(message.payload.clone(), &message.author)? {
+        Payload::File { metadata, .. } if metadata.chunks.len() > 1 => metadata,
+        _ => return Ok(true),
+    };
+
+    let missing_chunks: Vec<usize> = match call_agent(
+        message.receiver.clone(),
+        "get_missing_file_chunks",
+        &message_hash,
+    )? {
+        ZomeCallResponse::Ok(extern_io) => {
+            let missing_chunks_result: Result<Vec<usize>, SerializedBytesError> =
+                extern_io.decode();
+            match missing_chunks_result {
+                Ok(missing_chunks) => missing_chunks,
+                Err(e) => return decode_error(e),
+            }
+        }
+        _ => return Ok(false),
+    };
+
+    for index in missing_chunks.into_iter() {
+        let chunk_hash = match metadata.chunks.get(index) {
+            Some(chunk_hash) => chunk_hash.clone(),
+            None => continue,
+        };
+        let chunk = get_file_from_chain(chunk_hash)?;
+
+        // chunks of encrypted messages travel encrypted as well
+        let encrypted_chunk = encrypt_file(chunk.clone(), message)?;
+        let chunk_input = ReceiveFileChunkInput {
+            message_hash: message_hash.clone(),
+            index,
+            chunk: match encrypted_chunk {
+                Some(_) => None,
+                None => Some(chunk),
+            },
+            encrypted_chunk,
+        };
+
+        match call_agent(message.receiver.clone(), "receive_file_chunk", &chunk_input)? {
+            ZomeCallResponse::Ok(extern_io) => {
+                let chunk_status_result: Result<FileChunkStatus, SerializedBytesError> =
+                    extern_io.decode();
+                match chunk_status_result {
+                    Ok(FileChunkStatus::Received { .. }) => (),
+                    Ok(FileChunkStatus::ValidationFailed { reason }) => {
+                        return Err(wasm_error!(WasmErrorInner::Guest(reason)))
+                    }
+                    Err(e) => return decode_error(e),
+                }
+            }
+            _ => return Ok(false),
+        }
+    }
+
+    Ok(true)
+}
+
+// every chunk of a group file goes to the member after the message, false if one did not arrive
+pub fn send_group_file_chunks(
+    member: &AgentPubKey,
+    message_hash: EntryHash,
+    message: &P2PGroupMessage,
+) -> ExternResult<bool> {
+    let metadata = match message.payload {
+        Payload::File { ref metadata, .. } if metadata.chunks.len() > 1 => metadata,
+        _ => return Ok(true),
+    };
+
+    for (index, chunk_hash) in metadata.chunks.iter().enumerate() {
+        let chunk_input = ReceiveFileChunkInput {
+            message_hash: message_hash.clone(),
+            index,
+            chunk: Some(get_file_from_chain(chunk_hash.clone())?),
+            encrypted_chunk: None,
+        };
+
+        match call_agent(member.clone(), "receive_file_chunk", &chunk_input)? {
+            ZomeCallResponse::Ok(extern_io) => {
+                let chunk_status_result: Result<FileChunkStatus, SerializedBytesError> =
+                    extern_io.decode();
+                match chunk_status_result {
+                    Ok(FileChunkStatus::Received { .. }) => (),
+                    _ => return Ok(false),
+                }
+            }
+            _ => return Ok(false),
+        }
+    }
+
+    Ok(true)
+}
+
+pub fn get_missing_file_chunks_handler(message_hash: EntryHash) -> ExternResult<Vec<usize>> {
+    let message = get_received_message(message_hash)?;
+
+    if *message.author() != call_info()?.provenance {
+        return error(P2PMessageError::Unauthorized(String::from(
+            "Sorry. Only the author of a message can send its file.",
+        )));
+    }
+
+    let metadata = get_chunked_metadata(&message)?;
+    let stored_chunks = get_chunks_from_chain(&metadata)?;
+    let mut missing_chunks: Vec<usize> = Vec::new();
+    for (index, chunk_hash) in metadata.chunks.into_iter().enumerate() {
+        if !stored_chunks.contains_key(&chunk_hash.to_string()) {
+            missing_chunks.push(index);
+        }
+    }
+
+    Ok(missing_chunks)
+}
+
+pub fn receive_file_chunk_handler(
+    chunk_input: ReceiveFileChunkInput,
+) -> ExternResult<FileChunkStatus> {
+    let caller = call_info()?.provenance;
+
+    // chunks from blocked agents are silently dropped
+    if is_user_blocked(caller.clone())? {
+        return Ok(FileChunkStatus::Received {
+            received: 0,
+            total: 0,
+        });
+    }
+
+    let message = get_received_message(chunk_input.message_hash.clone())?;
+    if *message.author() != caller {
+        return error(P2PMessageError::Unauthorized(String::from(
+            "Sorry. Only the author of a message can send its file.",
+        )));
+    }
+
+    let metadata = get_chunked_metadata(&message)?;
+    let chunk = match (chunk_input.chunk, chunk_input.encrypted_chunk, &message) {
+        (_, Some(encrypted_chunk), ChunkedMessage::Direct(message)) => {
+            decrypt_file(encrypted_chunk, message)?
+        }
+        (Some(chunk), None, _) => chunk,
+        _ => {
+            return error(P2PMessageError::ValidationFailed(String::from(
+                "Sorry. The file chunk is empty.",
+            )))
+        }
+    };
+
+    let chunk_hash = hash_entry(&chunk)?;
+    if metadata.chunks.get(chunk_input.index) != Some(&chunk_hash) {
+        return error(P2PMessageError::ValidationFailed(String::from(
+            "Sorry. The file chunk does not match the file of the message.",
+        )));
+    }
+
+    // the chunk count was checked against the file size, so the chunks can not add up to more
+    if chunk.0.bytes().len() != chunk_size(metadata.file_size, chunk_input.index) {
+        return error(P2PMessageError::InvalidFile(String::from(
+            "Sorry. The file chunk does not match the size of the file.",
+        )));
+    }
+
+    let mut stored_chunks = get_chunks_from_chain(&metadata)?;
+    if let hash_map::Entry::Vacant(stored_chunk) = stored_chunks.entry(chunk_hash.to_string()) {
+        commit_file_chunk(chunk.clone())?;
+        stored_chunk.insert(chunk);
+    }
+    let received = stored_chunks.len();
+
+    /*
+     * the whole file is checked against the metadata once its last chunk arrived. the call
+     * succeeds so the sender learns the file failed, and the chunks of the file are deleted
+     */
+    if received == metadata.chunks.len() {
+        let file = reassemble_file(&metadata)?;
+        let verified = match message {
+            ChunkedMessage::Direct(ref message) => {
+                verify_received_file(message, &metadata, file.as_ref())
+            }
+            ChunkedMessage::Group(_) => match received_file_error(&metadata, file.as_ref())? {
+                Some(reason) => error(P2PMessageError::InvalidFile(reason.to_string())),
+                None => Ok(()),
+            },
+        };
+        if let Err(e) = verified {
+            delete_file_chunks(&metadata)?;
+            return Ok(FileChunkStatus::ValidationFailed {
+                reason: error_reason(e),
+            });
+        }
+    }
+
+    let signal = Signal::P2PFileChunk(FileChunkSignal {
+        message_hash: chunk_input.message_hash,
+        received,
+        total: metadata.chunks.len(),
+    });
+
+    let signal_details = SignalDetails {
+        name: "RECEIVE_P2P_FILE_CHUNK".to_string(),
+        payload: signal,
+    };
+    emit_signal(&signal_details)?;
+
+    Ok(FileChunkStatus::Received {
+        received,
+        total: metadata.chunks.len(),
+    })
+}
+
+// the manifest of the message carrying the file, found through the file hashes of the index
+pub fn get_file_manifest(file_hash: EntryHash) -> ExternResult<Option<FileMetadata>> {
+    let file_rows = get_chain_index()?.load_file(&file_hash)?;
+
+    for message in get_message_records(&file_rows)?.into_iter() {
+        if let Ok(message_entry) = TryInto::<P2PMessage>::try_into(message) {
+            if let Payload::File { metadata, .. } =
+                decrypt_payload(message_entry.payload, &message_entry.author)?
+            {
+                if metadata.file_hash == file_hash {
+                    return Ok(Some(metadata));
+                }
+            }
+        }
+    }
+
+    // group messages are not in the chain index
+    let queried_group_messages: Vec<Record> = query(
+        QueryFilter::new()
+            .entry_type(EntryType::App(AppEntryDef::new(
+                EntryDefIndex::from(11),
+                this_zome_index()?,
+                EntryVisibility::Private,
+            )))
+            .include_entries(true),
+    )?;
+    for message in queried_group_messages.into_iter().rev() {
+        if let Ok(P2PGroupMessage {
+            payload: Payload::File { metadata, .. },
+            ..
+        }) = TryInto::<P2PGroupMessage>::try_into(message)
+        {
+            if metadata.file_hash == file_hash {
+                return Ok(Some(metadata));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+// None while some chunks have not arrived yet
+pub fn reassemble_file(metadata: &FileMetadata) -> ExternResult<Option<P2PFileBytes>> {
+    let stored_chunks = get_chunks_from_chain(metadata)?;
+    let mut file_bytes: Vec<u8> = Vec::new();
+    for chunk_hash in metadata.chunks.iter() {
+        match stored_chunks.get(&chunk_hash.to_string()) {
+            Some(chunk) => file_bytes.extend_from_slice(chunk.0.bytes()),
+            None => return Ok(None),
+        }
+    }
+
+    Ok(Some(P2PFileBytes(SerializedBytes::from(
+        UnsafeBytes::from(file_bytes),
+    ))))
+}
+
+fn get_received_message(message_hash: EntryHash) -> ExternResult<ChunkedMessage> {
+    match get_message_from_chain(message_hash.clone()) {
+        Ok(message) => Ok(ChunkedMessage::Direct(message)),
+        Err(e) => match get_queued_message(&message_hash)? {
+            Some(message) => Ok(ChunkedMessage::Direct(message)),
+            None => match query_by_hashes(11, HashSet::from([message_hash]))?.pop() {
+                Some(record) => Ok(ChunkedMessage::Group(TryInto::<P2PGroupMessage>::try_into(
+                    record,
+                )?)),
+                None => Err(e),
+            },
+        },
+    }
+}
+
+fn get_chunked_metadata(message: &ChunkedMessage) -> ExternResult<FileMetadata> {
+    match message.payload()? {
+        Payload::File { metadata, .. } if metadata.chunks.len() != chunk_count(metadata.file_size) => {
+            error(P2PMessageError::InvalidFile(String::from(
+                "Sorry. The number of chunks does not match the file size.",
+            )))
+        }
+        Payload::File { metadata, .. } if !metadata.chunks.is_empty() => Ok(metadata),
+        _ => error(P2PMessageError::ValidationFailed(String::from(
+            "Sorry. This message does not carry a chunked file.",
+        ))),
+    }
+}
+
+// chunks of a file that failed its check are deleted, they are not served or counted again
+fn delete_file_chunks(metadata: &FileMetadata) -> ExternResult<()> {
+    let chunk_hashes: HashSet<EntryHash> = metadata.chunks.iter().cloned().collect();
+    for record in query_by_hashes(3, chunk_hashes)?.into_iter() {
+        host_call::<DeleteInput, ActionHash>(
+            __hc__delete_1,
+            DeleteInput::new(record.action_address().clone(), ChainTopOrdering::Relaxed),
+        )?;
+    }
+
+    Ok(())
+}
+
+fn get_deleted_actions() -> ExternResult<HashSet<ActionHash>> {
+    let queried_deletes: Vec<Record> =
+        query(QueryFilter::new().action_type(ActionType::Delete))?;
+
+    let mut deleted_actions: HashSet<ActionHash> = HashSet::new();
+    for record in queried_deletes.into_iter() {
+        if let Action::Delete(delete) = record.action() {
+            deleted_actions.insert(delete.deletes_address.clone());
+        }
+    }
+
+    Ok(deleted_actions)
+}
+
+// the chunks of the manifest found on this chain, by chunk hash
+fn get_chunks_from_chain(metadata: &FileMetadata) -> ExternResult<HashMap<String, P2PFileBytes>> {
+    let chunk_hashes: HashSet<EntryHash> = metadata.chunks.iter().cloned().collect();
+    get_stored_chunks(chunk_hashes)
+}
+
+// chunks with these hashes that were not deleted, by chunk hash
+pub fn get_stored_chunks(
+    chunk_hashes: HashSet<EntryHash>,
+) -> ExternResult<HashMap<String, P2PFileBytes>> {
+    let queried_files = query_by_hashes(3, chunk_hashes.clone())?;
+    let deleted_actions = get_deleted_actions()?;
+
+    let mut chunks: HashMap<String, P2PFileBytes> = HashMap::new();
+    for record in queried_files.into_iter() {
+        if deleted_actions.contains(record.action_address()) {
+            continue;
+        }
+        if let Ok(file_entry) = TryInto::<P2PFileBytes>::try_into(record) {
+            let file_hash = hash_entry(&file_entry)?;
+            if chunk_hashes.contains(&file_hash) {
+                chunks.insert(file_hash.to_string(), file_entry);
+            }
+        } else {
+            continue;
+        }
+    }
+
+    Ok(chunks)
+}
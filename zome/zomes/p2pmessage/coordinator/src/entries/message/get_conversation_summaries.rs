@@ -0,0 +1,127 @@
+use hdk::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+use p2pmessage_coordinator_types::*;
+use p2pmessage_integrity_types::*;
+
+use crate::{
+    chain_index::{get_chain_index, get_change_records, get_message_records},
+    get_pinned_messages::get_current_pins,
+    helpers::{get_deletions, get_edits, get_reactions, get_receipts_from_rows, insert_message},
+    is_user_blocked::get_blocked_agents_from_chain,
+};
+
+pub fn get_conversation_summaries_handler(
+    exclude_blocked: bool,
+) -> ExternResult<Vec<ConversationSummary>> {
+    let blocked_agents = match exclude_blocked {
+        true => get_blocked_agents_from_chain()?,
+        false => Vec::new(),
+    };
+
+    // unread counts cover the whole conversation, so every bucket of the listed agents is loaded
+    let rows = get_chain_index()?.load(|conversant, _day| !blocked_agents.contains(conversant))?;
+    let pin_records = get_change_records(2, &rows.pins)?;
+    let mut conversations: HashMap<String, Vec<IndexedMessage>> = HashMap::new();
+    for row in rows.messages.into_iter() {
+        conversations
+            .entry(row.conversant.to_string())
+            .or_default()
+            .push(row);
+    }
+
+    // every receipt of every conversation, matched to its messages
+    let message_hashes: HashSet<String> = conversations
+        .values()
+        .flatten()
+        .map(|row| row.hash.to_string())
+        .collect();
+    let mut read_hashes: HashSet<String> = HashSet::new();
+    let mut receipt_times: HashMap<String, Timestamp> = HashMap::new();
+    for (_receipt_hash, receipt_entry) in
+        get_receipts_from_rows(rows.receipts, &message_hashes)?.into_iter()
+    {
+        let timestamp = match receipt_entry.status {
+            Status::Sent { timestamp } | Status::Delivered { timestamp } => timestamp,
+            Status::Read { timestamp } => {
+                read_hashes.extend(receipt_entry.id.iter().map(|hash| hash.to_string()));
+                timestamp
+            }
+        };
+        for message_id in receipt_entry.id.iter() {
+            let receipt_time = receipt_times
+                .entry(message_id.to_string())
+                .or_insert(timestamp);
+            *receipt_time = std::cmp::max(*receipt_time, timestamp);
+        }
+    }
+
+    let last_rows: Vec<IndexedMessage> = conversations
+        .values()
+        .filter_map(|rows| rows.iter().max_by_key(|row| row.time_sent).cloned())
+        .collect();
+    let mut agent_messages: HashMap<String, Vec<String>> = HashMap::new();
+    let mut message_contents: HashMap<String, (P2PMessageData, Vec<String>)> = HashMap::new();
+    for message in get_message_records(&last_rows)?.into_iter() {
+        if let Ok(message_entry) = TryInto::<P2PMessage>::try_into(message) {
+            let message_hash = hash_entry(&message_entry)?;
+            let key = message_entry.receiver.clone();
+            insert_message(
+                &mut agent_messages,
+                &mut message_contents,
+                message_entry,
+                message_hash,
+                key,
+            )?;
+        } else {
+            continue;
+        }
+    }
+
+    get_edits(&mut message_contents)?;
+
+    get_reactions(&mut message_contents)?;
+
+    get_deletions(&mut message_contents)?;
+
+    let mut summaries: Vec<ConversationSummary> = Vec::new();
+    for last_row in last_rows.into_iter() {
+        let rows = match conversations.get(&last_row.conversant.to_string()) {
+            Some(rows) => rows,
+            None => continue,
+        };
+        let last_message = match message_contents.remove(&last_row.hash.to_string()) {
+            Some((message_data, _)) => message_data,
+            None => continue,
+        };
+
+        let last_activity = rows
+            .iter()
+            .filter_map(|row| receipt_times.get(&row.hash.to_string()))
+            .copied()
+            .fold(last_row.time_sent, std::cmp::max);
+        let unread_count = rows
+            .iter()
+            .filter(|row| !row.from_me && !read_hashes.contains(&row.hash.to_string()))
+            .count();
+        let last_outgoing_read = rows
+            .iter()
+            .filter(|row| row.from_me)
+            .max_by_key(|row| row.time_sent)
+            .is_some_and(|row| read_hashes.contains(&row.hash.to_string()));
+
+        summaries.push(ConversationSummary {
+            pinned_count: get_current_pins(&last_row.conversant, pin_records.clone())?.len(),
+            conversant: last_row.conversant,
+            last_message: (last_row.hash, last_message),
+            last_activity,
+            unread_count,
+            last_outgoing_read,
+        });
+    }
+
+    // the most recently active conversation first
+    summaries.sort_by_key(|summary| std::cmp::Reverse(summary.last_activity));
+
+    Ok(summaries)
+}
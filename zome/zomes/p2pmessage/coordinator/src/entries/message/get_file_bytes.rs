@@ -1,30 +1,34 @@
 use hdk::prelude::*;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
+use p2pmessage_coordinator_types::*;
 use p2pmessage_integrity_types::*;
 
-use super::utils::this_zome_index;
+use crate::{
+    chain_index::{get_chain_index, query_by_hashes},
+    file_chunks::{get_file_manifest, get_stored_chunks, reassemble_file},
+    helpers::get_deletions_of_messages,
+    utils::error,
+};
 
 pub fn get_file_bytes_handler(
     file_hashes: Vec<EntryHash>,
 ) -> ExternResult<HashMap<String, P2PFileBytes>> {
-    let queried_files: Vec<Record> = query(
-        QueryFilter::new()
-            .entry_type(EntryType::App(AppEntryDef::new(
-                EntryDefIndex::from(3),
-                this_zome_index()?,
-                EntryVisibility::Private,
-            )))
-            .include_entries(true),
-    )?;
+    let queried_files = query_by_hashes(3, file_hashes.iter().cloned().collect::<HashSet<_>>())?;
 
     let mut files: HashMap<String, P2PFileBytes> = HashMap::new();
+    let mut deleted_file_hashes: Vec<EntryHash> = Vec::new();
+    for file_hash in file_hashes.iter() {
+        if is_file_deleted(file_hash)? {
+            deleted_file_hashes.push(file_hash.clone());
+        }
+    }
 
     for file in queried_files.into_iter() {
         if let Ok(file_entry) = TryInto::<P2PFileBytes>::try_into(file.clone()) {
             let file_hash = hash_entry(&file_entry)?;
 
-            if file_hashes.contains(&file_hash) {
+            if file_hashes.contains(&file_hash) && !deleted_file_hashes.contains(&file_hash) {
                 match files.get(&file_hash.clone().to_string()) {
                     Some(_file) => continue,
                     _ => {
@@ -38,5 +42,63 @@ pub fn get_file_bytes_handler(
         }
     }
 
+    // chunked files are reassembled once all of their chunks are on this chain
+    for file_hash in file_hashes.into_iter() {
+        if files.contains_key(&file_hash.to_string()) || deleted_file_hashes.contains(&file_hash) {
+            continue;
+        }
+        if let Some(metadata) = get_file_manifest(file_hash.clone())? {
+            if let Some(file_bytes) = reassemble_file(&metadata)? {
+                files.insert(file_hash.to_string(), file_bytes);
+            }
+        }
+    }
+
     Ok(files)
 }
+
+pub fn get_file_chunk_handler(filter: FileChunkFilter) -> ExternResult<P2PFileBytes> {
+    if is_file_deleted(&filter.file_hash)? {
+        return error(P2PMessageError::ValidationFailed(String::from(
+            "Sorry. The file was deleted.",
+        )));
+    }
+
+    let metadata = match get_file_manifest(filter.file_hash)? {
+        Some(metadata) => metadata,
+        None => {
+            return error(P2PMessageError::NotFound(String::from(
+                "Sorry. File entry for hash not found.",
+            )))
+        }
+    };
+
+    // files stored whole have a single chunk
+    let chunk_hash = match metadata.chunks.get(filter.index) {
+        Some(chunk_hash) => chunk_hash.clone(),
+        None if filter.index == 0 && metadata.chunks.is_empty() => metadata.file_hash,
+        None => {
+            return error(P2PMessageError::ValidationFailed(String::from(
+                "Sorry. The file has no chunk at this index.",
+            )))
+        }
+    };
+
+    match get_stored_chunks(HashSet::from([chunk_hash.clone()]))?.remove(&chunk_hash.to_string()) {
+        Some(chunk) => Ok(chunk),
+        None => error(P2PMessageError::NotFound(String::from(
+            "Sorry. File entry for hash not found.",
+        ))),
+    }
+}
+
+// bytes of deleted file messages are no longer served, only the messages carrying the file are checked
+fn is_file_deleted(file_hash: &EntryHash) -> ExternResult<bool> {
+    let file_messages: HashMap<String, (AgentPubKey, Timestamp)> = get_chain_index()?
+        .load_file(file_hash)?
+        .into_iter()
+        .map(|row| (row.hash.to_string(), (row.conversant, row.time_sent)))
+        .collect();
+
+    Ok(!get_deletions_of_messages(&file_messages)?.is_empty())
+}
@@ -4,21 +4,40 @@ use std::collections::HashMap;
 use p2pmessage_coordinator_types::*;
 use p2pmessage_integrity_types::*;
 
-use crate::helpers::{get_receipts, get_replies, insert_message, insert_reply};
+use crate::{
+    chain_index::{get_chain_index, get_message_records},
+    helpers::{
+        get_deletions, get_edits, get_reactions, get_receipts, get_replies, insert_message,
+        insert_reply,
+    },
+    is_user_blocked::get_blocked_agents_from_chain,
+};
 
-use super::utils::this_zome_index;
+pub fn get_latest_messages_handler(
+    filter: P2PMessageFilterLatest,
+) -> ExternResult<P2PMessageHashTables> {
+    let batch_size = filter.batch_size;
+    let blocked_agents = match filter.exclude_blocked {
+        true => get_blocked_agents_from_chain()?,
+        false => Vec::new(),
+    };
 
-pub fn get_latest_messages_handler(batch_size: u8) -> ExternResult<P2PMessageHashTables> {
-    let mut queried_messages: Vec<Record> = query(
-        QueryFilter::new()
-            .entry_type(EntryType::App(AppEntryDef::new(
-                EntryDefIndex::from(0),
-                this_zome_index()?,
-                EntryVisibility::Private,
-            )))
-            .include_entries(true),
-    )?;
-    queried_messages.reverse();
+    // the latest batch_size rows of every conversation, newest first
+    let chain_index = get_chain_index()?;
+    let mut selected_rows: Vec<IndexedMessage> = Vec::new();
+    for conversant in chain_index.conversants().into_iter() {
+        if blocked_agents.contains(&conversant) {
+            continue;
+        }
+        let rows = chain_index.load_conversation(
+            &conversant,
+            Timestamp::from_micros(i64::MAX),
+            false,
+            |rows| rows.len() >= batch_size.into(),
+        )?;
+        selected_rows.extend(rows.into_iter().take(batch_size.into()));
+    }
+    let queried_messages = get_message_records(&selected_rows)?;
     let mut agent_messages: HashMap<String, Vec<String>> = HashMap::new();
     let mut message_contents: HashMap<String, (P2PMessageData, Vec<String>)> = HashMap::new();
     let mut receipt_contents: HashMap<String, P2PMessageReceipt> = HashMap::new();
@@ -28,6 +47,12 @@ pub fn get_latest_messages_handler(batch_size: u8) -> ExternResult<P2PMessageHas
         if let Ok(message_entry) = TryInto::<P2PMessage>::try_into(message.clone()) {
             let message_hash: EntryHash = hash_entry(&message_entry)?;
 
+            if blocked_agents.contains(&message_entry.author)
+                || blocked_agents.contains(&message_entry.receiver)
+            {
+                continue;
+            }
+
             if message_entry.author.clone() == agent_info()?.agent_latest_pubkey {
                 match agent_messages.get(&message_entry.receiver.clone().to_string()) {
                     Some(messages) if messages.len() >= batch_size.into() => {
@@ -115,6 +140,12 @@ pub fn get_latest_messages_handler(batch_size: u8) -> ExternResult<P2PMessageHas
 
     get_replies(&mut reply_pairs, &mut message_contents)?;
 
+    get_edits(&mut message_contents)?;
+
+    get_reactions(&mut message_contents)?;
+
+    get_deletions(&mut message_contents)?;
+
     Ok(P2PMessageHashTables(
         agent_messages,
         message_contents,
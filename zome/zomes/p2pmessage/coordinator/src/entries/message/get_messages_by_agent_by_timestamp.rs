@@ -4,23 +4,39 @@ use std::collections::HashMap;
 use p2pmessage_coordinator_types::*;
 use p2pmessage_integrity_types::*;
 
-use crate::helpers::{get_receipts, get_replies, insert_message, insert_reply};
-
-use super::utils::this_zome_index;
+use crate::{
+    chain_index::{day_of, get_chain_index, get_message_records, matches_payload_filter},
+    helpers::{
+        get_deletions, get_edits, get_reactions, get_receipts, get_replies, insert_message,
+        insert_reply,
+    },
+};
 
 pub fn get_messages_by_agent_by_timestamp_handler(
     filter: P2PMessageFilterAgentTimestamp,
 ) -> ExternResult<P2PMessageHashTables> {
-    let mut queried_messages: Vec<Record> = query(
-        QueryFilter::new()
-            .entry_type(EntryType::App(AppEntryDef::new(
-                EntryDefIndex::from(0),
-                this_zome_index()?,
-                EntryVisibility::Private,
-            )))
-            .include_entries(true),
-    )?;
-    queried_messages.reverse();
+    // input is in microseconds since epoch
+    let day_start = filter.date.as_micros();
+    let day_end = day_start + 86399 * 1000000;
+
+    // TODO: use header timestamp for message_time
+    let first_day = day_of(Timestamp::from_micros(day_start));
+    let last_day = day_of(Timestamp::from_micros(day_end));
+    let selected_rows: Vec<IndexedMessage> = get_chain_index()?
+        .load(|conversant, day| {
+            *conversant == filter.conversant && day >= first_day && day <= last_day
+        })?
+        .messages
+        .into_iter()
+        .rev()
+        .filter(|row| {
+            row.conversant == filter.conversant
+                && row.time_sent.as_micros() >= day_start
+                && row.time_sent.as_micros() <= day_end
+                && matches_payload_filter(row, &filter.payload_filter)
+        })
+        .collect();
+    let queried_messages = get_message_records(&selected_rows)?;
 
     let mut agent_messages: HashMap<String, Vec<String>> = HashMap::new();
     agent_messages.insert(filter.conversant.clone().to_string(), Vec::new());
@@ -28,60 +44,25 @@ pub fn get_messages_by_agent_by_timestamp_handler(
     let mut receipt_contents: HashMap<String, P2PMessageReceipt> = HashMap::new();
     let mut reply_pairs: HashMap<String, Vec<String>> = HashMap::new();
 
-    // input is in microseconds since epoch
-    let day_start = filter.date.as_micros();
-    let day_end = day_start + 86399 * 1000000;
-
     for message in queried_messages.into_iter() {
-        if let Ok(message_entry) = TryInto::<P2PMessage>::try_into(message.clone()) {
+        if let Ok(message_entry) = TryInto::<P2PMessage>::try_into(message) {
             let message_hash = hash_entry(&message_entry)?;
 
-            // TODO: use header timestamp for message_time
-            if message_entry.time_sent.as_micros() >= day_start
-                && message_entry.time_sent.as_micros() <= day_end
-                && (message_entry.author == filter.conversant
-                    || message_entry.receiver == filter.conversant)
-            {
-                match message_entry.payload {
-                    Payload::Text { .. } => {
-                        if filter.payload_type == "Text" || filter.payload_type == "All" {
-                            if message_entry.reply_to != None {
-                                insert_reply(
-                                    &mut reply_pairs,
-                                    message_entry.clone(),
-                                    message_hash.clone(),
-                                );
-                            }
-                            insert_message(
-                                &mut agent_messages,
-                                &mut message_contents,
-                                message_entry,
-                                message_hash,
-                                filter.conversant.clone(),
-                            )?;
-                        }
-                    }
-                    Payload::File { .. } => {
-                        if filter.payload_type == "File" || filter.payload_type == "All" {
-                            if message_entry.reply_to != None {
-                                insert_reply(
-                                    &mut reply_pairs,
-                                    message_entry.clone(),
-                                    message_hash.clone(),
-                                );
-                            }
-
-                            insert_message(
-                                &mut agent_messages,
-                                &mut message_contents,
-                                message_entry,
-                                message_hash,
-                                filter.conversant.clone(),
-                            )?;
-                        }
-                    }
-                }
+            if message_entry.reply_to.is_some() {
+                insert_reply(
+                    &mut reply_pairs,
+                    message_entry.clone(),
+                    message_hash.clone(),
+                );
             }
+
+            insert_message(
+                &mut agent_messages,
+                &mut message_contents,
+                message_entry,
+                message_hash,
+                filter.conversant.clone(),
+            )?;
         } else {
             continue;
         }
@@ -91,6 +72,12 @@ pub fn get_messages_by_agent_by_timestamp_handler(
 
     get_replies(&mut reply_pairs, &mut message_contents)?;
 
+    get_edits(&mut message_contents)?;
+
+    get_reactions(&mut message_contents)?;
+
+    get_deletions(&mut message_contents)?;
+
     Ok(P2PMessageHashTables(
         agent_messages,
         message_contents,
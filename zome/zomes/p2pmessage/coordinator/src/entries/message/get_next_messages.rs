@@ -4,144 +4,79 @@ use std::collections::HashMap;
 use p2pmessage_coordinator_types::*;
 use p2pmessage_integrity_types::*;
 
-use crate::helpers::{get_receipts, get_replies, insert_message, insert_reply};
-
-use super::utils::this_zome_index;
+use crate::{
+    chain_index::{get_chain_index, get_message_records, matches_payload_filter},
+    helpers::{
+        get_deletions, get_edits, get_reactions, get_receipts, get_replies, insert_message,
+        insert_reply,
+    },
+};
 
 pub fn get_next_messages_handler(
     filter: P2PMessageFilterBatch,
 ) -> ExternResult<P2PMessageHashTables> {
-    let mut queried_messages: Vec<Record> = query(
-        QueryFilter::new()
-            .entry_type(EntryType::App(AppEntryDef::new(
-                EntryDefIndex::from(0),
-                this_zome_index()?,
-                EntryVisibility::Private,
-            )))
-            .include_entries(true),
-    )?;
-    queried_messages.reverse();
+    let filter_timestamp = match filter.last_fetched_timestamp {
+        Some(timestamp) => timestamp,
+        None => sys_time()?,
+    };
+
+    // the batch after the last fetched message, newest first
+    let is_selected = |row: &IndexedMessage| {
+        row.time_sent.as_micros() >= filter_timestamp.as_micros()
+            && filter.last_fetched_message_id.as_ref() != Some(&row.hash)
+            && matches_payload_filter(row, &filter.payload_filter)
+    };
+    let mut selected_rows: Vec<IndexedMessage> = get_chain_index()?
+        .load_conversation(&filter.conversant, filter_timestamp, true, |rows| {
+            rows.iter().filter(|row| is_selected(row)).count() >= filter.batch_size.into()
+        })?
+        .into_iter()
+        .filter(is_selected)
+        .collect();
+    let selected_rows =
+        selected_rows.split_off(selected_rows.len().saturating_sub(filter.batch_size.into()));
+    let queried_messages = get_message_records(&selected_rows)?;
 
     let mut agent_messages: HashMap<String, Vec<String>> = HashMap::new();
     agent_messages.insert(filter.conversant.clone().to_string(), Vec::new());
     let mut message_contents: HashMap<String, (P2PMessageData, Vec<String>)> = HashMap::new();
     let mut receipt_contents: HashMap<String, P2PMessageReceipt> = HashMap::new();
     let mut reply_pairs: HashMap<String, Vec<String>> = HashMap::new();
-    let mut later_message_hashes: Vec<EntryHash> = Vec::new();
-    let mut later_messages: Vec<P2PMessage> = Vec::new();
-
-    let filter_timestamp = match filter.last_fetched_timestamp {
-        Some(timestamp) => timestamp,
-        None => sys_time()?,
-    };
 
     for message in queried_messages.into_iter() {
-        if let Ok(message_entry) = TryInto::<P2PMessage>::try_into(message.clone()) {
+        if let Ok(message_entry) = TryInto::<P2PMessage>::try_into(message) {
             let message_hash = hash_entry(&message_entry)?;
 
-            if (message_entry.time_sent.as_micros() >= filter_timestamp.as_micros())
-                && (match filter.last_fetched_message_id {
-                    Some(ref id) if *id == message_hash => false,
-                    Some(ref id) if *id != message_hash => true,
-                    _ => false,
-                } || filter.last_fetched_message_id == None)
-                && (message_entry.author == filter.conversant
-                    || message_entry.receiver == filter.conversant)
-            {
-                match message_entry.payload {
-                    Payload::Text { .. } => {
-                        if filter.payload_type == "Text" || filter.payload_type == "All" {
-                            if message_entry.reply_to != None {
-                                insert_reply(
-                                    &mut reply_pairs,
-                                    message_entry.clone(),
-                                    message_hash.clone(),
-                                );
-                            }
-
-                            later_message_hashes.push(message_hash);
-                            later_messages.push(message_entry);
-                        }
-                    }
-                    Payload::File { ref file_type, .. } => match file_type {
-                        FileType::Image { .. } => {
-                            if filter.payload_type == "Media"
-                                || filter.payload_type == "File"
-                                || filter.payload_type == "All"
-                            {
-                                if message_entry.reply_to != None {
-                                    insert_reply(
-                                        &mut reply_pairs,
-                                        message_entry.clone(),
-                                        message_hash.clone(),
-                                    );
-                                }
-
-                                later_message_hashes.push(message_hash);
-                                later_messages.push(message_entry);
-                            }
-                        }
-                        FileType::Video { .. } => {
-                            if filter.payload_type == "Media"
-                                || filter.payload_type == "File"
-                                || filter.payload_type == "All"
-                            {
-                                if message_entry.reply_to != None {
-                                    insert_reply(
-                                        &mut reply_pairs,
-                                        message_entry.clone(),
-                                        message_hash.clone(),
-                                    );
-                                }
-
-                                later_message_hashes.push(message_hash);
-                                later_messages.push(message_entry);
-                            }
-                        }
-                        FileType::Other { .. } => {
-                            if filter.payload_type == "Other"
-                                || filter.payload_type == "File"
-                                || filter.payload_type == "All"
-                            {
-                                if message_entry.reply_to != None {
-                                    insert_reply(
-                                        &mut reply_pairs,
-                                        message_entry.clone(),
-                                        message_hash.clone(),
-                                    );
-                                }
-
-                                later_message_hashes.push(message_hash);
-                                later_messages.push(message_entry);
-                            }
-                        }
-                    },
-                }
+            if message_entry.reply_to.is_some() {
+                insert_reply(
+                    &mut reply_pairs,
+                    message_entry.clone(),
+                    message_hash.clone(),
+                );
             }
+
+            insert_message(
+                &mut agent_messages,
+                &mut message_contents,
+                message_entry,
+                message_hash,
+                filter.conversant.clone(),
+            )?;
         } else {
             continue;
         }
     }
 
-    let mut start_index: usize = 0;
-    if later_messages.len() > filter.batch_size.into() {
-        start_index = later_messages.len() as usize - filter.batch_size as usize;
-    }
-
-    for index in start_index..later_messages.len() {
-        insert_message(
-            &mut agent_messages,
-            &mut message_contents,
-            later_messages[index].clone(),
-            later_message_hashes[index].clone(),
-            filter.conversant.clone(),
-        )?;
-    }
-
     get_receipts(&mut message_contents, &mut receipt_contents)?;
 
     get_replies(&mut reply_pairs, &mut message_contents)?;
 
+    get_edits(&mut message_contents)?;
+
+    get_reactions(&mut message_contents)?;
+
+    get_deletions(&mut message_contents)?;
+
     Ok(P2PMessageHashTables(
         agent_messages,
         message_contents,
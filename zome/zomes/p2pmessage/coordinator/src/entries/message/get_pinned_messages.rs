@@ -1,52 +1,101 @@
 use hdk::prelude::*;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use p2pmessage_coordinator_types::*;
 use p2pmessage_integrity_types::*;
 
-use crate::helpers::{get_receipts, insert_message};
-
-use super::utils::this_zome_index;
+use crate::{
+    chain_index::{get_chain_index, get_change_records, query_by_hashes},
+    helpers::{get_deletions, get_edits, get_reactions, get_receipts, insert_message},
+};
 
 pub fn get_pinned_messages_handler(conversant: AgentPubKey) -> ExternResult<P2PMessageHashTables> {
-    let mut queried_pins: Vec<Record> = query(
-        QueryFilter::new()
-            .entry_type(EntryType::App(AppEntryDef::new(
-                EntryDefIndex::from(2),
-                this_zome_index()?,
-                EntryVisibility::Private,
-            )))
-            .include_entries(true),
-    )?;
-
-    let mut queried_messages: Vec<Record> = query(
-        QueryFilter::new()
-            .entry_type(EntryType::App(AppEntryDef::new(
-                EntryDefIndex::from(0),
-                this_zome_index()?,
-                EntryVisibility::Private,
-            )))
-            .include_entries(true),
-    )?;
-
-    queried_messages.reverse();
-    queried_pins.reverse();
-
     let mut agent_messages: HashMap<String, Vec<String>> = HashMap::new();
     agent_messages.insert(conversant.clone().to_string(), Vec::new());
     let mut message_contents: HashMap<String, (P2PMessageData, Vec<String>)> = HashMap::new();
     let mut receipt_contents: HashMap<String, P2PMessageReceipt> = HashMap::new();
 
+    let pinned_messages = get_pins_from_chain(&conversant)?;
+
+    let pinned_hashes: HashSet<EntryHash> = pinned_messages
+        .values()
+        .flat_map(|pin| pin.id.iter().cloned())
+        .collect();
+    let queried_messages = query_by_hashes(0, pinned_hashes)?;
+
+    for message in queried_messages.into_iter() {
+        if let Ok(message_entry) = TryInto::<P2PMessage>::try_into(message) {
+            let message_hash: EntryHash = hash_entry(&message_entry)?;
+
+            if pinned_messages.contains_key(&message_hash.clone().to_string()) {
+                insert_message(
+                    &mut agent_messages,
+                    &mut message_contents,
+                    message_entry.clone(),
+                    message_hash,
+                    message_entry.receiver.clone(),
+                )?;
+            }
+        } else {
+            continue;
+        }
+    }
+
+    get_receipts(&mut message_contents, &mut receipt_contents)?;
+
+    get_edits(&mut message_contents)?;
+
+    get_reactions(&mut message_contents)?;
+
+    get_deletions(&mut message_contents)?;
+
+    Ok(P2PMessageHashTables(
+        agent_messages,
+        message_contents,
+        receipt_contents,
+    ))
+}
+
+// messages currently pinned in the conversation, keyed by message hash
+pub fn get_pins_from_chain(
+    conversant: &AgentPubKey,
+) -> ExternResult<HashMap<String, P2PMessagePin>> {
+    let pin_rows = get_chain_index()?
+        .load(|bucket_conversant, _day| bucket_conversant == conversant)?
+        .pins;
+
+    get_current_pins(conversant, get_change_records(2, &pin_rows)?)
+}
+
+// the pins of the conversation still in effect, from pin records in chain order
+pub fn get_current_pins(
+    conversant: &AgentPubKey,
+    mut queried_pins: Vec<Record>,
+) -> ExternResult<HashMap<String, P2PMessagePin>> {
+    queried_pins.reverse();
+
+    let now = sys_time()?;
     let mut unpinned_messages: HashMap<String, P2PMessagePin> = HashMap::new();
     let mut pinned_messages: HashMap<String, P2PMessagePin> = HashMap::new();
 
     for pin in queried_pins.into_iter() {
         if let Ok(pin_entry) = TryInto::<P2PMessagePin>::try_into(pin.clone()) {
-            let _pin_hash = hash_entry(&pin_entry)?;
-
-            if pin_entry.conversants.contains(&conversant) {
+            if pin_entry.conversants.contains(conversant) {
                 match pin_entry.status {
-                    PinStatus::Pinned { timestamp: _ } => {
+                    // pins past their expiry count as unpinned
+                    PinStatus::Pinned {
+                        expires_at: Some(expires_at),
+                        ..
+                    } if expires_at <= now => {
+                        for message_hash in &pin_entry.id {
+                            match pinned_messages.get_mut(&message_hash.clone().to_string()) {
+                                Some(_pin) => None,
+                                None => unpinned_messages
+                                    .insert(message_hash.clone().to_string(), pin_entry.clone()),
+                            };
+                        }
+                    }
+                    PinStatus::Pinned { .. } => {
                         for message_hash in &pin_entry.id {
                             match unpinned_messages.get_mut(&message_hash.clone().to_string()) {
                                 Some(_pin) => None,
@@ -55,7 +104,7 @@ pub fn get_pinned_messages_handler(conversant: AgentPubKey) -> ExternResult<P2PM
                             };
                         }
                     }
-                    PinStatus::Unpinned { timestamp: _ } => {
+                    PinStatus::Unpinned { .. } => {
                         for message_hash in &pin_entry.id {
                             match pinned_messages.get_mut(&message_hash.clone().to_string()) {
                                 Some(_pin) => None,
@@ -71,29 +120,5 @@ pub fn get_pinned_messages_handler(conversant: AgentPubKey) -> ExternResult<P2PM
         }
     }
 
-    for message in queried_messages.into_iter() {
-        if let Ok(message_entry) = TryInto::<P2PMessage>::try_into(message) {
-            let message_hash: EntryHash = hash_entry(&message_entry)?;
-
-            if pinned_messages.contains_key(&message_hash.clone().to_string()) {
-                insert_message(
-                    &mut agent_messages,
-                    &mut message_contents,
-                    message_entry.clone(),
-                    message_hash,
-                    message_entry.receiver.clone(),
-                )?;
-            }
-        } else {
-            continue;
-        }
-    }
-
-    get_receipts(&mut message_contents, &mut receipt_contents)?;
-
-    Ok(P2PMessageHashTables(
-        agent_messages,
-        message_contents,
-        receipt_contents,
-    ))
+    Ok(pinned_messages)
 }
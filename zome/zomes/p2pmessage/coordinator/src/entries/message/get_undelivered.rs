@@ -0,0 +1,82 @@
+use hdk::prelude::*;
+use std::collections::HashSet;
+
+use p2pmessage_coordinator_types::*;
+use p2pmessage_integrity_types::*;
+
+use crate::{
+    chain_index::{get_chain_index, get_receipt_records},
+    outbox::{get_outbox_from_chain, get_receipt_outbox_from_chain},
+};
+
+pub fn get_undelivered_handler() -> ExternResult<Undelivered> {
+    let me = agent_info()?.agent_latest_pubkey;
+    let rows = get_chain_index()?.load(|conversant, _day| *conversant != me)?;
+
+    let sent_rows: Vec<IndexedMessage> = rows
+        .messages
+        .into_iter()
+        .filter(|row| row.from_me && row.conversant != me)
+        .collect();
+    let sent_hashes: HashSet<String> = sent_rows.iter().map(|row| row.hash.to_string()).collect();
+
+    // a delivered or read receipt means the message reached the receiver
+    let receipt_rows: Vec<IndexedReceipt> = rows
+        .receipts
+        .into_iter()
+        .filter(|row| {
+            row.messages
+                .iter()
+                .any(|message_id| sent_hashes.contains(&message_id.to_string()))
+        })
+        .collect();
+    let mut delivered_hashes: HashSet<String> = HashSet::new();
+    for record in get_receipt_records(&receipt_rows)?.into_iter() {
+        if let Ok(receipt_entry) = TryInto::<P2PMessageReceipt>::try_into(record) {
+            match receipt_entry.status {
+                Status::Delivered { .. } | Status::Read { .. } => delivered_hashes.extend(
+                    receipt_entry
+                        .id
+                        .iter()
+                        .map(|message_id| message_id.to_string()),
+                ),
+                Status::Sent { .. } => (),
+            }
+        }
+    }
+
+    let mut outbox = get_outbox_from_chain()?;
+    let messages: Vec<UndeliveredMessage> = sent_rows
+        .into_iter()
+        .filter(|row| !delivered_hashes.contains(&row.hash.to_string()))
+        .map(|row| UndeliveredMessage {
+            status: outbox
+                .remove(&row.hash.to_string())
+                .map(|outbox_entry| outbox_entry.status),
+            message_hash: row.hash,
+            receiver: row.conversant,
+            time_sent: row.time_sent,
+        })
+        .collect();
+
+    // receipts the sender acknowledged have a delivered entry in the receipt outbox
+    let receipts: Vec<UndeliveredReceipt> = get_receipt_outbox_from_chain()?
+        .into_values()
+        .filter(|outbox_entry| match outbox_entry.status {
+            OutboxStatus::Pending { .. }
+            | OutboxStatus::Requested { .. }
+            | OutboxStatus::Failed { .. } => true,
+            OutboxStatus::Delivered { .. } => false,
+        })
+        .map(|outbox_entry| UndeliveredReceipt {
+            receipt_hash: outbox_entry.receipt_hash,
+            sender: outbox_entry.sender,
+            status: outbox_entry.status,
+        })
+        .collect();
+
+    Ok(Undelivered {
+        messages,
+        receipts,
+    })
+}
@@ -1,12 +1,18 @@
 use hdk::prelude::*;
-use std::collections::HashMap;
+use holo_hash::EntryHashB64;
+use std::collections::{HashMap, HashSet};
 
 use p2pmessage_coordinator_types::*;
 use p2pmessage_integrity_types::*;
 
-use crate::utils::error;
-
-use super::utils::this_zome_index;
+use crate::{
+    chain_index::{
+        day_of, get_chain_index, get_change_records, get_receipt_records, query_by_hashes,
+        IndexRows,
+    },
+    encryption::decrypt_payload,
+    utils::error,
+};
 
 pub fn insert_message(
     agent_messages: &mut HashMap<String, Vec<String>>,
@@ -26,11 +32,14 @@ pub fn insert_message(
         }
     };
     let message_data = P2PMessageData {
+        payload: decrypt_payload(message_entry.payload, &message_entry.author)?,
         author: message_entry.author,
         receiver: message_entry.receiver,
-        payload: message_entry.payload,
         time_sent: message_entry.time_sent,
         reply_to: None,
+        edited: false,
+        deleted: false,
+        reactions: HashMap::new(),
     };
     message_contents.insert(message_hash.to_string(), (message_data, Vec::new()));
 
@@ -61,54 +70,90 @@ pub fn get_receipts(
     message_contents: &mut HashMap<String, (P2PMessageData, Vec<String>)>,
     receipt_contents: &mut HashMap<String, P2PMessageReceipt>,
 ) -> ExternResult<()> {
-    let queried_receipts: Vec<Record> = query(
-        QueryFilter::new()
-            .entry_type(EntryType::App(AppEntryDef::new(
-                EntryDefIndex::from(1),
-                this_zome_index()?,
-                EntryVisibility::Private,
-            )))
-            .include_entries(true),
-    )?;
-
-    for receipt in queried_receipts.into_iter() {
-        if let Ok(receipt_entry) = TryInto::<P2PMessageReceipt>::try_into(receipt) {
-            let receipt_hash = hash_entry(&receipt_entry)?;
-            // distribute this receipt to every messsage it belongs to
-            for message_id in receipt_entry.id.clone().into_iter() {
-                if message_contents.contains_key(&message_id.clone().to_string()) {
-                    receipt_contents
-                        .insert(receipt_hash.clone().to_string(), receipt_entry.clone());
-                    if let Some(message_bundle) =
-                        message_contents.get_mut(&message_id.clone().to_string())
-                    {
-                        message_bundle.1.push(receipt_hash.clone().to_string())
-                    };
-                }
+    let me = agent_info()?.agent_latest_pubkey;
+    let messages: HashMap<String, (AgentPubKey, Timestamp)> = message_contents
+        .iter()
+        .map(|(message_hash, (message_data, _))| {
+            let conversant = if message_data.author == me {
+                message_data.receiver.clone()
+            } else {
+                message_data.author.clone()
+            };
+            (message_hash.clone(), (conversant, message_data.time_sent))
+        })
+        .collect();
+
+    for (receipt_hash, receipt_entry) in get_receipts_of_messages(&messages)?.into_iter() {
+        // distribute this receipt to every messsage it belongs to
+        for message_id in receipt_entry.id.clone().into_iter() {
+            if message_contents.contains_key(&message_id.clone().to_string()) {
+                receipt_contents.insert(receipt_hash.clone().to_string(), receipt_entry.clone());
+                if let Some(message_bundle) =
+                    message_contents.get_mut(&message_id.clone().to_string())
+                {
+                    message_bundle.1.push(receipt_hash.clone().to_string())
+                };
             }
+        }
+    }
+
+    Ok(())
+}
+
+// the receipts of the given messages by conversant and time sent, only their buckets are loaded
+pub fn get_receipts_of_messages(
+    messages: &HashMap<String, (AgentPubKey, Timestamp)>,
+) -> ExternResult<Vec<(EntryHash, P2PMessageReceipt)>> {
+    let buckets: HashSet<(AgentPubKey, i64)> = messages
+        .values()
+        .map(|(conversant, time_sent)| (conversant.clone(), day_of(*time_sent)))
+        .collect();
+    let receipt_rows = get_chain_index()?
+        .load(|conversant, day| buckets.contains(&(conversant.clone(), day)))?
+        .receipts;
+    let message_hashes: HashSet<String> = messages.keys().cloned().collect();
+
+    get_receipts_from_rows(receipt_rows, &message_hashes)
+}
+
+// only the receipts of the given messages are fetched, in chain order
+pub fn get_receipts_from_rows(
+    receipt_rows: Vec<IndexedReceipt>,
+    message_hashes: &HashSet<String>,
+) -> ExternResult<Vec<(EntryHash, P2PMessageReceipt)>> {
+    let selected_rows: Vec<IndexedReceipt> = receipt_rows
+        .into_iter()
+        .filter(|row| {
+            row.messages
+                .iter()
+                .any(|message_id| message_hashes.contains(&message_id.to_string()))
+        })
+        .collect();
+
+    let mut receipts: Vec<(EntryHash, P2PMessageReceipt)> = Vec::new();
+    for receipt in get_receipt_records(&selected_rows)?.into_iter() {
+        if let Ok(receipt_entry) = TryInto::<P2PMessageReceipt>::try_into(receipt) {
+            receipts.push((hash_entry(&receipt_entry)?, receipt_entry));
         } else {
             continue;
         }
     }
 
-    Ok(())
+    Ok(receipts)
 }
 
 pub fn get_replies(
     reply_pairs: &mut HashMap<String, Vec<String>>,
     message_contents: &mut HashMap<String, (P2PMessageData, Vec<String>)>,
 ) -> ExternResult<()> {
-    let queried_messages: Vec<Record> = query(
-        QueryFilter::new()
-            .entry_type(EntryType::App(AppEntryDef::new(
-                EntryDefIndex::from(0),
-                this_zome_index()?,
-                EntryVisibility::Private,
-            )))
-            .include_entries(true),
-    )?;
-
-    for message in queried_messages.clone().into_iter() {
+    let replied_to_hashes: HashSet<EntryHash> = reply_pairs
+        .keys()
+        .filter_map(|message_hash| EntryHashB64::from_b64_str(message_hash).ok())
+        .map(EntryHash::from)
+        .collect();
+    let queried_messages = query_by_hashes(0, replied_to_hashes)?;
+
+    for message in queried_messages.into_iter() {
         if let Ok(message_entry) = TryInto::<P2PMessage>::try_into(message) {
             let message_hash = hash_entry(&message_entry)?;
 
@@ -119,9 +164,9 @@ pub fn get_replies(
                         // build reply_to data
                         let replied_to_message = P2PMessageReplyTo {
                             hash: message_hash.clone(),
+                            payload: decrypt_payload(message_entry.payload, &message_entry.author)?,
                             author: message_entry.author,
                             receiver: message_entry.receiver,
-                            payload: message_entry.payload,
                             time_sent: message_entry.time_sent,
                             reply_to: None,
                         };
@@ -147,74 +192,315 @@ pub fn get_replies(
     Ok(())
 }
 
-pub fn get_message_from_chain(hash: EntryHash) -> ExternResult<P2PMessage> {
-    let mut queried_messages: Vec<Record> = query(
-        QueryFilter::new()
-            .entry_type(EntryType::App(AppEntryDef::new(
-                EntryDefIndex::from(0),
-                this_zome_index()?,
-                EntryVisibility::Private,
-            )))
-            .include_entries(true),
-    )?;
-    queried_messages.reverse();
+// conversant and time sent of the messages and of the messages they reply to
+fn message_keys(
+    message_contents: &HashMap<String, (P2PMessageData, Vec<String>)>,
+) -> ExternResult<HashMap<String, (AgentPubKey, Timestamp)>> {
+    let me = agent_info()?.agent_latest_pubkey;
+    let conversant_of = |author: &AgentPubKey, receiver: &AgentPubKey| {
+        if *author == me {
+            receiver.clone()
+        } else {
+            author.clone()
+        }
+    };
+
+    let mut messages: HashMap<String, (AgentPubKey, Timestamp)> = HashMap::new();
+    for (message_hash, (message_data, _)) in message_contents.iter() {
+        messages.insert(
+            message_hash.clone(),
+            (
+                conversant_of(&message_data.author, &message_data.receiver),
+                message_data.time_sent,
+            ),
+        );
+        if let Some(ref replied_to_message) = message_data.reply_to {
+            messages.insert(
+                replied_to_message.hash.clone().to_string(),
+                (
+                    conversant_of(&replied_to_message.author, &replied_to_message.receiver),
+                    replied_to_message.time_sent,
+                ),
+            );
+        }
+    }
+
+    Ok(messages)
+}
+
+// the key the buckets of a single message are found by
+pub fn message_key(
+    message_hash: &EntryHash,
+    message: &P2PMessage,
+) -> ExternResult<HashMap<String, (AgentPubKey, Timestamp)>> {
+    let conversant = if message.author == agent_info()?.agent_latest_pubkey {
+        message.receiver.clone()
+    } else {
+        message.author.clone()
+    };
+
+    Ok(HashMap::from([(
+        message_hash.clone().to_string(),
+        (conversant, message.time_sent),
+    )]))
+}
+
+// the rows of the buckets of the given messages
+fn load_rows_of_messages(
+    messages: &HashMap<String, (AgentPubKey, Timestamp)>,
+) -> ExternResult<IndexRows> {
+    let buckets: HashSet<(AgentPubKey, i64)> = messages
+        .values()
+        .map(|(conversant, time_sent)| (conversant.clone(), day_of(*time_sent)))
+        .collect();
+
+    get_chain_index()?.load(|conversant, day| buckets.contains(&(conversant.clone(), day)))
+}
 
-    for record in queried_messages.into_iter() {
-        let message_entry = TryInto::<P2PMessage>::try_into(record.clone())?;
-        let message_hash = hash_entry(message_entry.clone())?;
+// records of the change rows about the given messages, in chain order
+fn get_changes_of_messages(
+    index: u8,
+    change_rows: Vec<IndexedChange>,
+    messages: &HashMap<String, (AgentPubKey, Timestamp)>,
+) -> ExternResult<Vec<Record>> {
+    let selected_rows: Vec<IndexedChange> = change_rows
+        .into_iter()
+        .filter(|row| {
+            row.messages
+                .iter()
+                .any(|message_id| messages.contains_key(&message_id.to_string()))
+        })
+        .collect();
 
-        if hash == message_hash {
-            return Ok(message_entry);
+    get_change_records(index, &selected_rows)
+}
+
+pub fn get_edits(
+    message_contents: &mut HashMap<String, (P2PMessageData, Vec<String>)>,
+) -> ExternResult<()> {
+    let mut latest_edits = get_latest_edits_of_messages(&message_keys(message_contents)?)?;
+    for edit in latest_edits.values_mut() {
+        edit.payload = decrypt_payload(edit.payload.clone(), &edit.author)?;
+    }
+
+    for (message_hash, message_bundle) in message_contents.iter_mut() {
+        if let Some(edit) = latest_edits.get(message_hash) {
+            message_bundle.0.payload = edit.payload.clone();
+            message_bundle.0.edited = true;
+        }
+        // replies show the latest content of the message they reply to
+        if let Some(ref mut replied_to_message) = message_bundle.0.reply_to {
+            if let Some(edit) = latest_edits.get(&replied_to_message.hash.clone().to_string()) {
+                replied_to_message.payload = edit.payload.clone();
+            }
         }
     }
 
-    return error("Sorry. Message entry for hash not found.");
+    Ok(())
 }
 
-pub fn get_receipt_from_chain(hash: EntryHash) -> ExternResult<P2PMessageReceipt> {
-    let queried_receipts: Vec<Record> = query(
-        QueryFilter::new()
-            .entry_type(EntryType::App(AppEntryDef::new(
-                EntryDefIndex::from(1),
-                this_zome_index()?,
-                EntryVisibility::Private,
-            )))
-            .include_entries(true),
-    )?;
+pub fn get_edits_from_chain(
+    message_hash: EntryHash,
+) -> ExternResult<Vec<(EntryHash, P2PMessageEdit)>> {
+    let message = get_message_from_chain(message_hash.clone())?;
+    let messages = message_key(&message_hash, &message)?;
+    let edit_rows = load_rows_of_messages(&messages)?.edits;
 
-    for record in queried_receipts {
-        let receipt_entry = TryInto::<P2PMessageReceipt>::try_into(record.clone())?;
-        let receipt_hash = hash_entry(receipt_entry.clone())?;
+    let mut edits: Vec<(EntryHash, P2PMessageEdit)> = Vec::new();
+    for record in get_changes_of_messages(6, edit_rows, &messages)?.into_iter() {
+        if let Ok(edit_entry) = TryInto::<P2PMessageEdit>::try_into(record) {
+            let edit_hash = hash_entry(&edit_entry)?;
+            let payload = decrypt_payload(edit_entry.payload.clone(), &edit_entry.author)?;
+            edits.push((
+                edit_hash,
+                P2PMessageEdit {
+                    payload,
+                    ..edit_entry
+                },
+            ));
+        } else {
+            continue;
+        }
+    }
+    edits.sort_by_key(|(_, edit_entry)| edit_entry.time_edited);
+
+    Ok(edits)
+}
+
+// the latest edit of each of the given messages, only their buckets are loaded
+pub fn get_latest_edits_of_messages(
+    messages: &HashMap<String, (AgentPubKey, Timestamp)>,
+) -> ExternResult<HashMap<String, P2PMessageEdit>> {
+    let edit_rows = load_rows_of_messages(messages)?.edits;
 
-        if hash == receipt_hash {
-            return Ok(receipt_entry);
+    let mut latest_edits: HashMap<String, P2PMessageEdit> = HashMap::new();
+    for record in get_changes_of_messages(6, edit_rows, messages)?.into_iter() {
+        if let Ok(edit_entry) = TryInto::<P2PMessageEdit>::try_into(record) {
+            let message_hash = edit_entry.message_hash.clone().to_string();
+            match latest_edits.get(&message_hash) {
+                Some(latest_edit) if latest_edit.time_edited >= edit_entry.time_edited => continue,
+                _ => {
+                    latest_edits.insert(message_hash, edit_entry);
+                }
+            }
+        } else {
+            continue;
         }
     }
 
-    return error("Sorry. Receipt entry for hash not found.");
+    Ok(latest_edits)
 }
 
-pub fn get_file_from_chain(file_hash: EntryHash) -> ExternResult<P2PFileBytes> {
-    let queried_files: Vec<Record> = query(
-        QueryFilter::new()
-            .entry_type(EntryType::App(AppEntryDef::new(
-                EntryDefIndex::from(3),
-                this_zome_index()?,
-                EntryVisibility::Private,
-            )))
-            .include_entries(true),
-    )?;
-
-    for record in queried_files.into_iter() {
-        if let Ok(file_entry) = TryInto::<P2PFileBytes>::try_into(record.clone()) {
-            let entry_hash = hash_entry(&file_entry)?;
-
-            if entry_hash == file_hash {
-                return Ok(file_entry);
+pub fn get_reactions(
+    message_contents: &mut HashMap<String, (P2PMessageData, Vec<String>)>,
+) -> ExternResult<()> {
+    let mut reactions = get_reactions_of_messages(&message_keys(message_contents)?)?;
+
+    for (message_hash, message_bundle) in message_contents.iter_mut() {
+        if let Some(message_reactions) = reactions.remove(message_hash) {
+            message_bundle.0.reactions = message_reactions;
+        }
+    }
+
+    Ok(())
+}
+
+// emoji to the agents who reacted with it
+pub type MessageReactions = HashMap<String, Vec<AgentPubKey>>;
+
+// the reactions of the given messages, only their buckets are loaded
+pub fn get_reactions_of_messages(
+    messages: &HashMap<String, (AgentPubKey, Timestamp)>,
+) -> ExternResult<HashMap<String, MessageReactions>> {
+    let reaction_rows = load_rows_of_messages(messages)?.reactions;
+
+    let mut reactions: HashMap<String, MessageReactions> = HashMap::new();
+    for record in get_changes_of_messages(8, reaction_rows, messages)?.into_iter() {
+        if let Ok(reaction_entry) = TryInto::<P2PMessageReaction>::try_into(record) {
+            let message_reactions = reactions
+                .entry(reaction_entry.message_hash.clone().to_string())
+                .or_default();
+            let reactors = message_reactions
+                .entry(reaction_entry.emoji.clone())
+                .or_default();
+
+            match reaction_entry.status {
+                ReactionStatus::Reacted { .. } => {
+                    if !reactors.contains(&reaction_entry.reactor) {
+                        reactors.push(reaction_entry.reactor);
+                    }
+                }
+                ReactionStatus::Removed { .. } => {
+                    reactors.retain(|reactor| *reactor != reaction_entry.reactor);
+                }
             }
         } else {
             continue;
         }
     }
-    return error("Sorry. File entry for hash not found.");
+
+    // drop emojis whose reactions were all removed
+    for message_reactions in reactions.values_mut() {
+        message_reactions.retain(|_, reactors| !reactors.is_empty());
+    }
+
+    Ok(reactions)
+}
+
+pub fn get_deletions(
+    message_contents: &mut HashMap<String, (P2PMessageData, Vec<String>)>,
+) -> ExternResult<()> {
+    let deleted_messages = get_deletions_of_messages(&message_keys(message_contents)?)?;
+
+    // deleted messages are returned as tombstones without their content
+    for (message_hash, message_bundle) in message_contents.iter_mut() {
+        if deleted_messages.contains_key(message_hash) {
+            message_bundle.0.payload = Payload::Text {
+                payload: String::new(),
+            };
+            message_bundle.0.deleted = true;
+            message_bundle.0.reactions = HashMap::new();
+        }
+        if let Some(ref mut replied_to_message) = message_bundle.0.reply_to {
+            if deleted_messages.contains_key(&replied_to_message.hash.clone().to_string()) {
+                replied_to_message.payload = Payload::Text {
+                    payload: String::new(),
+                };
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// the deletions of the given messages, only their buckets are loaded
+pub fn get_deletions_of_messages(
+    messages: &HashMap<String, (AgentPubKey, Timestamp)>,
+) -> ExternResult<HashMap<String, P2PMessageDeletion>> {
+    let deletion_rows = load_rows_of_messages(messages)?.deletions;
+
+    let mut deleted_messages: HashMap<String, P2PMessageDeletion> = HashMap::new();
+    for record in get_changes_of_messages(7, deletion_rows, messages)?.into_iter() {
+        if let Ok(deletion_entry) = TryInto::<P2PMessageDeletion>::try_into(record) {
+            deleted_messages.insert(
+                deletion_entry.message_hash.clone().to_string(),
+                deletion_entry,
+            );
+        } else {
+            continue;
+        }
+    }
+
+    Ok(deleted_messages)
+}
+
+pub fn get_message_from_chain(hash: EntryHash) -> ExternResult<P2PMessage> {
+    if let Some(record) = query_by_hashes(0, HashSet::from([hash]))?.pop() {
+        return TryInto::<P2PMessage>::try_into(record);
+    }
+
+    error(P2PMessageError::NotFound(String::from(
+        "Sorry. Message entry for hash not found.",
+    )))
+}
+
+pub fn get_receipt_from_chain(hash: EntryHash) -> ExternResult<P2PMessageReceipt> {
+    if let Some(record) = query_by_hashes(1, HashSet::from([hash]))?.pop() {
+        return TryInto::<P2PMessageReceipt>::try_into(record);
+    }
+
+    error(P2PMessageError::NotFound(String::from(
+        "Sorry. Receipt entry for hash not found.",
+    )))
+}
+
+pub fn get_edit_from_chain(hash: EntryHash) -> ExternResult<P2PMessageEdit> {
+    if let Some(record) = query_by_hashes(6, HashSet::from([hash]))?.pop() {
+        return TryInto::<P2PMessageEdit>::try_into(record);
+    }
+
+    error(P2PMessageError::NotFound(String::from(
+        "Sorry. Edit entry for hash not found.",
+    )))
+}
+
+pub fn get_deletion_from_chain(hash: EntryHash) -> ExternResult<P2PMessageDeletion> {
+    if let Some(record) = query_by_hashes(7, HashSet::from([hash]))?.pop() {
+        return TryInto::<P2PMessageDeletion>::try_into(record);
+    }
+
+    error(P2PMessageError::NotFound(String::from(
+        "Sorry. Deletion entry for hash not found.",
+    )))
+}
+
+pub fn get_file_from_chain(file_hash: EntryHash) -> ExternResult<P2PFileBytes> {
+    if let Some(record) = query_by_hashes(3, HashSet::from([file_hash]))?.pop() {
+        if let Ok(file_entry) = TryInto::<P2PFileBytes>::try_into(record) {
+            return Ok(file_entry);
+        }
+    }
+    error(P2PMessageError::NotFound(String::from(
+        "Sorry. File entry for hash not found.",
+    )))
 }
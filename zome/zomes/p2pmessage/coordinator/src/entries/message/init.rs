@@ -1,26 +1,17 @@
 use hdk::prelude::*;
 
+use super::cap_grants::create_policy_grants;
+use super::outbox::flush_outbox_handler;
+
 /*
- * ZOME INIT FUNCTION TO SET UNRESTRICTED ACCESS
+ * ZOME INIT FUNCTION TO SET ACCESS TO THE REMOTE FUNCTIONS
+ * access follows the cap_grant_policy dna property, see cap_grants.rs
  */
 
 pub fn init_handler() -> ExternResult<InitCallbackResult> {
     let zome_name: ZomeName = zome_info()?.name;
 
-    let mut receive_message_function = BTreeSet::new();
-    receive_message_function.insert((zome_name.clone(), "receive_message".into()));
-    let receive_message_functions: GrantedFunctions =
-        GrantedFunctions::Listed(receive_message_function);
-
-    let mut receive_receipt_function = BTreeSet::new();
-    receive_receipt_function.insert((zome_name.clone(), "receive_receipt".into()));
-    let receive_receipt_functions: GrantedFunctions =
-        GrantedFunctions::Listed(receive_receipt_function);
-
-    let mut typing_function = BTreeSet::new();
-    typing_function.insert((zome_name.clone(), "typing".into()));
-    let typing_functions: GrantedFunctions = GrantedFunctions::Listed(typing_function);
-
+    // remote signals are sent without a cap secret
     let mut recv_remote_signal_function = BTreeSet::new();
     recv_remote_signal_function.insert((zome_name.clone(), "recv_remote_signal".into()));
     let recv_remote_signal_functions: GrantedFunctions =
@@ -30,27 +21,11 @@ pub fn init_handler() -> ExternResult<InitCallbackResult> {
     post_commit_function.insert((zome_name.clone(), "post_commit".into()));
     let post_commit_functions: GrantedFunctions = GrantedFunctions::Listed(post_commit_function);
 
-    let mut sync_pins_function = BTreeSet::new();
-    sync_pins_function.insert((zome_name.clone(), "sync_pins".into()));
-    let sync_pins_functions: GrantedFunctions = GrantedFunctions::Listed(sync_pins_function);
-
-    create_cap_grant(CapGrantEntry {
-        tag: "receive_message".into(),
-        access: CapAccess::Unrestricted,
-        functions: receive_message_functions,
-    })?;
-
-    create_cap_grant(CapGrantEntry {
-        tag: "receive_receipt".into(),
-        access: CapAccess::Unrestricted,
-        functions: receive_receipt_functions,
-    })?;
-
-    create_cap_grant(CapGrantEntry {
-        tag: "typing".into(),
-        access: CapAccess::Unrestricted,
-        functions: typing_functions,
-    })?;
+    // agents ask for the secret of the grant assigned to them, see request_cap_claim
+    let mut request_cap_claim_function = BTreeSet::new();
+    request_cap_claim_function.insert((zome_name.clone(), "request_cap_claim".into()));
+    let request_cap_claim_functions: GrantedFunctions =
+        GrantedFunctions::Listed(request_cap_claim_function);
 
     create_cap_grant(CapGrantEntry {
         tag: "recv_remote_signal".into(),
@@ -65,10 +40,16 @@ pub fn init_handler() -> ExternResult<InitCallbackResult> {
     })?;
 
     create_cap_grant(CapGrantEntry {
-        tag: "sync_pins".into(),
+        tag: "request_cap_claim".into(),
         access: CapAccess::Unrestricted,
-        functions: sync_pins_functions,
+        functions: request_cap_claim_functions,
     })?;
 
+    create_policy_grants()?;
+
+    // retry undelivered messages periodically, see retry_outbox
+    schedule("retry_outbox")?;
+    let _res = flush_outbox_handler(None);
+
     Ok(InitCallbackResult::Pass)
 }
@@ -1,47 +1,59 @@
-// TODO: discuss whether this is still needed or not
-// both for receiving and sending
-
-// fn is_user_blocked(agent_pubkey: AgentPubKey) -> ExternResult<bool> {
-//     match call::<AgentPubKey, BooleanWrapper>(
-//         None,
-//         "contacts".into(),
-//         "in_blocked".into(),
-//         None,
-//         &agent_pubkey.clone()
-//     ) {
-//         Ok(output) => Ok(output.0),
-//         _ => return crate::error("{\"code\": \"401\", \"message\": \"This agent has no proper authorization\"}")
-//     }
-
-//     let block_result: Result<BooleanWrapper, HdkError> = call_remote(
-//         message_input.clone().receiver,
-//         "contacts".into(),
-//         "in_blocked".into(),
-//         None,
-//         &agent_pubkey
-//     );
-//     match block_result {
-//         Ok(receive_output) => {
-//             let message_entry = P2PMessage::from_parameter(receive_output.clone());
-//             create_entry(&message_entry)?;
-//             Ok(receive_output)
-//         },
-//         Err(kind) => {
-//             match kind {
-//                 // TIMEOUT; RECIPIENT IS OFFLINE; MESSAGE NEEDS TO BE SENT ASYNC
-//                 HdkError::ZomeCallNetworkError(_err) => {
-//                     match send_message_async(message_input) {
-//                         Ok(async_result) => {
-//                             let message_entry = P2PMessage::from_parameter(async_result.clone());
-//                             create_entry(&message_entry)?;
-//                             Ok(async_result)
-//                         },
-//                         _ => crate::err("TODO: 000", "This agent has no proper authorization")
-//                     }
-//                 },
-//                 HdkError::UnauthorizedZomeCall(_c,_z,_f,_p) => crate::err("TODO: 000:", "This case shouldn't happen because of unrestricted access to receive message"),
-//                 _ => crate::err("TODO: 000", "Unknown other error")
-//             }
-//         }
-//     }
-// }
+use hdk::prelude::*;
+use std::collections::HashMap;
+
+use p2pmessage_integrity_types::*;
+
+use super::utils::this_zome_index;
+
+/*
+ * ZOME-LOCAL BLOCK LIST
+ * the block list is append-only, the latest entry for an agent is its current state
+ */
+
+pub fn is_user_blocked(agent_pubkey: AgentPubKey) -> ExternResult<bool> {
+    Ok(get_blocked_agents_from_chain()?.contains(&agent_pubkey))
+}
+
+pub fn get_blocked_agents_from_chain() -> ExternResult<Vec<AgentPubKey>> {
+    let queried_blocks: Vec<Record> = query(
+        QueryFilter::new()
+            .entry_type(EntryType::App(AppEntryDef::new(
+                EntryDefIndex::from(5),
+                this_zome_index()?,
+                EntryVisibility::Private,
+            )))
+            .include_entries(true),
+    )?;
+
+    let mut block_statuses: HashMap<AgentPubKey, BlockStatus> = HashMap::new();
+    for record in queried_blocks.into_iter() {
+        if let Ok(block_entry) = TryInto::<P2PBlockedAgent>::try_into(record) {
+            block_statuses.insert(block_entry.agent, block_entry.status);
+        } else {
+            continue;
+        }
+    }
+
+    let blocked_agents = block_statuses
+        .into_iter()
+        .filter_map(|(agent, status)| match status {
+            BlockStatus::Blocked { .. } => Some(agent),
+            BlockStatus::Unblocked { .. } => None,
+        })
+        .collect();
+
+    Ok(blocked_agents)
+}
+
+pub fn commit_block_status(agent: AgentPubKey, status: BlockStatus) -> ExternResult<ActionHash> {
+    let block_entry = Entry::App(P2PBlockedAgent { agent, status }.try_into()?);
+    host_call::<CreateInput, ActionHash>(
+        __hc__create_1,
+        CreateInput::new(
+            EntryDefLocation::app(this_zome_index()?, 5),
+            EntryVisibility::Private,
+            block_entry,
+            ChainTopOrdering::Relaxed,
+        ),
+    )
+}
@@ -0,0 +1,58 @@
+use hdk::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+use p2pmessage_coordinator_types::*;
+use p2pmessage_integrity_types::*;
+
+use crate::{
+    chain_index::{day_of, get_chain_index},
+    helpers::get_receipts_from_rows,
+    read_message::read_message_handler,
+};
+
+pub fn mark_conversation_read_handler(
+    mark_read_input: MarkConversationReadInput,
+) -> ExternResult<HashMap<String, P2PMessageReceipt>> {
+    let last_day = day_of(mark_read_input.up_to_timestamp);
+    let conversation_rows = get_chain_index()?
+        .load(|conversant, day| *conversant == mark_read_input.conversant && day <= last_day)?;
+    let received_rows: Vec<IndexedMessage> = conversation_rows
+        .messages
+        .into_iter()
+        .filter(|row| {
+            row.conversant == mark_read_input.conversant
+                && !row.from_me
+                && row.time_sent <= mark_read_input.up_to_timestamp
+        })
+        .collect();
+    let received_hashes: HashSet<String> = received_rows
+        .iter()
+        .map(|row| row.hash.to_string())
+        .collect();
+
+    // messages already marked as read do not get another receipt
+    let mut read_hashes: HashSet<String> = HashSet::new();
+    for (_receipt_hash, receipt_entry) in
+        get_receipts_from_rows(conversation_rows.receipts, &received_hashes)?.into_iter()
+    {
+        if let Status::Read { .. } = receipt_entry.status {
+            read_hashes.extend(receipt_entry.id.iter().map(|hash| hash.to_string()));
+        }
+    }
+
+    let unread_hashes: Vec<EntryHash> = received_rows
+        .into_iter()
+        .filter(|row| !read_hashes.contains(&row.hash.to_string()))
+        .map(|row| row.hash)
+        .collect();
+    if unread_hashes.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    // a single receipt covers every message marked as read
+    read_message_handler(ReadMessageInput {
+        message_hashes: unread_hashes,
+        sender: mark_read_input.conversant,
+        timestamp: sys_time()?,
+    })
+}
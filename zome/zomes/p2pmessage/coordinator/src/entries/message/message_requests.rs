@@ -0,0 +1,386 @@
+use hdk::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+use p2pmessage_coordinator_types::*;
+use p2pmessage_integrity_types::*;
+
+use crate::{
+    chain_index::{get_chain_index, index_chain, query_by_hashes, query_since},
+    encryption::decrypt_payload,
+    helpers::get_message_from_chain,
+    is_user_blocked::get_blocked_agents_from_chain,
+    rate_limits::payload_size,
+    search_messages::index_messages,
+    utils::error,
+};
+
+use super::utils::this_zome_index;
+
+/*
+ * MESSAGE REQUESTS
+ * with the message_requests dna property set, the messages of an agent without a conversation
+ * wait in a request queue and only reach the inbox, with their delivered receipt, once accepted
+ */
+
+// what the P2PMessageRequest entries tell about a queued message, without the message itself
+struct PendingRequest {
+    message_hash: EntryHash,
+    queued_hash: EntryHash,
+    size: usize,
+    received: Timestamp,
+}
+
+struct QueuedMessage {
+    message_hash: EntryHash,
+    message: P2PMessage,
+    file: Option<P2PFileBytes>,
+    received: Timestamp,
+}
+
+// agents we sent a message to or accepted messages from are in a conversation with us
+pub fn is_message_request(sender: &AgentPubKey) -> ExternResult<bool> {
+    if !get_properties()?.message_requests {
+        return Ok(false);
+    }
+
+    Ok(!get_chain_index()?.conversants().contains(sender))
+}
+
+pub fn queue_message_request(message: P2PMessage, file: Option<P2PFileBytes>) -> ExternResult<()> {
+    let message_hash = hash_entry(&message)?;
+    let sender = message.author.clone();
+
+    // a retry from the sender's outbox, the message is already queued
+    let pending = get_requests_from_chain()?
+        .remove(&sender)
+        .unwrap_or_default();
+    if pending
+        .iter()
+        .any(|pending_request| pending_request.message_hash == message_hash)
+    {
+        return Ok(());
+    }
+
+    let size = payload_size(&decrypt_payload(message.payload.clone(), &sender)?);
+    let queued_message = P2PQueuedMessage {
+        message: message.clone(),
+        file: file.clone(),
+    };
+    let queued_hash = hash_entry(&queued_message)?;
+    let queued_entry = Entry::App(queued_message.try_into()?);
+    host_call::<CreateInput, ActionHash>(
+        __hc__create_1,
+        CreateInput::new(
+            EntryDefLocation::app(this_zome_index()?, 19),
+            EntryVisibility::Private,
+            queued_entry,
+            ChainTopOrdering::Relaxed,
+        ),
+    )?;
+
+    let received = sys_time()?;
+    commit_request(P2PMessageRequest {
+        sender: sender.clone(),
+        status: RequestStatus::Pending {
+            message_hash: message_hash.clone(),
+            queued_hash,
+            size,
+            timestamp: received,
+        },
+    })?;
+
+    let mut queued = get_queued_messages(&pending)?;
+    queued.push(QueuedMessage {
+        message_hash,
+        message,
+        file,
+        received,
+    });
+
+    let signal_details = SignalDetails {
+        name: "RECEIVE_P2P_MESSAGE_REQUEST".to_string(),
+        payload: Signal::MessageRequest(MessageRequestSignal {
+            request: message_request(sender, &queued)?,
+        }),
+    };
+    emit_signal(&signal_details)?;
+
+    Ok(())
+}
+
+pub fn list_message_requests_handler() -> ExternResult<Vec<MessageRequest>> {
+    let blocked_agents = get_blocked_agents_from_chain()?;
+
+    let mut requests: Vec<MessageRequest> = Vec::new();
+    for (sender, pending) in get_requests_from_chain()?.into_iter() {
+        if blocked_agents.contains(&sender) {
+            continue;
+        }
+        requests.push(message_request(sender, &get_queued_messages(&pending)?)?);
+    }
+
+    // latest request first
+    requests.sort_by_key(|request| std::cmp::Reverse(request.last_received));
+
+    Ok(requests)
+}
+
+pub fn accept_message_request_handler(
+    sender: AgentPubKey,
+) -> ExternResult<Vec<MessageDataAndReceipt>> {
+    let queued = match get_requests_from_chain()?.remove(&sender) {
+        Some(pending) => get_queued_messages(&pending)?,
+        None => {
+            return error(P2PMessageError::NotFound(String::from(
+                "Sorry. There is no message request from this agent.",
+            )))
+        }
+    };
+
+    // the indexes cover the earlier calls only, this one has not committed yet
+    let _res = index_chain();
+    let _res = index_messages();
+
+    let mut accepted_messages: Vec<MessageDataAndReceipt> = Vec::new();
+    for queued_message in queued.into_iter() {
+        if get_message_from_chain(queued_message.message_hash.clone()).is_ok() {
+            continue;
+        }
+
+        // the delivered receipt goes back to the sender in post_commit
+        let receipt = P2PMessageReceipt {
+            id: vec![queued_message.message_hash.clone()],
+            status: Status::Delivered {
+                timestamp: sys_time()?,
+            },
+        };
+
+        let message_entry = Entry::App(queued_message.message.clone().try_into()?);
+        let receipt_entry = Entry::App(receipt.clone().try_into()?);
+        host_call::<CreateInput, ActionHash>(
+            __hc__create_1,
+            CreateInput::new(
+                EntryDefLocation::app(this_zome_index()?, 0),
+                EntryVisibility::Private,
+                message_entry,
+                ChainTopOrdering::Relaxed,
+            ),
+        )?;
+        host_call::<CreateInput, ActionHash>(
+            __hc__create_1,
+            CreateInput::new(
+                EntryDefLocation::app(this_zome_index()?, 1),
+                EntryVisibility::Private,
+                receipt_entry,
+                ChainTopOrdering::Relaxed,
+            ),
+        )?;
+
+        if let Some(file) = queued_message.file.clone() {
+            let file_entry = Entry::App(file.try_into()?);
+            host_call::<CreateInput, ActionHash>(
+                __hc__create_1,
+                CreateInput::new(
+                    EntryDefLocation::app(this_zome_index()?, 3),
+                    EntryVisibility::Private,
+                    file_entry,
+                    ChainTopOrdering::Relaxed,
+                ),
+            )?;
+        }
+
+        accepted_messages.push(MessageDataAndReceipt(
+            (
+                queued_message.message_hash.clone(),
+                message_data(&queued_message.message)?,
+            ),
+            (hash_entry(&receipt)?, receipt),
+        ));
+    }
+
+    commit_request(P2PMessageRequest {
+        sender,
+        status: RequestStatus::Accepted {
+            timestamp: sys_time()?,
+        },
+    })?;
+
+    Ok(accepted_messages)
+}
+
+// the queued messages are dropped, later messages of the agent form a new request
+pub fn decline_message_request_handler(sender: AgentPubKey) -> ExternResult<()> {
+    if !get_requests_from_chain()?.contains_key(&sender) {
+        return error(P2PMessageError::NotFound(String::from(
+            "Sorry. There is no message request from this agent.",
+        )));
+    }
+
+    commit_request(P2PMessageRequest {
+        sender,
+        status: RequestStatus::Declined {
+            timestamp: sys_time()?,
+        },
+    })?;
+
+    Ok(())
+}
+
+// a message waiting in an open request, its file chunks are taken before it is accepted
+pub fn get_queued_message(message_hash: &EntryHash) -> ExternResult<Option<P2PMessage>> {
+    let pending: Vec<PendingRequest> = get_requests_from_chain()?
+        .into_values()
+        .flatten()
+        .filter(|pending_request| pending_request.message_hash == *message_hash)
+        .collect();
+
+    Ok(get_queued_messages(&pending)?
+        .pop()
+        .map(|queued_message| queued_message.message))
+}
+
+/*
+ * when the open requests of an agent that arrived after `since` came in and how large they
+ * are, see rate_limits.rs. the sizes are kept on the requests, so the queued messages are
+ * not read, and a request answered later was answered after `since` as well
+ */
+pub fn get_queued_sizes(
+    sender: &AgentPubKey,
+    since: Timestamp,
+) -> ExternResult<Vec<(Timestamp, usize)>> {
+    let sizes = open_requests(query_since(18, since)?)
+        .remove(sender)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|pending_request| (pending_request.received, pending_request.size))
+        .collect();
+
+    Ok(sizes)
+}
+
+// the open requests keyed by sender, in the order their messages were received
+fn get_requests_from_chain() -> ExternResult<HashMap<AgentPubKey, Vec<PendingRequest>>> {
+    let queried_requests: Vec<Record> = query(
+        QueryFilter::new()
+            .entry_type(EntryType::App(AppEntryDef::new(
+                EntryDefIndex::from(18),
+                this_zome_index()?,
+                EntryVisibility::Private,
+            )))
+            .include_entries(true),
+    )?;
+
+    Ok(open_requests(queried_requests))
+}
+
+fn open_requests(queried_requests: Vec<Record>) -> HashMap<AgentPubKey, Vec<PendingRequest>> {
+    let mut requests: HashMap<AgentPubKey, Vec<PendingRequest>> = HashMap::new();
+    for record in queried_requests.into_iter() {
+        if let Ok(request_entry) = TryInto::<P2PMessageRequest>::try_into(record) {
+            match request_entry.status {
+                RequestStatus::Pending {
+                    message_hash,
+                    queued_hash,
+                    size,
+                    timestamp,
+                } => {
+                    let pending_request = PendingRequest {
+                        message_hash,
+                        queued_hash,
+                        size,
+                        received: timestamp,
+                    };
+                    requests
+                        .entry(request_entry.sender)
+                        .or_default()
+                        .push(pending_request);
+                }
+                RequestStatus::Accepted { .. } | RequestStatus::Declined { .. } => {
+                    requests.remove(&request_entry.sender);
+                }
+            }
+        } else {
+            continue;
+        }
+    }
+
+    requests
+}
+
+// the queued messages of the requests, in the order of the requests
+fn get_queued_messages(pending: &[PendingRequest]) -> ExternResult<Vec<QueuedMessage>> {
+    let queued_hashes: HashSet<EntryHash> = pending
+        .iter()
+        .map(|pending_request| pending_request.queued_hash.clone())
+        .collect();
+
+    let mut queued_messages: HashMap<EntryHash, P2PQueuedMessage> = HashMap::new();
+    for record in query_by_hashes(19, queued_hashes)?.into_iter() {
+        if let Ok(queued_entry) = TryInto::<P2PQueuedMessage>::try_into(record) {
+            queued_messages.insert(hash_entry(&queued_entry)?, queued_entry);
+        }
+    }
+
+    let mut queued: Vec<QueuedMessage> = Vec::new();
+    for pending_request in pending.iter() {
+        if let Some(queued_entry) = queued_messages.remove(&pending_request.queued_hash) {
+            queued.push(QueuedMessage {
+                message_hash: pending_request.message_hash.clone(),
+                message: queued_entry.message,
+                file: queued_entry.file,
+                received: pending_request.received,
+            });
+        }
+    }
+
+    Ok(queued)
+}
+
+fn commit_request(request: P2PMessageRequest) -> ExternResult<ActionHash> {
+    let request_entry = Entry::App(request.try_into()?);
+    host_call::<CreateInput, ActionHash>(
+        __hc__create_1,
+        CreateInput::new(
+            EntryDefLocation::app(this_zome_index()?, 18),
+            EntryVisibility::Private,
+            request_entry,
+            ChainTopOrdering::Relaxed,
+        ),
+    )
+}
+
+fn message_request(sender: AgentPubKey, queued: &[QueuedMessage]) -> ExternResult<MessageRequest> {
+    let mut messages: Vec<(EntryHash, P2PMessageData)> = Vec::new();
+    for queued_message in queued.iter() {
+        messages.push((
+            queued_message.message_hash.clone(),
+            message_data(&queued_message.message)?,
+        ));
+    }
+
+    let last_received = queued
+        .iter()
+        .map(|queued_message| queued_message.received)
+        .max()
+        .unwrap_or(Timestamp::from_micros(0));
+
+    Ok(MessageRequest {
+        sender,
+        messages,
+        last_received,
+    })
+}
+
+// the first messages of an agent cannot reply to anything on this chain
+fn message_data(message: &P2PMessage) -> ExternResult<P2PMessageData> {
+    Ok(P2PMessageData {
+        author: message.author.clone(),
+        receiver: message.receiver.clone(),
+        payload: decrypt_payload(message.payload.clone(), &message.author)?,
+        time_sent: message.time_sent,
+        reply_to: None,
+        edited: false,
+        deleted: false,
+        reactions: HashMap::new(),
+    })
+}
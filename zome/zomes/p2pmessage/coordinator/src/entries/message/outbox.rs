@@ -0,0 +1,500 @@
+use hdk::prelude::*;
+use std::collections::HashMap;
+
+use p2pmessage_coordinator_types::*;
+use p2pmessage_integrity_types::*;
+
+use crate::{
+    commit_message_to_receiver_chain::call_receive_message,
+    file_chunks::send_file_chunks,
+    helpers::{
+        get_deletion_from_chain, get_edit_from_chain, get_message_from_chain,
+        get_receipt_from_chain,
+    },
+    is_user_blocked::get_blocked_agents_from_chain,
+    utils::{call_agent, decode_error, error, error_reason, zome_call_error, zome_call_failure},
+};
+
+use super::utils::this_zome_index;
+
+/*
+ * OUTBOX FOR MESSAGES THAT COULD NOT REACH THE RECEIVER
+ * the outbox is append-only, the latest entry for a message hash is its current state,
+ * receipts going back to the sender of a message, and edits and retractions going to its
+ * receiver, have an outbox of their own
+ */
+
+pub fn deliver_message_handler(message_hash: EntryHash) -> ExternResult<OutboxStatus> {
+    let message = get_message_from_chain(message_hash.clone())?;
+
+    if message.author != agent_info()?.agent_latest_pubkey {
+        return error(P2PMessageError::Unauthorized(String::from(
+            "Sorry. Only the author of a message can deliver it.",
+        )));
+    }
+
+    let previous = get_outbox_from_chain()?.remove(&message_hash.clone().to_string());
+    let attempts = match &previous {
+        Some(P2PMessageOutbox {
+            status: OutboxStatus::Pending { attempts, .. } | OutboxStatus::Failed { attempts, .. },
+            ..
+        }) => *attempts,
+        _ => 0,
+    };
+
+    match send_to_receiver(message_hash.clone(), &message, previous.is_some(), attempts) {
+        Ok(status) => Ok(status),
+        // anything but an unreachable receiver stays failed until the delivery is retried
+        Err(e) => {
+            let status = OutboxStatus::Failed {
+                reason: error_reason(e),
+                attempts: attempts + 1,
+                timestamp: sys_time()?,
+            };
+            commit_outbox(P2PMessageOutbox {
+                message_hash,
+                receiver: message.receiver,
+                status: status.clone(),
+            })?;
+            Ok(status)
+        }
+    }
+}
+
+fn send_to_receiver(
+    message_hash: EntryHash,
+    message: &P2PMessage,
+    queued: bool,
+    attempts: u32,
+) -> ExternResult<OutboxStatus> {
+    match call_receive_message(message.clone())? {
+        ZomeCallResponse::Ok(extern_io) => {
+            let receipt_result: Result<P2PMessageReceipt, SerializedBytesError> =
+                extern_io.decode();
+            let receipt = match receipt_result {
+                Ok(receipt) => receipt,
+                Err(e) => return decode_error(e),
+            };
+
+            // the remaining chunks of a large file are sent with the next retry
+            if !send_file_chunks(message_hash.clone(), message)? {
+                let status = OutboxStatus::Pending {
+                    attempts: attempts + 1,
+                    timestamp: sys_time()?,
+                };
+                commit_outbox(P2PMessageOutbox {
+                    message_hash,
+                    receiver: message.receiver.clone(),
+                    status: status.clone(),
+                })?;
+                return Ok(status);
+            }
+
+            // a message request is not delivered until the receiver accepts it
+            if let Status::Sent { .. } = receipt.status {
+                let status = OutboxStatus::Requested {
+                    timestamp: sys_time()?,
+                };
+                commit_outbox(P2PMessageOutbox {
+                    message_hash,
+                    receiver: message.receiver.clone(),
+                    status: status.clone(),
+                })?;
+                return Ok(status);
+            }
+
+            let status = OutboxStatus::Delivered {
+                timestamp: sys_time()?,
+            };
+            // only messages that went through the outbox need to be marked as delivered
+            if queued {
+                commit_outbox(P2PMessageOutbox {
+                    message_hash,
+                    receiver: message.receiver.clone(),
+                    status: status.clone(),
+                })?;
+            }
+            Ok(status)
+        }
+        ZomeCallResponse::NetworkError(e) => {
+            debug!("receiver unreachable, queueing message: {}", e);
+            let status = OutboxStatus::Pending {
+                attempts: attempts + 1,
+                timestamp: sys_time()?,
+            };
+            commit_outbox(P2PMessageOutbox {
+                message_hash,
+                receiver: message.receiver.clone(),
+                status: status.clone(),
+            })?;
+            Ok(status)
+        }
+        failed => zome_call_error(failed),
+    }
+}
+
+pub fn flush_outbox_handler(
+    receiver: Option<AgentPubKey>,
+) -> ExternResult<HashMap<String, OutboxStatus>> {
+    let mut statuses: HashMap<String, OutboxStatus> = HashMap::new();
+    let blocked_agents = get_blocked_agents_from_chain()?;
+
+    for outbox_entry in get_pending_messages(receiver.clone())?.into_iter() {
+        // keep messages to blocked agents queued in case they get unblocked
+        if blocked_agents.contains(&outbox_entry.receiver) {
+            continue;
+        }
+
+        match deliver_message_handler(outbox_entry.message_hash.clone()) {
+            Ok(status) => {
+                statuses.insert(outbox_entry.message_hash.to_string(), status);
+            }
+            Err(e) => {
+                debug!("outbox delivery failed: {:?}", e);
+                continue;
+            }
+        }
+    }
+
+    for outbox_entry in get_pending_receipts(receiver.clone())?.into_iter() {
+        if blocked_agents.contains(&outbox_entry.sender) {
+            continue;
+        }
+
+        match deliver_receipt_handler(outbox_entry.receipt_hash.clone()) {
+            Ok(status) => {
+                statuses.insert(outbox_entry.receipt_hash.to_string(), status);
+            }
+            Err(e) => {
+                debug!("outbox receipt delivery failed: {:?}", e);
+                continue;
+            }
+        }
+    }
+
+    // after the messages, a change waits for the message it is about
+    for outbox_entry in get_pending_changes(receiver)?.into_iter() {
+        if blocked_agents.contains(&outbox_entry.receiver) {
+            continue;
+        }
+
+        match deliver_change_handler(outbox_entry.change_hash.clone()) {
+            Ok(status) => {
+                statuses.insert(outbox_entry.change_hash.to_string(), status);
+            }
+            Err(e) => {
+                debug!("outbox change delivery failed: {:?}", e);
+                continue;
+            }
+        }
+    }
+
+    Ok(statuses)
+}
+
+pub fn deliver_receipt_handler(receipt_hash: EntryHash) -> ExternResult<OutboxStatus> {
+    let receipt = get_receipt_from_chain(receipt_hash.clone())?;
+    let message = get_message_from_chain(receipt.id[0].clone())?;
+
+    if message.receiver != agent_info()?.agent_latest_pubkey {
+        return error(P2PMessageError::Unauthorized(String::from(
+            "Sorry. Only the receiver of a message can deliver its receipt.",
+        )));
+    }
+
+    let attempts = match get_receipt_outbox_from_chain()?.remove(&receipt_hash.to_string()) {
+        Some(P2PReceiptOutbox {
+            status: OutboxStatus::Pending { attempts, .. } | OutboxStatus::Failed { attempts, .. },
+            ..
+        }) => attempts,
+        _ => 0,
+    };
+
+    let status = match call_agent(message.author.clone(), "receive_receipt", &receipt)? {
+        ZomeCallResponse::Ok(_) => OutboxStatus::Delivered {
+            timestamp: sys_time()?,
+        },
+        ZomeCallResponse::NetworkError(e) => {
+            debug!("sender unreachable, queueing receipt: {}", e);
+            OutboxStatus::Pending {
+                attempts: attempts + 1,
+                timestamp: sys_time()?,
+            }
+        }
+        failed => OutboxStatus::Failed {
+            reason: zome_call_failure(failed).to_string(),
+            attempts: attempts + 1,
+            timestamp: sys_time()?,
+        },
+    };
+
+    // a delivered entry is the acknowledgement of the sender, see get_undelivered.rs
+    commit_receipt_outbox(P2PReceiptOutbox {
+        receipt_hash,
+        sender: message.author,
+        status: status.clone(),
+    })?;
+
+    Ok(status)
+}
+
+// the changes of a message that share the change outbox
+enum Change {
+    Edit(P2PMessageEdit),
+    Retraction(P2PMessageDeletion),
+}
+
+pub fn deliver_change_handler(change_hash: EntryHash) -> ExternResult<OutboxStatus> {
+    let change = get_change_from_chain(change_hash.clone())?;
+    let message_hash = match &change {
+        Change::Edit(edit) => edit.message_hash.clone(),
+        Change::Retraction(deletion) => deletion.message_hash.clone(),
+    };
+    let message = get_message_from_chain(message_hash.clone())?;
+
+    if message.author != agent_info()?.agent_latest_pubkey {
+        return error(P2PMessageError::Unauthorized(String::from(
+            "Sorry. Only the author of a message can deliver its changes.",
+        )));
+    }
+
+    let previous = get_change_outbox_from_chain()?.remove(&change_hash.to_string());
+
+    // the receiver cannot take a change of a message it does not have yet
+    if is_queued(&message_hash)? {
+        if let Some(outbox_entry) = previous {
+            return Ok(outbox_entry.status);
+        }
+        let status = OutboxStatus::Pending {
+            attempts: 0,
+            timestamp: sys_time()?,
+        };
+        commit_change_outbox(P2PChangeOutbox {
+            change_hash,
+            receiver: message.receiver,
+            status: status.clone(),
+        })?;
+        return Ok(status);
+    }
+
+    let attempts = match &previous {
+        Some(P2PChangeOutbox {
+            status: OutboxStatus::Pending { attempts, .. } | OutboxStatus::Failed { attempts, .. },
+            ..
+        }) => *attempts,
+        _ => 0,
+    };
+
+    let zome_call_response = match &change {
+        Change::Edit(edit) => call_agent(message.receiver.clone(), "receive_edit", edit)?,
+        Change::Retraction(deletion) => {
+            call_agent(message.receiver.clone(), "retract_message", deletion)?
+        }
+    };
+    let status = match zome_call_response {
+        ZomeCallResponse::Ok(_) => OutboxStatus::Delivered {
+            timestamp: sys_time()?,
+        },
+        ZomeCallResponse::NetworkError(e) => {
+            debug!("receiver unreachable, queueing change: {}", e);
+            OutboxStatus::Pending {
+                attempts: attempts + 1,
+                timestamp: sys_time()?,
+            }
+        }
+        failed => OutboxStatus::Failed {
+            reason: zome_call_failure(failed).to_string(),
+            attempts: attempts + 1,
+            timestamp: sys_time()?,
+        },
+    };
+
+    // only changes that went through the outbox need to be marked as delivered
+    if previous.is_some() || !matches!(status, OutboxStatus::Delivered { .. }) {
+        commit_change_outbox(P2PChangeOutbox {
+            change_hash,
+            receiver: message.receiver,
+            status: status.clone(),
+        })?;
+    }
+
+    Ok(status)
+}
+
+// the hash of a change is either the hash of an edit or of a deletion
+fn get_change_from_chain(change_hash: EntryHash) -> ExternResult<Change> {
+    match get_edit_from_chain(change_hash.clone()) {
+        Ok(edit) => Ok(Change::Edit(edit)),
+        Err(_) => Ok(Change::Retraction(get_deletion_from_chain(change_hash)?)),
+    }
+}
+
+// a message still in the outbox has not reached its receiver
+fn is_queued(message_hash: &EntryHash) -> ExternResult<bool> {
+    Ok(matches!(
+        get_outbox_from_chain()?.get(&message_hash.to_string()),
+        Some(P2PMessageOutbox {
+            status: OutboxStatus::Pending { .. } | OutboxStatus::Failed { .. },
+            ..
+        })
+    ))
+}
+
+pub fn get_pending_messages(receiver: Option<AgentPubKey>) -> ExternResult<Vec<P2PMessageOutbox>> {
+    let pending_messages = get_outbox_from_chain()?
+        .into_values()
+        .filter(|outbox_entry| match outbox_entry.status {
+            OutboxStatus::Pending { .. } => match receiver {
+                Some(ref agent) => *agent == outbox_entry.receiver,
+                None => true,
+            },
+            OutboxStatus::Delivered { .. }
+            | OutboxStatus::Requested { .. }
+            | OutboxStatus::Failed { .. } => false,
+        })
+        .collect();
+
+    Ok(pending_messages)
+}
+
+pub fn get_outbox_from_chain() -> ExternResult<HashMap<String, P2PMessageOutbox>> {
+    let queried_outbox: Vec<Record> = query(
+        QueryFilter::new()
+            .entry_type(EntryType::App(AppEntryDef::new(
+                EntryDefIndex::from(4),
+                this_zome_index()?,
+                EntryVisibility::Private,
+            )))
+            .include_entries(true),
+    )?;
+
+    let mut outbox: HashMap<String, P2PMessageOutbox> = HashMap::new();
+    for record in queried_outbox.into_iter() {
+        if let Ok(outbox_entry) = TryInto::<P2PMessageOutbox>::try_into(record) {
+            outbox.insert(outbox_entry.message_hash.clone().to_string(), outbox_entry);
+        } else {
+            continue;
+        }
+    }
+
+    Ok(outbox)
+}
+
+fn commit_outbox(outbox_entry: P2PMessageOutbox) -> ExternResult<ActionHash> {
+    let entry = Entry::App(outbox_entry.try_into()?);
+    host_call::<CreateInput, ActionHash>(
+        __hc__create_1,
+        CreateInput::new(
+            EntryDefLocation::app(this_zome_index()?, 4),
+            EntryVisibility::Private,
+            entry,
+            ChainTopOrdering::Relaxed,
+        ),
+    )
+}
+
+pub fn get_pending_receipts(receiver: Option<AgentPubKey>) -> ExternResult<Vec<P2PReceiptOutbox>> {
+    let pending_receipts = get_receipt_outbox_from_chain()?
+        .into_values()
+        .filter(|outbox_entry| match outbox_entry.status {
+            OutboxStatus::Pending { .. } => match receiver {
+                Some(ref agent) => *agent == outbox_entry.sender,
+                None => true,
+            },
+            OutboxStatus::Delivered { .. }
+            | OutboxStatus::Requested { .. }
+            | OutboxStatus::Failed { .. } => false,
+        })
+        .collect();
+
+    Ok(pending_receipts)
+}
+
+pub fn get_receipt_outbox_from_chain() -> ExternResult<HashMap<String, P2PReceiptOutbox>> {
+    let queried_outbox: Vec<Record> = query(
+        QueryFilter::new()
+            .entry_type(EntryType::App(AppEntryDef::new(
+                EntryDefIndex::from(17),
+                this_zome_index()?,
+                EntryVisibility::Private,
+            )))
+            .include_entries(true),
+    )?;
+
+    let mut outbox: HashMap<String, P2PReceiptOutbox> = HashMap::new();
+    for record in queried_outbox.into_iter() {
+        if let Ok(outbox_entry) = TryInto::<P2PReceiptOutbox>::try_into(record) {
+            outbox.insert(outbox_entry.receipt_hash.clone().to_string(), outbox_entry);
+        } else {
+            continue;
+        }
+    }
+
+    Ok(outbox)
+}
+
+fn commit_receipt_outbox(outbox_entry: P2PReceiptOutbox) -> ExternResult<ActionHash> {
+    let entry = Entry::App(outbox_entry.try_into()?);
+    host_call::<CreateInput, ActionHash>(
+        __hc__create_1,
+        CreateInput::new(
+            EntryDefLocation::app(this_zome_index()?, 17),
+            EntryVisibility::Private,
+            entry,
+            ChainTopOrdering::Relaxed,
+        ),
+    )
+}
+
+pub fn get_pending_changes(receiver: Option<AgentPubKey>) -> ExternResult<Vec<P2PChangeOutbox>> {
+    let pending_changes = get_change_outbox_from_chain()?
+        .into_values()
+        .filter(|outbox_entry| match outbox_entry.status {
+            OutboxStatus::Pending { .. } => match receiver {
+                Some(ref agent) => *agent == outbox_entry.receiver,
+                None => true,
+            },
+            OutboxStatus::Delivered { .. }
+            | OutboxStatus::Requested { .. }
+            | OutboxStatus::Failed { .. } => false,
+        })
+        .collect();
+
+    Ok(pending_changes)
+}
+
+pub fn get_change_outbox_from_chain() -> ExternResult<HashMap<String, P2PChangeOutbox>> {
+    let queried_outbox: Vec<Record> = query(
+        QueryFilter::new()
+            .entry_type(EntryType::App(AppEntryDef::new(
+                EntryDefIndex::from(22),
+                this_zome_index()?,
+                EntryVisibility::Private,
+            )))
+            .include_entries(true),
+    )?;
+
+    let mut outbox: HashMap<String, P2PChangeOutbox> = HashMap::new();
+    for record in queried_outbox.into_iter() {
+        if let Ok(outbox_entry) = TryInto::<P2PChangeOutbox>::try_into(record) {
+            outbox.insert(outbox_entry.change_hash.clone().to_string(), outbox_entry);
+        } else {
+            continue;
+        }
+    }
+
+    Ok(outbox)
+}
+
+fn commit_change_outbox(outbox_entry: P2PChangeOutbox) -> ExternResult<ActionHash> {
+    let entry = Entry::App(outbox_entry.try_into()?);
+    host_call::<CreateInput, ActionHash>(
+        __hc__create_1,
+        CreateInput::new(
+            EntryDefLocation::app(this_zome_index()?, 22),
+            EntryVisibility::Private,
+            entry,
+            ChainTopOrdering::Relaxed,
+        ),
+    )
+}
@@ -4,7 +4,10 @@ use std::collections::HashMap;
 use p2pmessage_coordinator_types::*;
 use p2pmessage_integrity_types::*;
 
-use crate::utils::error;
+use crate::{
+    get_pinned_messages::get_pins_from_chain,
+    utils::{call_agent, decode_error, error, zome_call_error},
+};
 
 use super::utils::this_zome_index;
 
@@ -16,15 +19,7 @@ pub fn pin_message_handler(
     let pin = P2PMessagePin {
         id: pin_message_input.message_hashes,
         conversants: pin_message_input.conversants.clone(),
-        status: if pin_message_input.status == "Pinned" {
-            PinStatus::Pinned {
-                timestamp: pin_message_input.timestamp,
-            }
-        } else {
-            PinStatus::Unpinned {
-                timestamp: pin_message_input.timestamp,
-            }
-        },
+        status: pin_message_input.status,
     };
 
     let conversant: AgentPubKey;
@@ -34,13 +29,9 @@ pub fn pin_message_handler(
         conversant = pin_message_input.conversants[1].clone()
     }
 
-    let zome_call_response: ZomeCallResponse = call_remote(
-        conversant,
-        zome_info()?.name,
-        FunctionName("sync_pins".into()),
-        None,
-        &pin,
-    )?;
+    check_pin(&conversant, &pin)?;
+
+    let zome_call_response: ZomeCallResponse = call_agent(conversant, "sync_pins", &pin)?;
 
     match zome_call_response {
         ZomeCallResponse::Ok(extern_io) => {
@@ -61,17 +52,40 @@ pub fn pin_message_handler(
             let result = extern_io.decode();
             match result {
                 Ok(map) => return Ok(map),
-                Err(e) => return Err(wasm_error!(WasmErrorInner::Guest(String::from(e)))),
+                Err(e) => decode_error(e),
             }
         }
-        ZomeCallResponse::Unauthorized(..) => {
-            return error("Sorry, something went wrong. [Authorization error]");
+        failed => zome_call_error(failed),
+    }
+}
+
+// the pin is checked by both conversants against the pins of the conversation they have
+pub fn check_pin(conversant: &AgentPubKey, pin: &P2PMessagePin) -> ExternResult<()> {
+    if let PinStatus::Pinned {
+        timestamp,
+        expires_at,
+    } = pin.status
+    {
+        if let Some(expires_at) = expires_at {
+            if expires_at <= timestamp {
+                return error(P2PMessageError::InvalidPin(String::from(
+                    "Sorry. A pin must expire after it was pinned.",
+                )));
+            }
         }
-        ZomeCallResponse::NetworkError(_e) => {
-            return error("Sorry, something went wrong. [Network error]");
+
+        let max_pins = get_properties()?.max_pins_per_conversation;
+        let mut current_pins = get_pins_from_chain(conversant)?;
+        for message_hash in pin.id.iter() {
+            current_pins.insert(message_hash.to_string(), pin.clone());
         }
-        ZomeCallResponse::CountersigningSession(_e) => {
-            return error("Sorry, something went wrong. [Countersigning error]");
+        if current_pins.len() > max_pins {
+            return error(P2PMessageError::PinLimitExceeded(format!(
+                "Sorry. Only {} messages can be pinned in a conversation.",
+                max_pins
+            )));
         }
     }
+
+    Ok(())
 }
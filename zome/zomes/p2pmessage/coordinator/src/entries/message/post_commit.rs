@@ -0,0 +1,102 @@
+use hdk::prelude::*;
+
+use p2pmessage_coordinator_types::*;
+use p2pmessage_integrity_types::*;
+
+use crate::{
+    helpers::{get_message_from_chain, get_receipt_from_chain},
+    utils::{call_self, error_reason, zome_call_failure},
+};
+
+/*
+ * POST COMMIT DELIVERY
+ * post_commit cannot fail, deliveries that do not go through are reported to the UI
+ * as ERROR_P2P_MESSAGE and ERROR_P2P_RECEIPT signals so it can offer a retry
+ */
+
+pub fn post_commit_handler(actions: Vec<SignedActionHashed>) {
+    for signed_action in actions.into_iter() {
+        if let Action::Create(create) = signed_action.action() {
+            let result = match &create.entry_type {
+                EntryType::App(apptype) => match apptype.entry_index() {
+                    EntryDefIndex(0) => deliver_message(create),
+                    EntryDefIndex(1) => deliver_receipt(create),
+                    _ => Ok(()),
+                },
+                _ => Ok(()),
+            };
+
+            if let Err(e) = result {
+                debug!("post commit delivery failed: {:?}", e);
+            }
+        }
+    }
+}
+
+fn deliver_message(create: &Create) -> ExternResult<()> {
+    let message = get_message_from_chain(create.entry_hash.clone())?;
+
+    if message.author == message.receiver {
+        return Ok(());
+    }
+
+    if message.author != create.author {
+        // the author is online, push anything still queued for them
+        let _res = call_self("flush_outbox", Some(message.author));
+        return Ok(());
+    }
+
+    // post_commit cannot write, deliver_message records the delivery state in the outbox
+    let call_result = call_self("deliver_message", create.entry_hash.clone());
+    if let Some(reason) = delivery_failure(call_result) {
+        let signal_details = SignalDetails {
+            name: "ERROR_P2P_MESSAGE".to_string(),
+            payload: Signal::ErrorMessage(ErrorMessage {
+                message,
+                reason,
+            }),
+        };
+        emit_signal(&signal_details)?;
+    }
+
+    Ok(())
+}
+
+fn deliver_receipt(create: &Create) -> ExternResult<()> {
+    let receipt = get_receipt_from_chain(create.entry_hash.clone())?;
+
+    // only delivered receipts of received messages go back to the sender
+    if let Status::Delivered { .. } = receipt.status {
+        let message = get_message_from_chain(receipt.id[0].clone())?;
+        if message.receiver != create.author {
+            return Ok(());
+        }
+
+        let call_result = call_self("deliver_receipt", create.entry_hash.clone());
+        if let Some(reason) = delivery_failure(call_result) {
+            let signal_details = SignalDetails {
+                name: "ERROR_P2P_RECEIPT".to_string(),
+                payload: Signal::ErrorReceipt(ErrorReceipt {
+                    receipt,
+                    reason,
+                }),
+            };
+            emit_signal(&signal_details)?;
+        }
+    }
+
+    Ok(())
+}
+
+// queued deliveries are retried later and are not reported
+fn delivery_failure(call_result: ExternResult<ZomeCallResponse>) -> Option<String> {
+    match call_result {
+        Ok(ZomeCallResponse::Ok(extern_io)) => match extern_io.decode() {
+            Ok(OutboxStatus::Failed { reason, .. }) => Some(reason),
+            Ok(_) => None,
+            Err(e) => Some(String::from(e)),
+        },
+        Ok(failed) => Some(zome_call_failure(failed).to_string()),
+        Err(e) => Some(error_reason(e)),
+    }
+}
@@ -0,0 +1,208 @@
+use hdk::prelude::*;
+
+use p2pmessage_coordinator_types::*;
+use p2pmessage_integrity_types::*;
+
+use crate::{
+    chain_index::{get_chain_index, query_since},
+    message_requests::get_queued_sizes,
+    utils::{call_self, error},
+};
+
+use super::utils::this_zome_index;
+
+/*
+ * RATE LIMITS ON INBOUND CALLS
+ * the rate_limits dna property caps what a single sender can make this agent commit,
+ * calls over a limit fail with RATE_LIMITED and can leave a local P2PSpamReport
+ */
+
+const SECOND: i64 = 1_000_000;
+const MINUTE: i64 = 60 * SECOND;
+const DAY: i64 = 24 * 60 * MINUTE;
+
+// files sent in chunks count in full with their message, every chunk is checked against the size
+pub fn payload_size(payload: &Payload) -> usize {
+    match payload {
+        Payload::Text { payload } => payload.len(),
+        Payload::File { metadata, .. } => metadata.file_size,
+        Payload::Encrypted { data, .. } => data.as_encrypted_data_ref().len(),
+    }
+}
+
+// direct and group messages of the sender count against the same limits
+pub fn check_message_rate(sender: &AgentPubKey, payload: &Payload) -> ExternResult<()> {
+    let limits = get_properties()?.rate_limits;
+    let now = sys_time()?.as_micros();
+
+    let received = get_received_messages(sender, Timestamp::from_micros(now - DAY))?;
+    let last_minute = received
+        .iter()
+        .filter(|(timestamp, _size)| *timestamp > now - MINUTE)
+        .count();
+    if last_minute >= limits.messages_per_minute {
+        return rate_limited(
+            sender,
+            RateLimit::MessagesPerMinute,
+            format!(
+                "Sorry. Only {} messages can be received from an agent per minute.",
+                limits.messages_per_minute
+            ),
+        );
+    }
+
+    let last_day: usize = received.iter().map(|(_timestamp, size)| size).sum();
+    if last_day + payload_size(payload) > limits.bytes_per_day {
+        return rate_limited(
+            sender,
+            RateLimit::BytesPerDay,
+            format!(
+                "Sorry. Only {} bytes can be received from an agent per day.",
+                limits.bytes_per_day
+            ),
+        );
+    }
+
+    Ok(())
+}
+
+// pins of the conversation with the sender count against the messages per minute
+pub fn check_pin_rate(sender: &AgentPubKey) -> ExternResult<()> {
+    check_change_rate(sender, 2, "pins", |record| {
+        TryInto::<P2PMessagePin>::try_into(record)
+            .is_ok_and(|pin_entry| pin_entry.conversants.contains(sender))
+    })
+}
+
+pub fn check_reaction_rate(sender: &AgentPubKey) -> ExternResult<()> {
+    check_change_rate(sender, 8, "reactions", |record| {
+        TryInto::<P2PMessageReaction>::try_into(record)
+            .is_ok_and(|reaction_entry| reaction_entry.reactor == *sender)
+    })
+}
+
+pub fn check_edit_rate(sender: &AgentPubKey) -> ExternResult<()> {
+    check_change_rate(sender, 6, "edits", |record| {
+        TryInto::<P2PMessageEdit>::try_into(record)
+            .is_ok_and(|edit_entry| edit_entry.author == *sender)
+    })
+}
+
+fn check_change_rate(
+    sender: &AgentPubKey,
+    index: u8,
+    changes: &str,
+    from_sender: impl Fn(Record) -> bool,
+) -> ExternResult<()> {
+    let limits = get_properties()?.rate_limits;
+    let now = sys_time()?.as_micros();
+
+    let last_minute = query_since(index, Timestamp::from_micros(now - MINUTE))?
+        .into_iter()
+        .filter(|record| from_sender(record.clone()))
+        .count();
+    if last_minute >= limits.messages_per_minute {
+        return rate_limited(
+            sender,
+            RateLimit::MessagesPerMinute,
+            format!(
+                "Sorry. Only {} {} can be synced by an agent per minute.",
+                limits.messages_per_minute, changes
+            ),
+        );
+    }
+
+    Ok(())
+}
+
+// the messages of the sender committed here since, including open message requests
+fn get_received_messages(
+    sender: &AgentPubKey,
+    since: Timestamp,
+) -> ExternResult<Vec<(i64, usize)>> {
+    let mut received: Vec<(i64, usize)> = get_queued_sizes(sender, since)?
+        .into_iter()
+        .filter(|(timestamp, _size)| *timestamp > since)
+        .map(|(timestamp, size)| (timestamp.as_micros(), size))
+        .collect();
+
+    // the time a message was committed here counts, time_sent is up to the sender
+    received.extend(
+        get_chain_index()?
+            .load_received_since(sender, since)?
+            .into_iter()
+            .map(|row| (row.time_committed.as_micros(), row.size)),
+    );
+
+    for record in query_since(11, since)?.into_iter() {
+        let timestamp = record.action().timestamp().as_micros();
+        if let Ok(message_entry) = TryInto::<P2PGroupMessage>::try_into(record) {
+            if message_entry.author == *sender {
+                received.push((timestamp, payload_size(&message_entry.payload)));
+            }
+        }
+    }
+
+    Ok(received)
+}
+
+pub fn get_rate_limits_handler() -> ExternResult<RateLimits> {
+    Ok(get_properties()?.rate_limits)
+}
+
+// only called by rate_limited through call_self, other callers cannot leave reports here
+pub fn record_spam_report_handler(report: P2PSpamReport) -> ExternResult<ActionHash> {
+    if call_info()?.provenance != agent_info()?.agent_latest_pubkey {
+        return error(P2PMessageError::Unauthorized(String::from(
+            "Sorry. Spam reports can only be recorded by the agent itself.",
+        )));
+    }
+
+    let report_entry = Entry::App(report.try_into()?);
+    host_call::<CreateInput, ActionHash>(
+        __hc__create_1,
+        CreateInput::new(
+            EntryDefLocation::app(this_zome_index()?, 20),
+            EntryVisibility::Private,
+            report_entry,
+            ChainTopOrdering::Relaxed,
+        ),
+    )
+}
+
+pub fn get_spam_reports_handler() -> ExternResult<Vec<P2PSpamReport>> {
+    let queried_reports: Vec<Record> = query(
+        QueryFilter::new()
+            .entry_type(EntryType::App(AppEntryDef::new(
+                EntryDefIndex::from(20),
+                this_zome_index()?,
+                EntryVisibility::Private,
+            )))
+            .include_entries(true),
+    )?;
+
+    let mut reports: Vec<P2PSpamReport> = Vec::new();
+    for record in queried_reports.into_iter().rev() {
+        if let Ok(report_entry) = TryInto::<P2PSpamReport>::try_into(record) {
+            reports.push(report_entry);
+        }
+    }
+
+    Ok(reports)
+}
+
+fn rate_limited<T>(sender: &AgentPubKey, limit: RateLimit, message: String) -> ExternResult<T> {
+    // the failing call rolls back its own writes, the report is committed by a call of its own
+    if get_properties()?.rate_limits.report_spam {
+        let report = P2PSpamReport {
+            sender: sender.clone(),
+            limit,
+            timestamp: sys_time()?,
+        };
+        if let Err(e) = call_self("record_spam_report", report) {
+            debug!("spam report failed: {:?}", e);
+        }
+    }
+
+    error(P2PMessageError::RateLimited(message))
+}
@@ -0,0 +1,128 @@
+use hdk::prelude::*;
+use std::collections::HashMap;
+
+use p2pmessage_coordinator_types::*;
+use p2pmessage_integrity_types::*;
+
+use crate::{
+    helpers::{
+        get_deletions_of_messages, get_message_from_chain, get_reactions_of_messages,
+        message_key,
+    },
+    utils::{call_agent, error, zome_call_error},
+};
+
+use super::utils::this_zome_index;
+
+pub fn react_to_message_handler(
+    reaction_input: ReactionInput,
+) -> ExternResult<HashMap<String, P2PMessageReaction>> {
+    let status = ReactionStatus::Reacted {
+        timestamp: sys_time()?,
+    };
+    commit_reaction(reaction_input, status)
+}
+
+pub fn remove_reaction_handler(
+    reaction_input: ReactionInput,
+) -> ExternResult<HashMap<String, P2PMessageReaction>> {
+    let status = ReactionStatus::Removed {
+        timestamp: sys_time()?,
+    };
+    commit_reaction(reaction_input, status)
+}
+
+pub fn has_reacted(
+    message_hash: &EntryHash,
+    message: &P2PMessage,
+    emoji: &str,
+    reactor: &AgentPubKey,
+) -> ExternResult<bool> {
+    let reactions = get_reactions_of_messages(&message_key(message_hash, message)?)?;
+    let reacted = match reactions.get(&message_hash.clone().to_string()) {
+        Some(message_reactions) => match message_reactions.get(emoji) {
+            Some(reactors) => reactors.contains(reactor),
+            None => false,
+        },
+        None => false,
+    };
+
+    Ok(reacted)
+}
+
+fn commit_reaction(
+    reaction_input: ReactionInput,
+    status: ReactionStatus,
+) -> ExternResult<HashMap<String, P2PMessageReaction>> {
+    let me = agent_info()?.agent_latest_pubkey;
+    let message = get_message_from_chain(reaction_input.message_hash.clone())?;
+
+    if get_deletions_of_messages(&message_key(&reaction_input.message_hash, &message)?)?
+        .contains_key(&reaction_input.message_hash.clone().to_string())
+    {
+        return error(P2PMessageError::ValidationFailed(String::from(
+            "Sorry. You cannot react to a deleted message.",
+        )));
+    }
+
+    let reacted = has_reacted(
+        &reaction_input.message_hash,
+        &message,
+        &reaction_input.emoji,
+        &me,
+    )?;
+    match status {
+        ReactionStatus::Reacted { .. } if reacted => {
+            return error(P2PMessageError::ValidationFailed(String::from(
+                "Sorry. You already reacted to this message with this emoji.",
+            )));
+        }
+        ReactionStatus::Removed { .. } if !reacted => {
+            return error(P2PMessageError::ValidationFailed(String::from(
+                "Sorry. You have not reacted to this message with this emoji.",
+            )));
+        }
+        _ => (),
+    }
+
+    let reaction = P2PMessageReaction {
+        message_hash: reaction_input.message_hash,
+        reactor: me.clone(),
+        conversants: vec![message.author.clone(), message.receiver.clone()],
+        emoji: reaction_input.emoji,
+        status,
+    };
+
+    // message self
+    if message.author != message.receiver {
+        let conversant = if message.author != me {
+            message.author
+        } else {
+            message.receiver
+        };
+
+        let zome_call_response: ZomeCallResponse =
+            call_agent(conversant, "sync_reactions", &reaction)?;
+
+        match zome_call_response {
+            ZomeCallResponse::Ok(_) => (),
+            failed => return zome_call_error(failed),
+        }
+    }
+
+    let reaction_entry = Entry::App(reaction.clone().try_into()?);
+    let reaction_hash = host_call::<CreateInput, ActionHash>(
+        __hc__create_1,
+        CreateInput::new(
+            EntryDefLocation::app(this_zome_index()?, 8),
+            EntryVisibility::Private,
+            reaction_entry,
+            ChainTopOrdering::Relaxed,
+        ),
+    )?;
+
+    let mut reaction_contents: HashMap<String, P2PMessageReaction> = HashMap::new();
+    reaction_contents.insert(reaction_hash.to_string(), reaction);
+
+    Ok(reaction_contents)
+}
@@ -4,7 +4,7 @@ use std::collections::HashMap;
 use p2pmessage_coordinator_types::*;
 use p2pmessage_integrity_types::*;
 
-use crate::utils::error;
+use crate::outbox::deliver_receipt_handler;
 
 use super::utils::this_zome_index;
 
@@ -18,41 +18,26 @@ pub fn read_message_handler(
         },
     };
 
-    let zome_call_response: ZomeCallResponse = call_remote(
-        read_message_input.sender,
-        zome_info()?.name,
-        FunctionName("receive_receipt".into()),
-        None,
-        &receipt,
+    // the read state is kept even when the sender cannot be reached
+    let read_receipt_entry = Entry::App(receipt.clone().try_into()?);
+    host_call::<CreateInput, ActionHash>(
+        __hc__create_1,
+        CreateInput::new(
+            EntryDefLocation::app(this_zome_index()?, 1),
+            EntryVisibility::Private,
+            read_receipt_entry,
+            ChainTopOrdering::Relaxed,
+        ),
     )?;
+    let receipt_hash = hash_entry(&receipt)?;
 
-    match zome_call_response {
-        ZomeCallResponse::Ok(extern_io) => {
-            let read_receipt_entry = Entry::App(receipt.try_into()?);
-            host_call::<CreateInput, ActionHash>(
-                __hc__create_1,
-                CreateInput::new(
-                    EntryDefLocation::app(this_zome_index()?, 1),
-                    EntryVisibility::Private,
-                    read_receipt_entry,
-                    ChainTopOrdering::Relaxed,
-                ),
-            )?;
-
-            let result = extern_io.decode();
-            match result {
-                Ok(map) => return Ok(map),
-                Err(e) => return Err(wasm_error!(WasmErrorInner::Guest(String::from(e)))),
-            }
-        }
-        ZomeCallResponse::Unauthorized(..) => {
-            return error("Sorry, something went wrong. [Authorization error]");
-        }
-        ZomeCallResponse::NetworkError(_e) => {
-            return error("Sorry, something went wrong. [Network error]");
-        }
-        ZomeCallResponse::CountersigningSession(_e) => {
-            return error("Sorry, something went wrong. [Countersigning error]");
-        }
+    // an offline sender gets the receipt when the outbox is flushed, see outbox.rs
+    if let Err(e) = deliver_receipt_handler(receipt_hash.clone()) {
+        debug!("read receipt delivery failed: {:?}", e);
     }
+
+    let mut receipt_contents: HashMap<String, P2PMessageReceipt> = HashMap::new();
+    receipt_contents.insert(receipt_hash.to_string(), receipt);
+
+    Ok(receipt_contents)
 }
@@ -0,0 +1,71 @@
+use hdk::prelude::*;
+use std::collections::HashMap;
+
+use p2pmessage_coordinator_types::*;
+use p2pmessage_integrity_types::*;
+
+use crate::{
+    encryption::decrypt_payload, helpers::get_message_from_chain, is_user_blocked::is_user_blocked,
+    rate_limits::check_edit_rate, search_messages::index_messages, utils::error,
+};
+
+use super::utils::this_zome_index;
+
+pub fn receive_edit_handler(edit: P2PMessageEdit) -> ExternResult<HashMap<String, P2PMessageEdit>> {
+    let caller = call_info()?.provenance;
+
+    // edits from blocked agents are silently dropped
+    if is_user_blocked(caller.clone())? {
+        return Ok(HashMap::new());
+    }
+
+    if edit.receiver != agent_info()?.agent_latest_pubkey {
+        return error(P2PMessageError::Unauthorized(String::from(
+            "Sorry. Edits can only be received by the receiver of the message.",
+        )));
+    }
+
+    let message = get_message_from_chain(edit.message_hash.clone())?;
+    if message.author != edit.author || message.author != caller {
+        return error(P2PMessageError::Unauthorized(String::from(
+            "Sorry. Only the author of a message can edit it.",
+        )));
+    }
+    check_edit_rate(&caller)?;
+
+    // the search index covers the earlier calls only, this one has not committed yet
+    let _res = index_messages();
+
+    let edit_entry = Entry::App(edit.clone().try_into()?);
+    host_call::<CreateInput, ActionHash>(
+        __hc__create_1,
+        CreateInput::new(
+            EntryDefLocation::app(this_zome_index()?, 6),
+            EntryVisibility::Private,
+            edit_entry,
+            ChainTopOrdering::Relaxed,
+        ),
+    )?;
+
+    let mut edit_contents: HashMap<String, P2PMessageEdit> = HashMap::new();
+    edit_contents.insert(
+        hash_entry(&edit)?.to_string(),
+        P2PMessageEdit {
+            payload: decrypt_payload(edit.payload.clone(), &edit.author)?,
+            ..edit
+        },
+    );
+
+    let signal = Signal::P2PMessageEdit(EditSignal {
+        edit: edit_contents.clone(),
+    });
+
+    let signal_details = SignalDetails {
+        name: "RECEIVE_P2P_MESSAGE_EDIT".to_string(),
+        payload: signal,
+    };
+
+    emit_signal(&signal_details)?;
+
+    Ok(edit_contents)
+}
@@ -1,8 +1,21 @@
 use hdk::prelude::*;
+use std::collections::HashMap;
 
 use p2pmessage_coordinator_types::*;
 use p2pmessage_integrity_types::*;
 
+use crate::{
+    chain_index::index_chain,
+    encryption::{decrypt_file, decrypt_payload},
+    helpers::get_message_from_chain,
+    is_user_blocked::is_user_blocked,
+    message_requests::{is_message_request, queue_message_request},
+    rate_limits::check_message_rate,
+    search_messages::index_messages,
+    utils::error,
+    verify_file::verify_received_file,
+};
+
 use super::utils::this_zome_index;
 
 pub fn receive_message_handler(input: ReceiveMessageInput) -> ExternResult<P2PMessageReceipt> {
@@ -12,6 +25,49 @@ pub fn receive_message_handler(input: ReceiveMessageInput) -> ExternResult<P2PMe
             timestamp: sys_time()?,
         },
     };
+
+    let caller = call_info()?.provenance;
+    if input.message.author != caller || input.message.receiver != agent_info()?.agent_latest_pubkey
+    {
+        return error(P2PMessageError::Unauthorized(String::from(
+            "Sorry. Messages can only be sent by their author to their receiver.",
+        )));
+    }
+
+    // a retry from the sender's outbox, the message is already on this chain
+    // messages from blocked agents are silently dropped
+    if get_message_from_chain(hash_entry(&input.message)?).is_ok() || is_user_blocked(caller)? {
+        return Ok(receipt);
+    }
+
+    // the indexes cover the earlier calls only, this one has not committed yet
+    let _res = index_chain();
+    let _res = index_messages();
+
+    // file bytes are stored decrypted, their hash is the one in the file metadata
+    let file = match input.encrypted_file.clone() {
+        Some(encrypted_file) => Some(decrypt_file(encrypted_file, &input.message)?),
+        None => input.file.clone(),
+    };
+    let payload = decrypt_payload(input.message.payload.clone(), &input.message.author)?;
+    check_message_rate(&input.message.author, &payload)?;
+
+    // the message is rejected when its file does not match the metadata
+    if let Payload::File { ref metadata, .. } = payload {
+        verify_received_file(&input.message, metadata, file.as_ref())?;
+    }
+
+    // the sender gets no delivered receipt until the request is accepted, see message_requests.rs
+    if is_message_request(&input.message.author)? {
+        queue_message_request(input.message, file)?;
+        return Ok(P2PMessageReceipt {
+            id: receipt.id,
+            status: Status::Sent {
+                timestamp: sys_time()?,
+            },
+        });
+    }
+
     let receipt_entry = Entry::App(receipt.clone().try_into()?);
     let message_entry = Entry::App(input.message.clone().try_into()?);
     host_call::<CreateInput, ActionHash>(
@@ -33,7 +89,7 @@ pub fn receive_message_handler(input: ReceiveMessageInput) -> ExternResult<P2PMe
         ),
     )?;
 
-    if let Some(file) = input.file.clone() {
+    if let Some(file) = file {
         let file_entry = Entry::App(file.clone().try_into()?);
         host_call::<CreateInput, ActionHash>(
             __hc__create_1,
@@ -50,43 +106,34 @@ pub fn receive_message_handler(input: ReceiveMessageInput) -> ExternResult<P2PMe
     message_return = P2PMessageData {
         author: input.message.author.clone(),
         receiver: input.message.receiver.clone(),
-        payload: input.message.payload.clone(),
+        payload: payload.clone(),
         time_sent: input.message.time_sent.clone(),
         reply_to: None,
+        edited: false,
+        deleted: false,
+        reactions: HashMap::new(),
     };
     if let Some(ref reply_to_hash) = input.message.reply_to {
-        let queried_messages: Vec<Record> = query(
-            QueryFilter::new()
-                .entry_type(EntryType::App(AppEntryDef::new(
-                    EntryDefIndex::from(0),
-                    this_zome_index()?,
-                    EntryVisibility::Private,
-                )))
-                .include_entries(true),
-        )?;
-        for queried_message in queried_messages.into_iter() {
-            if let Ok(message_entry) = TryInto::<P2PMessage>::try_into(queried_message) {
-                let message_hash = hash_entry(&message_entry)?;
-
-                if *reply_to_hash == message_hash {
-                    let replied_to_message = P2PMessageReplyTo {
-                        hash: message_hash,
-                        author: message_entry.author,
-                        receiver: message_entry.receiver,
-                        payload: message_entry.payload,
-                        time_sent: message_entry.time_sent,
-                        reply_to: None,
-                    };
-
-                    message_return = P2PMessageData {
-                        author: input.message.author.clone(),
-                        receiver: input.message.receiver.clone(),
-                        payload: input.message.payload.clone(),
-                        time_sent: input.message.time_sent.clone(),
-                        reply_to: Some(replied_to_message),
-                    };
-                }
-            }
+        if let Ok(message_entry) = get_message_from_chain(reply_to_hash.clone()) {
+            let replied_to_message = P2PMessageReplyTo {
+                hash: reply_to_hash.clone(),
+                author: message_entry.author.clone(),
+                receiver: message_entry.receiver,
+                payload: decrypt_payload(message_entry.payload, &message_entry.author)?,
+                time_sent: message_entry.time_sent,
+                reply_to: None,
+            };
+
+            message_return = P2PMessageData {
+                author: input.message.author.clone(),
+                receiver: input.message.receiver.clone(),
+                payload: payload.clone(),
+                time_sent: input.message.time_sent,
+                reply_to: Some(replied_to_message),
+                edited: false,
+                deleted: false,
+                reactions: HashMap::new(),
+            };
         }
     }
 
@@ -4,11 +4,38 @@ use std::collections::HashMap;
 use p2pmessage_coordinator_types::*;
 use p2pmessage_integrity_types::*;
 
+use crate::{helpers::get_message_from_chain, is_user_blocked::is_user_blocked, utils::error};
+
 use super::utils::this_zome_index;
 
 pub fn receive_receipt_handler(
     receipt: P2PMessageReceipt,
 ) -> ExternResult<HashMap<String, P2PMessageReceipt>> {
+    let caller = call_info()?.provenance;
+    let me = agent_info()?.agent_latest_pubkey;
+
+    // receipts from blocked agents are silently dropped
+    if is_user_blocked(caller.clone())? {
+        return Ok(HashMap::new());
+    }
+
+    // receipts are only taken for messages on this chain that we sent to the caller
+    if receipt.id.is_empty() {
+        return error(P2PMessageError::ValidationFailed(String::from(
+            "Sorry. The receipt does not name any message.",
+        )));
+    }
+    for message_hash in receipt.id.iter() {
+        match get_message_from_chain(message_hash.clone()) {
+            Ok(message) if message.author == me && message.receiver == caller => (),
+            _ => {
+                return error(P2PMessageError::Unauthorized(String::from(
+                    "Sorry. Receipts can only be sent by the receiver of the messages.",
+                )))
+            }
+        }
+    }
+
     let receipt_entry = Entry::App(receipt.clone().try_into()?);
 
     let receipt_hash = host_call::<CreateInput, ActionHash>(
@@ -0,0 +1,57 @@
+use hdk::prelude::*;
+use std::collections::HashMap;
+
+use p2pmessage_coordinator_types::*;
+use p2pmessage_integrity_types::*;
+
+use crate::{helpers::get_message_from_chain, is_user_blocked::is_user_blocked, utils::error};
+
+use super::utils::this_zome_index;
+
+pub fn retract_message_handler(
+    deletion: P2PMessageDeletion,
+) -> ExternResult<HashMap<String, P2PMessageDeletion>> {
+    let caller = call_info()?.provenance;
+
+    // retractions from blocked agents are silently dropped
+    if is_user_blocked(caller.clone())? {
+        return Ok(HashMap::new());
+    }
+
+    let message = get_message_from_chain(deletion.message_hash.clone())?;
+    if deletion.scope != DeleteScope::ForEveryone
+        || message.author != deletion.deleted_by
+        || message.author != caller
+    {
+        return error(P2PMessageError::Unauthorized(String::from(
+            "Sorry. Only the author of a message can delete it for everyone.",
+        )));
+    }
+
+    let deletion_entry = Entry::App(deletion.clone().try_into()?);
+    let deletion_hash = host_call::<CreateInput, ActionHash>(
+        __hc__create_1,
+        CreateInput::new(
+            EntryDefLocation::app(this_zome_index()?, 7),
+            EntryVisibility::Private,
+            deletion_entry,
+            ChainTopOrdering::Relaxed,
+        ),
+    )?;
+
+    let mut deletion_contents: HashMap<String, P2PMessageDeletion> = HashMap::new();
+    deletion_contents.insert(deletion_hash.to_string(), deletion);
+
+    let signal = Signal::P2PMessageRetraction(RetractionSignal {
+        deletion: deletion_contents.clone(),
+    });
+
+    let signal_details = SignalDetails {
+        name: "RECEIVE_P2P_MESSAGE_RETRACTION".to_string(),
+        payload: signal,
+    };
+
+    emit_signal(&signal_details)?;
+
+    Ok(deletion_contents)
+}
@@ -0,0 +1,34 @@
+use hdk::prelude::*;
+use std::collections::HashMap;
+
+use p2pmessage_coordinator_types::*;
+
+use crate::{
+    outbox::{deliver_message_handler, deliver_receipt_handler},
+    utils::error_reason,
+};
+
+pub fn retry_delivery_handler(
+    retry_input: RetryDeliveryInput,
+) -> ExternResult<HashMap<String, DeliveryResult>> {
+    let mut results: HashMap<String, DeliveryResult> = HashMap::new();
+
+    // one failed item does not stop the rest of the batch
+    for message_hash in retry_input.message_hashes.into_iter() {
+        let result = match deliver_message_handler(message_hash.clone()) {
+            Ok(status) => DeliveryResult::Status(status),
+            Err(e) => DeliveryResult::Error(error_reason(e)),
+        };
+        results.insert(message_hash.to_string(), result);
+    }
+
+    for receipt_hash in retry_input.receipt_hashes.into_iter() {
+        let result = match deliver_receipt_handler(receipt_hash.clone()) {
+            Ok(status) => DeliveryResult::Status(status),
+            Err(e) => DeliveryResult::Error(error_reason(e)),
+        };
+        results.insert(receipt_hash.to_string(), result);
+    }
+
+    Ok(results)
+}
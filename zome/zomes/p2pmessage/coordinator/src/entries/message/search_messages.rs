@@ -0,0 +1,588 @@
+use hdk::prelude::*;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use p2pmessage_coordinator_types::*;
+use p2pmessage_integrity_types::*;
+
+use crate::{
+    chain_index::{matches_payload_filter, message_row, query_by_hashes},
+    encryption::decrypt_payload,
+    helpers::{get_reactions, get_receipts, get_replies, insert_message, insert_reply},
+    utils::error,
+};
+
+use super::utils::this_zome_index;
+
+/*
+ * SEARCH INDEX
+ * P2PSearchIndex entries cover a batch of at least SEARCH_INDEX_BATCH_SIZE messages, edits
+ * and deletions each. the words of an edit point to the message it edits, so a search
+ * only reads the matching messages and edits, the actions after the last batch are indexed
+ * on the fly. words are only indexed once decrypted and only as keyed hashes of their first
+ * SEARCH_PREFIX_LEN letters, the matches are checked against the decrypted payloads
+ */
+
+pub const SEARCH_INDEX_BATCH_SIZE: usize = 200;
+// longer query words are looked up by their first letters
+const SEARCH_PREFIX_LEN: usize = 6;
+
+pub fn search_messages_handler(
+    filter: P2PMessageFilterSearch,
+) -> ExternResult<P2PMessageHashTables> {
+    let query_tokens = tokenize(&filter.query);
+    if query_tokens.is_empty() {
+        return error(P2PMessageError::ValidationFailed(String::from(
+            "Sorry. The search query is empty.",
+        )));
+    }
+
+    let search_key = get_search_key()?;
+    let mut indexes: Vec<P2PSearchIndex> = get_search_indexes_from_chain()?
+        .into_iter()
+        .map(|(_index_hash, index)| index)
+        .collect();
+    let first_unindexed_seq = indexes
+        .iter()
+        .map(|index| index.last_seq + 1)
+        .max()
+        .unwrap_or(0);
+    let (unindexed, _indexed_count) = build_index(
+        &search_key,
+        first_unindexed_seq,
+        agent_info()?.chain_head.1,
+    )?;
+    indexes.push(unindexed);
+
+    let mut latest_edits: HashMap<EntryHash, IndexedEdit> = HashMap::new();
+    let mut deleted_messages: HashSet<EntryHash> = HashSet::new();
+    for index in indexes.iter() {
+        for edit in index.edits.iter() {
+            match latest_edits.get(&edit.message_hash) {
+                Some(latest_edit) if latest_edit.time_edited >= edit.time_edited => (),
+                _ => {
+                    latest_edits.insert(edit.message_hash.clone(), edit.clone());
+                }
+            }
+        }
+        deleted_messages.extend(index.deleted.iter().cloned());
+    }
+
+    // messages whose text or one of whose edits contains every word
+    let mut token_hashes: HashMap<String, String> = HashMap::new();
+    let mut query_hashes: Vec<String> = Vec::new();
+    for query_token in query_tokens.iter() {
+        let prefix: String = query_token.chars().take(SEARCH_PREFIX_LEN).collect();
+        query_hashes.push(hash_token(&search_key, &mut token_hashes, prefix)?);
+    }
+    let candidate_hashes: HashSet<EntryHash> = find_in_indexes(&indexes, &query_hashes)
+        .into_iter()
+        .filter(|message_hash| !deleted_messages.contains(message_hash))
+        .collect();
+    let mut candidates: Vec<(u32, Timestamp, P2PMessage)> = Vec::new();
+    for record in query_by_hashes(0, candidate_hashes.clone())?.into_iter() {
+        let seq = record.action().action_seq();
+        let time_committed = record.action().timestamp();
+        if let Ok(message_entry) = TryInto::<P2PMessage>::try_into(record) {
+            candidates.push((seq, time_committed, message_entry));
+        }
+    }
+    candidates.sort_by_key(|(_, _, message_entry)| std::cmp::Reverse(message_entry.time_sent));
+
+    // the latest content of the message is the one searched
+    let edited_payloads = get_edited_payloads(&latest_edits, &candidate_hashes)?;
+
+    let me = agent_info()?.agent_latest_pubkey;
+    let mut searched_messages: HashSet<String> = HashSet::new();
+    let mut agent_messages: HashMap<String, Vec<String>> = HashMap::new();
+    let mut message_contents: HashMap<String, (P2PMessageData, Vec<String>)> = HashMap::new();
+    let mut receipt_contents: HashMap<String, P2PMessageReceipt> = HashMap::new();
+    let mut reply_pairs: HashMap<String, Vec<String>> = HashMap::new();
+
+    for (seq, time_committed, message_entry) in candidates.into_iter() {
+        let message_hash = hash_entry(&message_entry)?;
+        if !searched_messages.insert(message_hash.clone().to_string()) {
+            continue;
+        }
+
+        let conversant = if message_entry.author == me {
+            message_entry.receiver.clone()
+        } else {
+            message_entry.author.clone()
+        };
+        if filter
+            .conversant
+            .as_ref()
+            .is_some_and(|agent| *agent != conversant)
+            || filter
+                .from
+                .is_some_and(|from| message_entry.time_sent < from)
+            || filter
+                .until
+                .is_some_and(|until| message_entry.time_sent > until)
+        {
+            continue;
+        }
+
+        let payload = match edited_payloads.get(&message_hash) {
+            Some(edited_payload) => edited_payload.clone(),
+            None => decrypt_payload(message_entry.payload.clone(), &message_entry.author)?,
+        };
+        let row = message_row(
+            seq,
+            time_committed,
+            message_hash.clone(),
+            &message_entry,
+            &payload,
+            &me,
+        );
+        if !matches_payload_filter(&row, &filter.payload_filter)
+            || !matches_tokens(&payload_tokens(&payload), &query_tokens)
+        {
+            continue;
+        }
+
+        if message_entry.reply_to.is_some() {
+            insert_reply(
+                &mut reply_pairs,
+                message_entry.clone(),
+                message_hash.clone(),
+            );
+        }
+        insert_message(
+            &mut agent_messages,
+            &mut message_contents,
+            message_entry,
+            message_hash,
+            conversant,
+        )?;
+    }
+
+    get_receipts(&mut message_contents, &mut receipt_contents)?;
+
+    get_replies(&mut reply_pairs, &mut message_contents)?;
+
+    apply_edits_and_deletions(
+        &mut message_contents,
+        &latest_edits,
+        edited_payloads,
+        &deleted_messages,
+    )?;
+
+    get_reactions(&mut message_contents)?;
+
+    Ok(P2PMessageHashTables(
+        agent_messages,
+        message_contents,
+        receipt_contents,
+    ))
+}
+
+/*
+ * commits an index entry once a full batch of messages, edits and deletions is not
+ * indexed yet, called before a zome call commits anything so the seqs can no longer move
+ */
+pub fn index_messages() -> ExternResult<()> {
+    let first_seq = get_search_indexes_from_chain()?
+        .iter()
+        .map(|(_index_hash, index)| index.last_seq + 1)
+        .max()
+        .unwrap_or(0);
+    let head_seq = agent_info()?.chain_head.1;
+    if first_seq > head_seq {
+        return Ok(());
+    }
+
+    let (index, indexed_count) = build_index(&get_search_key()?, first_seq, head_seq)?;
+    if indexed_count < SEARCH_INDEX_BATCH_SIZE {
+        return Ok(());
+    }
+
+    commit_search_index(index)?;
+
+    Ok(())
+}
+
+/*
+ * messages and edits that could not be decrypted when their batch was indexed are indexed
+ * again once a key exchange made them readable, the new index replaces the old one
+ */
+pub fn reindex_decrypted() -> ExternResult<()> {
+    let search_key = get_search_key()?;
+    let mut token_hashes: HashMap<String, String> = HashMap::new();
+
+    for (index_hash, index) in get_search_indexes_from_chain()?.into_iter() {
+        if index.encrypted.is_empty() {
+            continue;
+        }
+        let encrypted_hashes: HashSet<EntryHash> = index.encrypted.iter().cloned().collect();
+        let mut positions: HashMap<EntryHash, u32> = index
+            .messages
+            .iter()
+            .enumerate()
+            .map(|(position, message_hash)| (message_hash.clone(), position as u32))
+            .collect();
+        let mut decrypted_hashes: HashSet<EntryHash> = HashSet::new();
+        let mut reindexed = P2PSearchIndex {
+            replaced: vec![index_hash],
+            ..index
+        };
+
+        for record in query_by_hashes(0, encrypted_hashes.clone())?.into_iter() {
+            if let (Some(message_hash), Ok(message_entry)) = (
+                record.action().entry_hash().cloned(),
+                TryInto::<P2PMessage>::try_into(record),
+            ) {
+                let payload = decrypt_payload(message_entry.payload, &message_entry.author)?;
+                if let Payload::Encrypted { .. } = payload {
+                    continue;
+                }
+                let tokens = payload_token_hashes(&search_key, &mut token_hashes, &payload)?;
+                add_tokens(&mut reindexed, &mut positions, message_hash.clone(), tokens);
+                decrypted_hashes.insert(message_hash);
+            }
+        }
+
+        for record in query_by_hashes(6, encrypted_hashes)?.into_iter() {
+            if let (Some(edit_hash), Ok(edit_entry)) = (
+                record.action().entry_hash().cloned(),
+                TryInto::<P2PMessageEdit>::try_into(record),
+            ) {
+                let payload = decrypt_payload(edit_entry.payload, &edit_entry.author)?;
+                if let Payload::Encrypted { .. } = payload {
+                    continue;
+                }
+                let tokens = payload_token_hashes(&search_key, &mut token_hashes, &payload)?;
+                add_tokens(&mut reindexed, &mut positions, edit_entry.message_hash, tokens);
+                decrypted_hashes.insert(edit_hash);
+            }
+        }
+
+        if decrypted_hashes.is_empty() {
+            continue;
+        }
+        reindexed
+            .encrypted
+            .retain(|entry_hash| !decrypted_hashes.contains(entry_hash));
+        commit_search_index(reindexed)?;
+    }
+
+    Ok(())
+}
+
+// the index of the messages, edits and deletions between the two seqs, with their count
+fn build_index(
+    search_key: &[u8],
+    first_seq: u32,
+    last_seq: u32,
+) -> ExternResult<(P2PSearchIndex, usize)> {
+    let mut index = P2PSearchIndex {
+        tokens: BTreeMap::new(),
+        messages: Vec::new(),
+        edits: Vec::new(),
+        deleted: Vec::new(),
+        encrypted: Vec::new(),
+        replaced: Vec::new(),
+        first_seq,
+        last_seq,
+    };
+    let mut positions: HashMap<EntryHash, u32> = HashMap::new();
+    let mut token_hashes: HashMap<String, String> = HashMap::new();
+    let mut indexed_count = 0;
+
+    for record in query_records(0, first_seq, last_seq)?.into_iter() {
+        indexed_count += 1;
+        if let (Some(message_hash), Ok(message_entry)) = (
+            record.action().entry_hash().cloned(),
+            TryInto::<P2PMessage>::try_into(record),
+        ) {
+            let payload = decrypt_payload(message_entry.payload, &message_entry.author)?;
+            if let Payload::Encrypted { .. } = payload {
+                index.encrypted.push(message_hash);
+                continue;
+            }
+            let tokens = payload_token_hashes(search_key, &mut token_hashes, &payload)?;
+            add_tokens(&mut index, &mut positions, message_hash, tokens);
+        }
+    }
+
+    for record in query_records(6, first_seq, last_seq)?.into_iter() {
+        indexed_count += 1;
+        if let (Some(edit_hash), Ok(edit_entry)) = (
+            record.action().entry_hash().cloned(),
+            TryInto::<P2PMessageEdit>::try_into(record),
+        ) {
+            let payload = decrypt_payload(edit_entry.payload, &edit_entry.author)?;
+            if let Payload::Encrypted { .. } = payload {
+                index.encrypted.push(edit_hash.clone());
+            } else {
+                let tokens = payload_token_hashes(search_key, &mut token_hashes, &payload)?;
+                add_tokens(
+                    &mut index,
+                    &mut positions,
+                    edit_entry.message_hash.clone(),
+                    tokens,
+                );
+            }
+            index.edits.push(IndexedEdit {
+                message_hash: edit_entry.message_hash,
+                edit_hash,
+                time_edited: edit_entry.time_edited,
+            });
+        }
+    }
+
+    for record in query_records(7, first_seq, last_seq)?.into_iter() {
+        indexed_count += 1;
+        if let Ok(deletion_entry) = TryInto::<P2PMessageDeletion>::try_into(record) {
+            index.deleted.push(deletion_entry.message_hash);
+        }
+    }
+
+    Ok((index, indexed_count))
+}
+
+fn add_tokens(
+    index: &mut P2PSearchIndex,
+    positions: &mut HashMap<EntryHash, u32>,
+    message_hash: EntryHash,
+    tokens: BTreeSet<String>,
+) {
+    let position = *positions.entry(message_hash.clone()).or_insert_with(|| {
+        index.messages.push(message_hash);
+        index.messages.len() as u32 - 1
+    });
+    for token in tokens.into_iter() {
+        let token_positions = index.tokens.entry(token).or_default();
+        if !token_positions.contains(&position) {
+            token_positions.push(position);
+        }
+    }
+}
+
+fn commit_search_index(index: P2PSearchIndex) -> ExternResult<ActionHash> {
+    let index_entry = Entry::App(index.try_into()?);
+    host_call::<CreateInput, ActionHash>(
+        __hc__create_1,
+        CreateInput::new(
+            EntryDefLocation::app(this_zome_index()?, 14),
+            EntryVisibility::Private,
+            index_entry,
+            ChainTopOrdering::Relaxed,
+        ),
+    )
+}
+
+// the indexes with their entry hashes, leaving out the ones indexed again
+fn get_search_indexes_from_chain() -> ExternResult<Vec<(EntryHash, P2PSearchIndex)>> {
+    let queried_indexes: Vec<Record> = query(
+        QueryFilter::new()
+            .entry_type(EntryType::App(AppEntryDef::new(
+                EntryDefIndex::from(14),
+                this_zome_index()?,
+                EntryVisibility::Private,
+            )))
+            .include_entries(true),
+    )?;
+
+    let mut indexes: Vec<(EntryHash, P2PSearchIndex)> = Vec::new();
+    let mut replaced: HashSet<EntryHash> = HashSet::new();
+    for record in queried_indexes.into_iter() {
+        if let (Some(index_hash), Ok(index_entry)) = (
+            record.action().entry_hash().cloned(),
+            TryInto::<P2PSearchIndex>::try_into(record),
+        ) {
+            replaced.extend(index_entry.replaced.iter().cloned());
+            indexes.push((index_hash, index_entry));
+        }
+    }
+
+    Ok(indexes
+        .into_iter()
+        .filter(|(index_hash, _)| !replaced.contains(index_hash))
+        .collect())
+}
+
+// entries of the type committed between the two action seqs, both included
+fn query_records(index: u8, first_seq: u32, last_seq: u32) -> ExternResult<Vec<Record>> {
+    if first_seq > last_seq {
+        return Ok(Vec::new());
+    }
+
+    query(
+        QueryFilter::new()
+            .sequence_range(ChainQueryFilterRange::ActionSeqRange(first_seq, last_seq))
+            .entry_type(EntryType::App(AppEntryDef::new(
+                EntryDefIndex::from(index),
+                this_zome_index()?,
+                EntryVisibility::Private,
+            )))
+            .include_entries(true),
+    )
+}
+
+// the decrypted payloads of the latest edits of the given messages, fetched in one query
+fn get_edited_payloads(
+    latest_edits: &HashMap<EntryHash, IndexedEdit>,
+    message_hashes: &HashSet<EntryHash>,
+) -> ExternResult<HashMap<EntryHash, Payload>> {
+    let edit_hashes: HashSet<EntryHash> = message_hashes
+        .iter()
+        .filter_map(|message_hash| latest_edits.get(message_hash))
+        .map(|edit| edit.edit_hash.clone())
+        .collect();
+
+    let mut edited_payloads: HashMap<EntryHash, Payload> = HashMap::new();
+    for record in query_by_hashes(6, edit_hashes)?.into_iter() {
+        if let Ok(edit_entry) = TryInto::<P2PMessageEdit>::try_into(record) {
+            let payload = decrypt_payload(edit_entry.payload, &edit_entry.author)?;
+            edited_payloads.insert(edit_entry.message_hash, payload);
+        }
+    }
+
+    Ok(edited_payloads)
+}
+
+// what get_edits and get_deletions do for the other getters, from what the indexes know
+fn apply_edits_and_deletions(
+    message_contents: &mut HashMap<String, (P2PMessageData, Vec<String>)>,
+    latest_edits: &HashMap<EntryHash, IndexedEdit>,
+    mut edited_payloads: HashMap<EntryHash, Payload>,
+    deleted_messages: &HashSet<EntryHash>,
+) -> ExternResult<()> {
+    let replied_to_hashes: HashSet<EntryHash> = message_contents
+        .values()
+        .filter_map(|(message_data, _)| message_data.reply_to.as_ref())
+        .map(|replied_to_message| replied_to_message.hash.clone())
+        .filter(|message_hash| !edited_payloads.contains_key(message_hash))
+        .collect();
+    edited_payloads.extend(get_edited_payloads(latest_edits, &replied_to_hashes)?);
+    let edited_payloads: HashMap<String, Payload> = edited_payloads
+        .into_iter()
+        .map(|(message_hash, payload)| (message_hash.to_string(), payload))
+        .collect();
+
+    for (message_hash, message_bundle) in message_contents.iter_mut() {
+        if let Some(payload) = edited_payloads.get(message_hash) {
+            message_bundle.0.payload = payload.clone();
+            message_bundle.0.edited = true;
+        }
+        // replies show the latest content of the message they reply to
+        if let Some(ref mut replied_to_message) = message_bundle.0.reply_to {
+            if deleted_messages.contains(&replied_to_message.hash) {
+                replied_to_message.payload = Payload::Text {
+                    payload: String::new(),
+                };
+            } else if let Some(payload) = edited_payloads.get(&replied_to_message.hash.to_string())
+            {
+                replied_to_message.payload = payload.clone();
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// messages where the first letters of every query word start a word of the message or an edit
+fn find_in_indexes(indexes: &[P2PSearchIndex], query_hashes: &[String]) -> HashSet<EntryHash> {
+    let mut found: HashSet<EntryHash> = HashSet::new();
+    for index in indexes.iter() {
+        let mut batch_matches: Option<BTreeSet<u32>> = None;
+        for query_hash in query_hashes.iter() {
+            let token_matches: BTreeSet<u32> = index
+                .tokens
+                .get(query_hash)
+                .map(|positions| positions.iter().cloned().collect())
+                .unwrap_or_default();
+            batch_matches = Some(match batch_matches {
+                Some(matches) => matches.intersection(&token_matches).cloned().collect(),
+                None => token_matches,
+            });
+        }
+        found.extend(
+            batch_matches
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|position| index.messages.get(position as usize).cloned()),
+        );
+    }
+
+    found
+}
+
+fn matches_tokens(tokens: &[String], query_tokens: &[String]) -> bool {
+    query_tokens.iter().all(|query_token| {
+        tokens
+            .iter()
+            .any(|token| token.starts_with(query_token.as_str()))
+    })
+}
+
+// the key the words are hashed with, only this agent can sign it
+fn get_search_key() -> ExternResult<Vec<u8>> {
+    let signature = sign_raw(
+        agent_info()?.agent_latest_pubkey,
+        b"p2pmessage_search_index".to_vec(),
+    )?;
+
+    Ok(signature.0.to_vec())
+}
+
+// the hashes of the first letters of every word of the payload
+fn payload_token_hashes(
+    search_key: &[u8],
+    token_hashes: &mut HashMap<String, String>,
+    payload: &Payload,
+) -> ExternResult<BTreeSet<String>> {
+    let mut hashes: BTreeSet<String> = BTreeSet::new();
+    for token in payload_tokens(payload).into_iter() {
+        let letters: Vec<char> = token.chars().take(SEARCH_PREFIX_LEN).collect();
+        for len in 1..=letters.len() {
+            let prefix: String = letters[..len].iter().collect();
+            hashes.insert(hash_token(search_key, token_hashes, prefix)?);
+        }
+    }
+
+    Ok(hashes)
+}
+
+// keyed blake2b of the token, remembered for the rest of the call
+fn hash_token(
+    search_key: &[u8],
+    token_hashes: &mut HashMap<String, String>,
+    token: String,
+) -> ExternResult<String> {
+    if let Some(token_hash) = token_hashes.get(&token) {
+        return Ok(token_hash.clone());
+    }
+
+    let mut input = search_key.to_vec();
+    input.extend_from_slice(token.as_bytes());
+    let token_hash: String = hash_blake2b(input, 16)?
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    token_hashes.insert(token, token_hash.clone());
+
+    Ok(token_hash)
+}
+
+// texts are searched by their words and files by the words of their name
+fn payload_tokens(payload: &Payload) -> Vec<String> {
+    match payload {
+        Payload::Text { payload } => tokenize(payload),
+        Payload::File { metadata, .. } => tokenize(&metadata.file_name),
+        Payload::Encrypted { .. } => Vec::new(),
+    }
+}
+
+fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens: Vec<String> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .map(|token| token.to_lowercase())
+        .collect();
+    tokens.sort();
+    tokens.dedup();
+
+    tokens
+}
@@ -1,40 +1,69 @@
 use hdk::prelude::*;
+use std::collections::HashMap;
 
 use p2pmessage_coordinator_types::*;
 use p2pmessage_integrity_types::*;
 
-use crate::{entries::message::utils::this_zome_index, receive_receipt::receive_receipt_handler};
+use crate::{
+    chain_index::index_chain,
+    encryption::{decrypt_payload, encrypt_payload},
+    entries::message::utils::this_zome_index,
+    file_chunks::{commit_file_chunk, split_file},
+    helpers::get_message_from_chain,
+    is_user_blocked::is_user_blocked,
+    receive_receipt::receive_receipt_handler,
+    search_messages::index_messages,
+    utils::error,
+    verify_file::verify_file_input,
+};
 
 pub fn send_message_handler(
     message_input: MessageInput,
 ) -> ExternResult<(EntryHash, P2PMessageData)> {
-    // TODO: check if receiver is blocked
+    if is_user_blocked(message_input.receiver.clone())? {
+        return error(P2PMessageError::Blocked(String::from(
+            "Sorry. You have blocked this agent. Unblock them to send a message.",
+        )));
+    }
+
+    // the indexes cover the earlier calls only, this one has not committed yet
+    let _res = index_chain();
+    let _res = index_messages();
+
+    let payload = match message_input.payload {
+        PayloadInput::Text { ref payload } => Payload::Text {
+            payload: payload.to_owned(),
+        },
+        PayloadInput::File {
+            ref metadata,
+            ref file_type,
+            ref file_bytes,
+        } => {
+            verify_file_input(metadata, file_type, file_bytes)?;
+            let p2pfile = P2PFileBytes(file_bytes.clone());
+            let file_hash = hash_entry(&p2pfile)?;
+            let chunks = split_file(file_bytes)
+                .iter()
+                .map(hash_entry)
+                .collect::<ExternResult<Vec<EntryHash>>>()?;
+            Payload::File {
+                metadata: FileMetadata {
+                    file_name: metadata.file_name.clone(),
+                    file_size: metadata.file_size,
+                    file_type: metadata.file_type.clone(),
+                    file_hash,
+                    chunks,
+                },
+                file_type: file_type.clone(),
+            }
+        }
+    };
 
+    // encrypted when the conversants have exchanged keys, see encryption.rs
     let message = P2PMessage {
         author: agent_info()?.agent_latest_pubkey,
-        receiver: message_input.receiver,
-        payload: match message_input.payload {
-            PayloadInput::Text { ref payload } => Payload::Text {
-                payload: payload.to_owned(),
-            },
-            PayloadInput::File {
-                ref metadata,
-                ref file_type,
-                ref file_bytes,
-            } => {
-                let p2pfile = P2PFileBytes(file_bytes.clone());
-                let file_hash = hash_entry(&p2pfile)?;
-                Payload::File {
-                    metadata: FileMetadata {
-                        file_name: metadata.file_name.clone(),
-                        file_size: metadata.file_size.clone(),
-                        file_type: metadata.file_type.clone(),
-                        file_hash: file_hash,
-                    },
-                    file_type: file_type.clone(),
-                }
-            }
-        },
+        receiver: message_input.receiver.clone(),
+        payload: encrypt_payload(payload.clone(), message_input.receiver)?,
         time_sent: sys_time()?,
         reply_to: message_input.reply_to,
     };
@@ -51,19 +80,11 @@ pub fn send_message_handler(
     )?;
     debug!("create_entry message");
 
+    // a file that fits in one chunk is stored whole
     if let PayloadInput::File { ref file_bytes, .. } = message_input.payload {
-        let p2pfile = P2PFileBytes(file_bytes.clone());
-        let p2pfile_entry = Entry::App(p2pfile.clone().try_into()?);
-        host_call::<CreateInput, ActionHash>(
-            __hc__create_1,
-            CreateInput::new(
-                EntryDefLocation::app(this_zome_index()?, 3),
-                EntryVisibility::Private,
-                p2pfile_entry,
-                ChainTopOrdering::Relaxed,
-            ),
-        )?;
-        ()
+        for chunk in split_file(file_bytes).into_iter() {
+            commit_file_chunk(chunk)?;
+        }
     };
 
     // message self
@@ -80,51 +101,39 @@ pub fn send_message_handler(
 
     let message_return;
     if let Some(ref reply_to_hash) = message.reply_to {
-        let queried_messages: Vec<Record> = query(
-            QueryFilter::new()
-                .entry_type(EntryType::App(AppEntryDef::new(
-                    EntryDefIndex::from(0),
-                    this_zome_index()?,
-                    EntryVisibility::Private,
-                )))
-                .include_entries(true),
-        )?;
+        if let Ok(message_entry) = get_message_from_chain(reply_to_hash.clone()) {
+            let replied_to_message = P2PMessageReplyTo {
+                hash: reply_to_hash.clone(),
+                author: message_entry.author.clone(),
+                receiver: message_entry.receiver,
+                payload: decrypt_payload(message_entry.payload, &message_entry.author)?,
+                time_sent: message_entry.time_sent,
+                reply_to: None,
+            };
 
-        for queried_message in queried_messages.clone().into_iter() {
-            if let Ok(message_entry) = TryInto::<P2PMessage>::try_into(queried_message.clone()) {
-                let message_hash = hash_entry(&message_entry)?;
+            message_return = P2PMessageData {
+                author: message.author.clone(),
+                receiver: message.receiver.clone(),
+                payload: payload.clone(),
+                time_sent: message.time_sent,
+                reply_to: Some(replied_to_message),
+                edited: false,
+                deleted: false,
+                reactions: HashMap::new(),
+            };
 
-                if *reply_to_hash == message_hash {
-                    let replied_to_message = P2PMessageReplyTo {
-                        hash: message_hash.clone(),
-                        author: message_entry.author,
-                        receiver: message_entry.receiver,
-                        payload: message_entry.payload,
-                        time_sent: message_entry.time_sent,
-                        reply_to: None,
-                    };
-
-                    message_return = P2PMessageData {
-                        author: message.author.clone(),
-                        receiver: message.receiver.clone(),
-                        payload: message.payload.clone(),
-                        time_sent: message.time_sent.clone(),
-                        reply_to: Some(replied_to_message),
-                    };
-
-                    return Ok((hash_entry(&message)?, message_return));
-                }
-            } else {
-                continue;
-            }
+            return Ok((hash_entry(&message)?, message_return));
         }
     }
     message_return = P2PMessageData {
         author: message.author.clone(),
         receiver: message.receiver.clone(),
-        payload: message.payload.clone(),
+        payload: payload.clone(),
         time_sent: message.time_sent.clone(),
         reply_to: None,
+        edited: false,
+        deleted: false,
+        reactions: HashMap::new(),
     };
 
     Ok((hash_entry(&message)?, message_return))
@@ -1,9 +1,13 @@
 use hdk::prelude::*;
+use std::collections::HashMap;
 
 use p2pmessage_coordinator_types::*;
 use p2pmessage_integrity_types::*;
 
-use crate::utils::error;
+use crate::{
+    helpers::get_message_from_chain,
+    utils::{call_agent, decode_error, error, zome_call_error},
+};
 
 use super::utils::this_zome_index;
 
@@ -30,7 +34,8 @@ pub fn send_message_with_timestamp_handler(
                         file_name: metadata.file_name.clone(),
                         file_size: metadata.file_size.clone(),
                         file_type: metadata.file_type.clone(),
-                        file_hash: file_hash,
+                        file_hash: file_hash.clone(),
+                        chunks: vec![file_hash],
                     },
                     file_type: file_type.clone(),
                 }
@@ -48,15 +53,11 @@ pub fn send_message_with_timestamp_handler(
     let receive_input = ReceiveMessageInput {
         message: message.clone(),
         file: file.clone(),
+        encrypted_file: None,
     };
 
-    let receive_call_result: ZomeCallResponse = call_remote(
-        message.receiver.clone(),
-        zome_info()?.name,
-        "receive_message".into(),
-        None,
-        &receive_input,
-    )?;
+    let receive_call_result: ZomeCallResponse =
+        call_agent(message.receiver.clone(), "receive_message", &receive_input)?;
 
     match receive_call_result {
         ZomeCallResponse::Ok(extern_io) => {
@@ -103,49 +104,31 @@ pub fn send_message_with_timestamp_handler(
 
                     let message_return;
                     if let Some(ref reply_to_hash) = message.reply_to {
-                        let mut queried_messages: Vec<Record> = query(
-                            QueryFilter::new()
-                                .entry_type(EntryType::App(AppEntryDef::new(
-                                    EntryDefIndex::from(0),
-                                    this_zome_index()?,
-                                    EntryVisibility::Private,
-                                )))
-                                .include_entries(true),
-                        )?;
-                        queried_messages.reverse();
-
-                        for queried_message in queried_messages.clone().into_iter() {
-                            if let Ok(message_entry) =
-                                TryInto::<P2PMessage>::try_into(queried_message.clone())
-                            {
-                                let message_hash = hash_entry(&message_entry)?;
-
-                                if *reply_to_hash == message_hash {
-                                    let replied_to_message = P2PMessageReplyTo {
-                                        hash: message_hash.clone(),
-                                        author: message_entry.author,
-                                        receiver: message_entry.receiver,
-                                        payload: message_entry.payload,
-                                        time_sent: message_entry.time_sent,
-                                        reply_to: None,
-                                    };
-
-                                    message_return = P2PMessageData {
-                                        author: message.author.clone(),
-                                        receiver: message.receiver.clone(),
-                                        payload: message.payload.clone(),
-                                        time_sent: message.time_sent.clone(),
-                                        reply_to: Some(replied_to_message),
-                                    };
-
-                                    return Ok((
-                                        (hash_entry(&message)?, message_return),
-                                        (hash_entry(&received_receipt)?, received_receipt),
-                                    ));
-                                }
-                            } else {
-                                continue;
-                            }
+                        if let Ok(message_entry) = get_message_from_chain(reply_to_hash.clone()) {
+                            let replied_to_message = P2PMessageReplyTo {
+                                hash: reply_to_hash.clone(),
+                                author: message_entry.author,
+                                receiver: message_entry.receiver,
+                                payload: message_entry.payload,
+                                time_sent: message_entry.time_sent,
+                                reply_to: None,
+                            };
+
+                            message_return = P2PMessageData {
+                                author: message.author.clone(),
+                                receiver: message.receiver.clone(),
+                                payload: message.payload.clone(),
+                                time_sent: message.time_sent,
+                                reply_to: Some(replied_to_message),
+                                edited: false,
+                                deleted: false,
+                                reactions: HashMap::new(),
+                            };
+
+                            return Ok((
+                                (hash_entry(&message)?, message_return),
+                                (hash_entry(&received_receipt)?, received_receipt),
+                            ));
                         }
                     }
 
@@ -155,6 +138,9 @@ pub fn send_message_with_timestamp_handler(
                         payload: message.payload.clone(),
                         time_sent: message.time_sent.clone(),
                         reply_to: None,
+                        edited: false,
+                        deleted: false,
+                        reactions: HashMap::new(),
                     };
 
                     Ok((
@@ -162,17 +148,12 @@ pub fn send_message_with_timestamp_handler(
                         (hash_entry(&received_receipt)?, received_receipt),
                     ))
                 }
-                Err(e) => return Err(wasm_error!(WasmErrorInner::Guest(String::from(e)))),
+                Err(e) => decode_error(e),
             }
         }
-        ZomeCallResponse::Unauthorized(..) => {
-            return error("Sorry, something went wrong. [Authorization error]");
-        }
         ZomeCallResponse::NetworkError(e) => {
-            return error(&e);
-        }
-        ZomeCallResponse::CountersigningSession(_e) => {
-            return error("Sorry, something went wrong. [Countersigning error]");
+            error(P2PMessageError::ReceiverOffline(e))
         }
+        failed => zome_call_error(failed),
     }
 }
@@ -4,9 +4,32 @@ use std::collections::HashMap;
 use p2pmessage_coordinator_types::*;
 use p2pmessage_integrity_types::*;
 
+use crate::{
+    is_user_blocked::is_user_blocked, pin_message::check_pin, rate_limits::check_pin_rate,
+    utils::error,
+};
+
 use super::utils::this_zome_index;
 
 pub fn sync_pins_handler(pin: P2PMessagePin) -> ExternResult<HashMap<String, P2PMessagePin>> {
+    let caller = call_info()?.provenance;
+
+    // pins from blocked agents are silently dropped
+    if is_user_blocked(caller.clone())? {
+        return Ok(HashMap::new());
+    }
+
+    if !pin.conversants.contains(&caller)
+        || !pin.conversants.contains(&agent_info()?.agent_latest_pubkey)
+    {
+        return error(P2PMessageError::Unauthorized(String::from(
+            "Sorry. Pins can only be synced by an agent of the conversation.",
+        )));
+    }
+
+    check_pin_rate(&caller)?;
+    check_pin(&caller, &pin)?;
+
     let pin_entry = Entry::App(pin.clone().try_into()?);
     let pin_hash = host_call::<CreateInput, ActionHash>(
         __hc__create_1,
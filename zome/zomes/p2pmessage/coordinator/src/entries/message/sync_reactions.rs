@@ -0,0 +1,78 @@
+use hdk::prelude::*;
+use std::collections::HashMap;
+
+use p2pmessage_coordinator_types::*;
+use p2pmessage_integrity_types::*;
+
+use crate::{
+    helpers::get_message_from_chain, is_user_blocked::is_user_blocked,
+    rate_limits::check_reaction_rate, react_to_message::has_reacted, utils::error,
+};
+
+use super::utils::this_zome_index;
+
+pub fn sync_reactions_handler(
+    reaction: P2PMessageReaction,
+) -> ExternResult<HashMap<String, P2PMessageReaction>> {
+    let caller = call_info()?.provenance;
+
+    // reactions from blocked agents are silently dropped
+    if is_user_blocked(caller.clone())? {
+        return Ok(HashMap::new());
+    }
+
+    if reaction.reactor != caller {
+        return error(P2PMessageError::Unauthorized(String::from(
+            "Sorry. Reactions can only be synced by the agent who reacted.",
+        )));
+    }
+
+    // only messages of the conversation with the caller can be reacted to
+    let message = get_message_from_chain(reaction.message_hash.clone())?;
+    if message.author != caller && message.receiver != caller {
+        return error(P2PMessageError::Unauthorized(String::from(
+            "Sorry. Reactions can only be synced for messages of the conversation.",
+        )));
+    }
+    check_reaction_rate(&caller)?;
+
+    // one reaction per agent and emoji
+    let reacted = has_reacted(
+        &reaction.message_hash,
+        &message,
+        &reaction.emoji,
+        &reaction.reactor,
+    )?;
+    match reaction.status {
+        ReactionStatus::Reacted { .. } if reacted => return Ok(HashMap::new()),
+        ReactionStatus::Removed { .. } if !reacted => return Ok(HashMap::new()),
+        _ => (),
+    }
+
+    let reaction_entry = Entry::App(reaction.clone().try_into()?);
+    let reaction_hash = host_call::<CreateInput, ActionHash>(
+        __hc__create_1,
+        CreateInput::new(
+            EntryDefLocation::app(this_zome_index()?, 8),
+            EntryVisibility::Private,
+            reaction_entry,
+            ChainTopOrdering::Relaxed,
+        ),
+    )?;
+
+    let mut reaction_contents: HashMap<String, P2PMessageReaction> = HashMap::new();
+    reaction_contents.insert(reaction_hash.to_string(), reaction);
+
+    let signal = Signal::P2PReactionSignal(ReactionSignal {
+        reaction: reaction_contents.clone(),
+    });
+
+    let signal_details = SignalDetails {
+        name: "SYNC_P2P_REACTIONS".to_string(),
+        payload: signal,
+    };
+
+    emit_signal(&signal_details)?;
+
+    Ok(reaction_contents)
+}
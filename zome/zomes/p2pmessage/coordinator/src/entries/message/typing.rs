@@ -1,11 +1,63 @@
 use hdk::prelude::*;
+use std::collections::{HashMap, HashSet};
 
 use p2pmessage_coordinator_types::*;
+use p2pmessage_integrity_types::*;
+
+use crate::{
+    chain_index::{query_by_hashes, query_since},
+    is_user_blocked::is_user_blocked,
+    utils::decode_error,
+};
+
+use super::utils::this_zome_index;
+
+/*
+ * TYPING INDICATORS
+ * a typing signal holds for its ttl unless it is renewed. starting, stopping and renewing
+ * are remote signals, each one sent or received updates the single P2PTypingState the
+ * typist and the receiver keep for the pair. the throttle is read from that state, so at
+ * most two states per throttle are committed for a conversant however often they signal
+ */
+
+// milliseconds
+pub const TYPING_TTL: u32 = 10000;
+// a start or a renewal is sent at most once per this after the last state
+pub const TYPING_THROTTLE: u32 = 5000;
+// signals can arrive closer together than they were sent
+const RECEIVE_THROTTLE: u32 = TYPING_THROTTLE / 2;
+// a typist cannot keep the indicator on longer than this without sending again
+const MAX_TYPING_TTL: u32 = 30000;
 
 pub fn typing_handler(typing_info: P2PTypingDetailIO) -> ExternResult<()> {
+    // blocked agents do not get typing indicators
+    if is_user_blocked(typing_info.agent.clone())? {
+        return Ok(());
+    }
+
+    let me = agent_info()?.agent_latest_pubkey;
+    let now = sys_time()?;
+
+    let last_state = get_recent_state(&me, &typing_info.agent, now)?;
+    if !accepts_state(&last_state, typing_info.is_typing, now, TYPING_THROTTLE) {
+        return Ok(());
+    }
+    record_typing_state(
+        last_state,
+        P2PTypingState {
+            typist: me.clone(),
+            receiver: typing_info.agent.clone(),
+            is_typing: typing_info.is_typing,
+            timestamp: now,
+            expires_at: Timestamp::from_micros(now.as_micros() + millis(TYPING_TTL)),
+        },
+    )?;
+
     let signal = Signal::P2PTypingDetailSignal(TypingSignal {
-        agent: agent_info()?.agent_latest_pubkey,
+        agent: me,
         is_typing: typing_info.is_typing,
+        timestamp: now,
+        ttl: TYPING_TTL,
     });
 
     let signal_details = SignalDetails {
@@ -13,16 +65,210 @@ pub fn typing_handler(typing_info: P2PTypingDetailIO) -> ExternResult<()> {
         payload: signal,
     };
 
-    let mut agents: Vec<AgentPubKey> = Vec::new();
-
-    agents.push(typing_info.agent);
+    let agents: Vec<AgentPubKey> = vec![typing_info.agent];
 
+    // an offline receiver just does not see the indicator
     let signal_details_result: Result<ExternIO, SerializedBytesError> = ExternIO::encode(signal_details);
     match signal_details_result {
         Ok(signal_details) => {
             remote_signal(signal_details, agents)?;
-            return Ok(())
+            Ok(())
+        }
+        Err(e) => decode_error(e),
+    }
+}
+
+/*
+ * the indicator runs on our clock, the timestamp of the typist is not trusted.
+ * called from recv_remote_signal, signals over the throttle are dropped uncommitted
+ */
+pub fn receive_typing_handler(typing: TypingSignal) -> ExternResult<()> {
+    let me = agent_info()?.agent_latest_pubkey;
+    let now = sys_time()?;
+
+    let last_state = get_recent_state(&typing.agent, &me, now)?;
+    if !accepts_state(&last_state, typing.is_typing, now, RECEIVE_THROTTLE) {
+        return Ok(());
+    }
+
+    let ttl = typing.ttl.min(MAX_TYPING_TTL);
+    record_typing_state(
+        last_state,
+        P2PTypingState {
+            typist: typing.agent.clone(),
+            receiver: me,
+            is_typing: typing.is_typing,
+            timestamp: now,
+            expires_at: Timestamp::from_micros(now.as_micros() + millis(ttl)),
+        },
+    )?;
+
+    emit_typing_signal(TypingSignal {
+        agent: typing.agent,
+        is_typing: typing.is_typing,
+        timestamp: now,
+        ttl,
+    })
+}
+
+// the conversants that typed to us, is_typing is false once they stopped or the indicator expired
+pub fn get_typing_states_handler() -> ExternResult<Vec<TypingState>> {
+    let me = agent_info()?.agent_latest_pubkey;
+    let now = sys_time()?;
+
+    let mut typing_states: HashMap<AgentPubKey, TypingState> = HashMap::new();
+    for state in get_typing_states_from_chain()?.into_iter() {
+        if state.receiver != me || state.typist == me {
+            continue;
+        }
+
+        let last_seen_typing = if state.is_typing {
+            Some(state.timestamp)
+        } else {
+            typing_states
+                .get(&state.typist)
+                .and_then(|previous| previous.last_seen_typing)
+        };
+        typing_states.insert(
+            state.typist.clone(),
+            TypingState {
+                agent: state.typist,
+                is_typing: state.is_typing && state.expires_at > now,
+                last_seen_typing,
+                expires_at: state.expires_at,
+            },
+        );
+    }
+
+    Ok(typing_states.into_values().collect())
+}
+
+// a stop after a start always goes through, a second stop or anything within the throttle does not
+fn accepts_state(
+    last_state: &Option<(ActionHash, P2PTypingState)>,
+    is_typing: bool,
+    now: Timestamp,
+    throttle: u32,
+) -> bool {
+    match last_state {
+        Some((_address, state)) if state.is_typing && !is_typing => true,
+        Some((_address, state)) if !state.is_typing && !is_typing => false,
+        Some((_address, state)) => {
+            now.as_micros() - state.timestamp.as_micros() >= millis(throttle)
+        }
+        // without a recent state the indicator is off already
+        None => is_typing,
+    }
+}
+
+fn emit_typing_signal(typing: TypingSignal) -> ExternResult<()> {
+    let signal_details = SignalDetails {
+        name: "TYPING_P2P".to_string(),
+        payload: Signal::P2PTypingDetailSignal(typing),
+    };
+    emit_signal(&signal_details)?;
+
+    Ok(())
+}
+
+/*
+ * the latest state of the pair and the action its updates point to. only the states
+ * committed within MAX_TYPING_TTL are read, an older indicator is off whatever it said
+ */
+fn get_recent_state(
+    typist: &AgentPubKey,
+    receiver: &AgentPubKey,
+    now: Timestamp,
+) -> ExternResult<Option<(ActionHash, P2PTypingState)>> {
+    let since = Timestamp::from_micros(now.as_micros() - millis(MAX_TYPING_TTL));
+
+    let mut recent_state: Option<(ActionHash, P2PTypingState)> = None;
+    for record in query_since(21, since)?.into_iter() {
+        let original_address = match record.action() {
+            Action::Update(update) => update.original_action_address.clone(),
+            _ => record.action_address().clone(),
+        };
+        if let Ok(state_entry) = TryInto::<P2PTypingState>::try_into(record) {
+            if state_entry.typist == *typist && state_entry.receiver == *receiver {
+                recent_state = Some((original_address, state_entry));
+            }
+        }
+    }
+
+    Ok(recent_state)
+}
+
+fn get_typing_states_from_chain() -> ExternResult<Vec<P2PTypingState>> {
+    let queried_states: Vec<Record> = query(
+        QueryFilter::new()
+            .entry_type(EntryType::App(AppEntryDef::new(
+                EntryDefIndex::from(21),
+                this_zome_index()?,
+                EntryVisibility::Private,
+            )))
+            .include_entries(true),
+    )?;
+
+    let mut typing_states: Vec<P2PTypingState> = Vec::new();
+    for record in queried_states.into_iter() {
+        if let Ok(state_entry) = TryInto::<P2PTypingState>::try_into(record) {
+            typing_states.push(state_entry);
         }
-        Err(e) => return Err(wasm_error!(WasmErrorInner::Guest(String::from(e))))
     }
+
+    Ok(typing_states)
+}
+
+// every state of the pair updates the same entry, see get_original_state for the first one
+fn record_typing_state(
+    last_state: Option<(ActionHash, P2PTypingState)>,
+    state: P2PTypingState,
+) -> ExternResult<ActionHash> {
+    let original_address = match last_state {
+        Some((original_address, _state)) => original_address,
+        None => get_original_state(&state.typist, &state.receiver)?,
+    };
+
+    update(UpdateInput {
+        original_action_address: original_address,
+        entry: Entry::App(state.try_into()?),
+        chain_top_ordering: ChainTopOrdering::Relaxed,
+    })
+}
+
+/*
+ * the entry the states of a pair update is a blank state committed the first time. its
+ * hash only depends on the pair, so it is found by hash once the recent states are gone
+ */
+fn get_original_state(typist: &AgentPubKey, receiver: &AgentPubKey) -> ExternResult<ActionHash> {
+    let blank_state = P2PTypingState {
+        typist: typist.clone(),
+        receiver: receiver.clone(),
+        is_typing: false,
+        timestamp: Timestamp::from_micros(0),
+        expires_at: Timestamp::from_micros(0),
+    };
+
+    let blank_hash = HashSet::from([hash_entry(&blank_state)?]);
+    match query_by_hashes(21, blank_hash)?.into_iter().next() {
+        Some(record) => Ok(record.action_address().clone()),
+        None => commit_typing_state(blank_state),
+    }
+}
+
+fn commit_typing_state(state: P2PTypingState) -> ExternResult<ActionHash> {
+    let state_entry = Entry::App(state.try_into()?);
+    host_call::<CreateInput, ActionHash>(
+        __hc__create_1,
+        CreateInput::new(
+            EntryDefLocation::app(this_zome_index()?, 21),
+            EntryVisibility::Private,
+            state_entry,
+            ChainTopOrdering::Relaxed,
+        ),
+    )
+}
+
+fn millis(milliseconds: u32) -> i64 {
+    milliseconds as i64 * 1000
 }
@@ -0,0 +1,18 @@
+use hdk::prelude::*;
+
+use p2pmessage_integrity_types::*;
+
+use crate::is_user_blocked::{commit_block_status, get_blocked_agents_from_chain, is_user_blocked};
+
+pub fn unblock_agent_handler(agent: AgentPubKey) -> ExternResult<Vec<AgentPubKey>> {
+    if is_user_blocked(agent.clone())? {
+        commit_block_status(
+            agent,
+            BlockStatus::Unblocked {
+                timestamp: sys_time()?,
+            },
+        )?;
+    }
+
+    get_blocked_agents_from_chain()
+}
@@ -1,17 +1,96 @@
 use hdk::prelude::*;
 
-pub fn error<T>(reason: &str) -> ExternResult<T> {
-    Err(wasm_error!(WasmErrorInner::Guest(String::from(reason))))
+use p2pmessage_coordinator_types::*;
+
+use super::cap_grants::cap_secret_for;
+
+pub fn error<T>(error: P2PMessageError) -> ExternResult<T> {
+    Err(wasm_error!(WasmErrorInner::Guest(error.to_string())))
+}
+
+pub fn zome_call_error<T>(zome_call_response: ZomeCallResponse) -> ExternResult<T> {
+    error(zome_call_failure(zome_call_response))
+}
+
+// the variants of a remote call that did not go through
+pub fn zome_call_failure(zome_call_response: ZomeCallResponse) -> P2PMessageError {
+    match zome_call_response {
+        ZomeCallResponse::Ok(_) => P2PMessageError::DecodeFailure(String::from(
+            "Sorry, something went wrong. [Decode error]",
+        )),
+        ZomeCallResponse::Unauthorized(..) => P2PMessageError::Unauthorized(String::from(
+            "Sorry, something went wrong. [Authorization error]",
+        )),
+        ZomeCallResponse::NetworkError(_e) => P2PMessageError::ReceiverOffline(String::from(
+            "Sorry, something went wrong. [Network error]",
+        )),
+        ZomeCallResponse::CountersigningSession(_e) => P2PMessageError::Countersigning(
+            String::from("Sorry, something went wrong. [Countersigning error]"),
+        ),
+    }
+}
+
+// the message of a failed call, structured errors keep their code
+pub fn error_reason(e: WasmError) -> String {
+    match e.error {
+        WasmErrorInner::Guest(reason) => reason,
+        other => format!("{:?}", other),
+    }
 }
 
-pub fn _err<T>(code: &str, message: &str) -> ExternResult<T> {
-    Err(wasm_error!(WasmErrorInner::Guest(format!(
-        "{{\"code\": \"{}\", \"message\": \"{}\"}}",
-        code, message
-    ))))
+pub fn decode_error<T>(e: SerializedBytesError) -> ExternResult<T> {
+    error(P2PMessageError::DecodeFailure(String::from(e)))
 }
 
 pub fn this_zome_index() -> ExternResult<ZomeIndex> {
     let zome_info = zome_info()?;
     Ok(zome_info.id)
 }
+
+pub fn call_self<I>(fn_name: &str, payload: I) -> ExternResult<ZomeCallResponse>
+where
+    I: Serialize + std::fmt::Debug,
+{
+    call(
+        CallTargetCell::Local,
+        zome_info()?.name,
+        fn_name.into(),
+        None,
+        payload,
+    )
+}
+
+// calls the zome of another agent with the cap secret the grant policy asks for
+pub fn call_agent<I>(
+    agent: AgentPubKey,
+    fn_name: &str,
+    payload: I,
+) -> ExternResult<ZomeCallResponse>
+where
+    I: Clone + Serialize + std::fmt::Debug,
+{
+    let cap_secret = cap_secret_for(&agent, false)?;
+    let zome_call_response = call_remote(
+        agent.clone(),
+        zome_info()?.name,
+        fn_name.into(),
+        cap_secret,
+        payload.clone(),
+    )?;
+
+    // a stored claim goes stale once its grant is revoked and issued again
+    if let ZomeCallResponse::Unauthorized(..) = zome_call_response {
+        let fresh_secret = cap_secret_for(&agent, true)?;
+        if fresh_secret.is_some() && fresh_secret != cap_secret {
+            return call_remote(
+                agent,
+                zome_info()?.name,
+                fn_name.into(),
+                fresh_secret,
+                payload,
+            );
+        }
+    }
+
+    Ok(zome_call_response)
+}
@@ -0,0 +1,101 @@
+use hdk::prelude::*;
+
+use p2pmessage_coordinator_types::*;
+use p2pmessage_integrity_types::*;
+
+use crate::{file_chunks::chunk_count, utils::error};
+
+/*
+ * FILE INTEGRITY
+ * the frontend metadata and the bytes received from the sender are both checked
+ * against the bytes themselves before anything is committed
+ */
+
+pub fn verify_file_input(
+    metadata: &FileMetadataInput,
+    file_type: &FileType,
+    file_bytes: &SerializedBytes,
+) -> ExternResult<()> {
+    if metadata.file_size > get_properties()?.max_file_size {
+        return error(P2PMessageError::FileTooLarge(String::from(
+            "Sorry. The file is larger than the maximum file size.",
+        )));
+    }
+
+    if file_bytes.bytes().len() != metadata.file_size {
+        return error(P2PMessageError::InvalidFile(String::from(
+            "Sorry. The file size does not match the file.",
+        )));
+    }
+
+    let file_type_matches = match file_type {
+        FileType::Image { .. } => metadata.file_type.starts_with("image/"),
+        FileType::Video { .. } => metadata.file_type.starts_with("video/"),
+        FileType::Other => true,
+    };
+    if !file_type_matches {
+        return error(P2PMessageError::InvalidFile(String::from(
+            "Sorry. The file type does not match the kind of file.",
+        )));
+    }
+
+    Ok(())
+}
+
+// chunked files are checked once all of their chunks arrived, see file_chunks.rs
+pub fn verify_received_file(
+    message: &P2PMessage,
+    metadata: &FileMetadata,
+    file: Option<&P2PFileBytes>,
+) -> ExternResult<()> {
+    match received_file_error(metadata, file)? {
+        Some(reason) => {
+            let signal = Signal::ErrorMessage(ErrorMessage {
+                message: message.clone(),
+                reason: reason.to_string(),
+            });
+
+            let signal_details = SignalDetails {
+                name: "ERROR_P2P_MESSAGE".to_string(),
+                payload: signal,
+            };
+            emit_signal(&signal_details)?;
+
+            error(P2PMessageError::InvalidFile(reason.to_string()))
+        }
+        None => Ok(()),
+    }
+}
+
+// why the file received with a message does not match its metadata, if it does not
+pub fn received_file_error(
+    metadata: &FileMetadata,
+    file: Option<&P2PFileBytes>,
+) -> ExternResult<Option<&'static str>> {
+    // the chunks that follow the message are bounded by the size in the metadata
+    if metadata.file_size > get_properties()?.max_file_size {
+        return Ok(Some("Sorry. The file is larger than the maximum file size."));
+    }
+    if metadata.chunks.len() != chunk_count(metadata.file_size) {
+        return Ok(Some(
+            "Sorry. The number of chunks does not match the file size.",
+        ));
+    }
+
+    if metadata.chunks.len() > 1 && file.is_none() {
+        return Ok(None);
+    }
+
+    let reason = match file {
+        None => Some("Sorry. The file of the message is missing."),
+        Some(file) if hash_entry(file)? != metadata.file_hash => {
+            Some("Sorry. The file does not match the hash in its metadata.")
+        }
+        Some(file) if file.0.bytes().len() != metadata.file_size => {
+            Some("Sorry. The file does not match the size in its metadata.")
+        }
+        Some(_) => None,
+    };
+
+    Ok(reason)
+}
@@ -6,25 +6,70 @@ use std::collections::HashMap;
 
 mod entries;
 
+use entries::group::add_group_member::add_group_member_handler;
+use entries::group::create_group::create_group_handler;
+use entries::group::get_group_messages::{
+    get_latest_group_messages_handler, get_previous_group_messages_handler,
+};
+use entries::group::get_groups::get_groups_handler;
+use entries::group::helpers::get_group_data;
+use entries::group::leave_group::leave_group_handler;
+use entries::group::read_group_messages::read_group_messages_handler;
+use entries::group::receive_group_membership::receive_group_membership_handler;
+use entries::group::receive_group_message::receive_group_message_handler;
+use entries::group::receive_group_receipt::receive_group_receipt_handler;
+use entries::group::remove_group_member::remove_group_member_handler;
+use entries::group::send_group_message::send_group_message_handler;
+use entries::message::block_agent::block_agent_handler;
+use entries::message::cap_grants::{
+    issue_grant_handler, request_cap_claim_handler, revoke_grant_handler,
+};
 use entries::message::commit_message_to_receiver_chain::commit_message_to_receiver_chain_handler;
-use entries::message::commit_receipt_to_sender_chain::commit_receipt_to_sender_chain_handler;
+use entries::message::delete_message::delete_message_handler;
+use entries::message::edit_message::edit_message_handler;
+use entries::message::encryption::{
+    enable_encryption_handler, exchange_keys_handler, is_conversation_encrypted,
+};
+use entries::message::file_chunks::{get_missing_file_chunks_handler, receive_file_chunk_handler};
 use entries::message::get_adjacent_messages::get_adjacent_messages_handler;
-use entries::message::get_file_bytes::get_file_bytes_handler;
+use entries::message::get_conversation_summaries::get_conversation_summaries_handler;
+use entries::message::get_file_bytes::{get_file_bytes_handler, get_file_chunk_handler};
 use entries::message::get_latest_messages::get_latest_messages_handler;
 use entries::message::get_messages_by_agent_by_timestamp::get_messages_by_agent_by_timestamp_handler;
 use entries::message::get_next_messages::get_next_messages_handler;
 use entries::message::get_pinned_messages::get_pinned_messages_handler;
 use entries::message::get_previous_messages::get_previous_messages_handler;
-use entries::message::helpers::get_message_from_chain;
+use entries::message::get_undelivered::get_undelivered_handler;
+use entries::message::helpers::get_edits_from_chain;
 use entries::message::init::init_handler;
+use entries::message::is_user_blocked::{get_blocked_agents_from_chain, is_user_blocked};
+use entries::message::mark_conversation_read::mark_conversation_read_handler;
+use entries::message::message_requests::{
+    accept_message_request_handler, decline_message_request_handler, list_message_requests_handler,
+};
+use entries::message::outbox::{
+    deliver_change_handler, deliver_message_handler, deliver_receipt_handler, flush_outbox_handler,
+};
 use entries::message::pin_message::pin_message_handler;
+use entries::message::post_commit::post_commit_handler;
+use entries::message::rate_limits::{
+    get_rate_limits_handler, get_spam_reports_handler, record_spam_report_handler,
+};
+use entries::message::react_to_message::{react_to_message_handler, remove_reaction_handler};
 use entries::message::read_message::read_message_handler;
+use entries::message::receive_edit::receive_edit_handler;
 use entries::message::receive_message::receive_message_handler;
 use entries::message::receive_receipt::receive_receipt_handler;
+use entries::message::retract_message::retract_message_handler;
+use entries::message::retry_delivery::retry_delivery_handler;
+use entries::message::search_messages::search_messages_handler;
 use entries::message::send_message::send_message_handler;
 use entries::message::send_message_with_timestamp::send_message_with_timestamp_handler;
 use entries::message::sync_pins::sync_pins_handler;
-use entries::message::typing::typing_handler;
+use entries::message::sync_reactions::sync_reactions_handler;
+use entries::message::typing::{get_typing_states_handler, receive_typing_handler, typing_handler};
+use entries::message::unblock_agent::unblock_agent_handler;
+use entries::message::utils::decode_error;
 use entries::message::*;
 
 #[hdk_extern]
@@ -32,10 +77,20 @@ fn recv_remote_signal(signal: ExternIO) -> ExternResult<()> {
     let signal_detail_result: Result<SignalDetails, SerializedBytesError> = signal.decode();
     match signal_detail_result {
         Ok(signal_detail) => {
-            emit_signal(&signal_detail)?;
-            return Ok(());
+            let caller = call_info()?.provenance;
+            if is_user_blocked(caller.clone())? {
+                return Ok(());
+            }
+            // only typing indicators travel as remote signals, and only for the caller
+            let typing = match signal_detail.payload {
+                Signal::P2PTypingDetailSignal(typing) if typing.agent == caller => typing,
+                _ => return Ok(()),
+            };
+            // the sender is online, push anything still queued for them
+            let _res = flush_outbox_handler(Some(caller.clone()));
+            receive_typing_handler(typing)
         }
-        Err(e) => return Err(wasm_error!(WasmErrorInner::Guest(String::from(e)))),
+        Err(e) => decode_error(e),
     }
 }
 
@@ -46,35 +101,7 @@ fn init(_: ()) -> ExternResult<InitCallbackResult> {
 
 #[hdk_extern(infallible)]
 fn post_commit(actions: Vec<SignedActionHashed>) {
-    for signed_action in actions.into_iter() {
-        match signed_action.action() {
-            Action::Create(create) => {
-                match &create.entry_type {
-                    EntryType::App(apptype) => match apptype.entry_index() {
-                        EntryDefIndex(0) => {
-                            let message =
-                                get_message_from_chain(create.entry_hash.clone()).unwrap();
-                            if message.author == message.receiver {
-                                debug!("post commit return ()");
-                                return ();
-                            } else {
-                                let _res = commit_message_to_receiver_chain_handler(
-                                    create.entry_hash.clone(),
-                                );
-                            }
-                        }
-                        EntryDefIndex(1) => {
-                            let _res =
-                                commit_receipt_to_sender_chain_handler(create.entry_hash.clone());
-                        }
-                        _ => (),
-                    },
-                    _ => (),
-                };
-            }
-            _ => (),
-        }
-    }
+    post_commit_handler(actions);
 }
 
 #[hdk_extern]
@@ -94,6 +121,44 @@ fn commit_message_to_receiver_chain(input: EntryHash) -> ExternResult<P2PMessage
     return commit_message_to_receiver_chain_handler(input);
 }
 
+#[hdk_extern]
+fn deliver_message(message_hash: EntryHash) -> ExternResult<OutboxStatus> {
+    deliver_message_handler(message_hash)
+}
+
+#[hdk_extern]
+fn flush_outbox(receiver: Option<AgentPubKey>) -> ExternResult<HashMap<String, OutboxStatus>> {
+    flush_outbox_handler(receiver)
+}
+
+#[hdk_extern]
+fn deliver_receipt(receipt_hash: EntryHash) -> ExternResult<OutboxStatus> {
+    deliver_receipt_handler(receipt_hash)
+}
+
+#[hdk_extern]
+fn deliver_change(change_hash: EntryHash) -> ExternResult<OutboxStatus> {
+    deliver_change_handler(change_hash)
+}
+
+#[hdk_extern]
+fn get_undelivered(_: ()) -> ExternResult<Undelivered> {
+    get_undelivered_handler()
+}
+
+#[hdk_extern]
+fn retry_delivery(
+    retry_input: RetryDeliveryInput,
+) -> ExternResult<HashMap<String, DeliveryResult>> {
+    retry_delivery_handler(retry_input)
+}
+
+#[hdk_extern(infallible)]
+fn retry_outbox(_: Option<Schedule>) -> Option<Schedule> {
+    let _res = flush_outbox_handler(None);
+    Some(Schedule::Persisted("0 */5 * * * * *".to_string()))
+}
+
 #[hdk_extern]
 fn receive_message(input: ReceiveMessageInput) -> ExternResult<P2PMessageReceipt> {
     return receive_message_handler(input);
@@ -106,9 +171,46 @@ fn read_message(
     return read_message_handler(read_message_input);
 }
 
+#[hdk_extern]
+fn mark_conversation_read(
+    mark_read_input: MarkConversationReadInput,
+) -> ExternResult<HashMap<String, P2PMessageReceipt>> {
+    mark_conversation_read_handler(mark_read_input)
+}
+
+#[hdk_extern]
+fn list_message_requests(_: ()) -> ExternResult<Vec<MessageRequest>> {
+    list_message_requests_handler()
+}
+
+#[hdk_extern]
+fn accept_message_request(sender: AgentPubKey) -> ExternResult<Vec<MessageDataAndReceipt>> {
+    accept_message_request_handler(sender)
+}
+
+#[hdk_extern]
+fn decline_message_request(sender: AgentPubKey) -> ExternResult<()> {
+    decline_message_request_handler(sender)
+}
+
 #[hdk_extern]
 fn get_latest_messages(batch_size: u8) -> ExternResult<P2PMessageHashTables> {
-    return get_latest_messages_handler(batch_size);
+    get_latest_messages_handler(P2PMessageFilterLatest {
+        batch_size,
+        exclude_blocked: false,
+    })
+}
+
+#[hdk_extern]
+fn get_latest_messages_filtered(
+    filter: P2PMessageFilterLatest,
+) -> ExternResult<P2PMessageHashTables> {
+    get_latest_messages_handler(filter)
+}
+
+#[hdk_extern]
+fn get_conversation_summaries(exclude_blocked: bool) -> ExternResult<Vec<ConversationSummary>> {
+    get_conversation_summaries_handler(exclude_blocked)
 }
 
 #[hdk_extern]
@@ -118,6 +220,11 @@ fn get_messages_by_agent_by_timestamp(
     return get_messages_by_agent_by_timestamp_handler(filter);
 }
 
+#[hdk_extern]
+fn search_messages(filter: P2PMessageFilterSearch) -> ExternResult<P2PMessageHashTables> {
+    search_messages_handler(filter)
+}
+
 #[hdk_extern]
 fn get_previous_messages(filter: P2PMessageFilterBatch) -> ExternResult<P2PMessageHashTables> {
     return get_previous_messages_handler(filter);
@@ -133,6 +240,11 @@ fn typing(typing_info: P2PTypingDetailIO) -> ExternResult<()> {
     return typing_handler(typing_info);
 }
 
+#[hdk_extern]
+fn get_typing_states(_: ()) -> ExternResult<Vec<TypingState>> {
+    get_typing_states_handler()
+}
+
 #[hdk_extern]
 fn receive_receipt(receipt: P2PMessageReceipt) -> ExternResult<HashMap<String, P2PMessageReceipt>> {
     return receive_receipt_handler(receipt);
@@ -143,6 +255,21 @@ fn get_file_bytes(file_hashes: Vec<EntryHash>) -> ExternResult<HashMap<String, P
     return get_file_bytes_handler(file_hashes);
 }
 
+#[hdk_extern]
+fn get_file_chunk(filter: FileChunkFilter) -> ExternResult<P2PFileBytes> {
+    get_file_chunk_handler(filter)
+}
+
+#[hdk_extern]
+fn get_missing_file_chunks(message_hash: EntryHash) -> ExternResult<Vec<usize>> {
+    get_missing_file_chunks_handler(message_hash)
+}
+
+#[hdk_extern]
+fn receive_file_chunk(chunk_input: ReceiveFileChunkInput) -> ExternResult<FileChunkStatus> {
+    receive_file_chunk_handler(chunk_input)
+}
+
 #[hdk_extern]
 fn pin_message(pin_message_input: PinMessageInput) -> ExternResult<HashMap<String, P2PMessagePin>> {
     return pin_message_handler(pin_message_input);
@@ -162,3 +289,184 @@ fn get_pinned_messages(conversant: AgentPubKey) -> ExternResult<P2PMessageHashTa
 fn get_adjacent_messages(filter: P2PMessageFilterBatch) -> ExternResult<P2PMessageHashTables> {
     return get_adjacent_messages_handler(filter);
 }
+
+#[hdk_extern]
+fn block_agent(agent: AgentPubKey) -> ExternResult<Vec<AgentPubKey>> {
+    block_agent_handler(agent)
+}
+
+#[hdk_extern]
+fn unblock_agent(agent: AgentPubKey) -> ExternResult<Vec<AgentPubKey>> {
+    unblock_agent_handler(agent)
+}
+
+#[hdk_extern]
+fn list_blocked_agents(_: ()) -> ExternResult<Vec<AgentPubKey>> {
+    get_blocked_agents_from_chain()
+}
+
+#[hdk_extern]
+fn get_rate_limits(_: ()) -> ExternResult<RateLimits> {
+    get_rate_limits_handler()
+}
+
+#[hdk_extern]
+fn record_spam_report(report: P2PSpamReport) -> ExternResult<ActionHash> {
+    record_spam_report_handler(report)
+}
+
+#[hdk_extern]
+fn get_spam_reports(_: ()) -> ExternResult<Vec<P2PSpamReport>> {
+    get_spam_reports_handler()
+}
+
+#[hdk_extern]
+fn issue_grant(agent: AgentPubKey) -> ExternResult<ActionHash> {
+    issue_grant_handler(agent)
+}
+
+#[hdk_extern]
+fn revoke_grant(agent: AgentPubKey) -> ExternResult<()> {
+    revoke_grant_handler(agent)
+}
+
+#[hdk_extern]
+fn request_cap_claim(_: ()) -> ExternResult<Option<CapSecret>> {
+    request_cap_claim_handler()
+}
+
+#[hdk_extern]
+fn edit_message(edit_message_input: EditMessageInput) -> ExternResult<(EntryHash, P2PMessageData)> {
+    edit_message_handler(edit_message_input)
+}
+
+#[hdk_extern]
+fn receive_edit(edit: P2PMessageEdit) -> ExternResult<HashMap<String, P2PMessageEdit>> {
+    receive_edit_handler(edit)
+}
+
+#[hdk_extern]
+fn get_message_edits(message_hash: EntryHash) -> ExternResult<Vec<(EntryHash, P2PMessageEdit)>> {
+    get_edits_from_chain(message_hash)
+}
+
+#[hdk_extern]
+fn delete_message(
+    delete_message_input: DeleteMessageInput,
+) -> ExternResult<HashMap<String, P2PMessageDeletion>> {
+    delete_message_handler(delete_message_input)
+}
+
+#[hdk_extern]
+fn retract_message(
+    deletion: P2PMessageDeletion,
+) -> ExternResult<HashMap<String, P2PMessageDeletion>> {
+    retract_message_handler(deletion)
+}
+
+#[hdk_extern]
+fn react_to_message(
+    reaction_input: ReactionInput,
+) -> ExternResult<HashMap<String, P2PMessageReaction>> {
+    react_to_message_handler(reaction_input)
+}
+
+#[hdk_extern]
+fn remove_reaction(
+    reaction_input: ReactionInput,
+) -> ExternResult<HashMap<String, P2PMessageReaction>> {
+    remove_reaction_handler(reaction_input)
+}
+
+#[hdk_extern]
+fn sync_reactions(
+    reaction: P2PMessageReaction,
+) -> ExternResult<HashMap<String, P2PMessageReaction>> {
+    sync_reactions_handler(reaction)
+}
+
+#[hdk_extern]
+fn create_group(create_group_input: CreateGroupInput) -> ExternResult<GroupData> {
+    create_group_handler(create_group_input)
+}
+
+#[hdk_extern]
+fn add_group_member(group_member_input: GroupMemberInput) -> ExternResult<GroupData> {
+    add_group_member_handler(group_member_input)
+}
+
+#[hdk_extern]
+fn remove_group_member(group_member_input: GroupMemberInput) -> ExternResult<GroupData> {
+    remove_group_member_handler(group_member_input)
+}
+
+#[hdk_extern]
+fn leave_group(group_id: EntryHash) -> ExternResult<GroupData> {
+    leave_group_handler(group_id)
+}
+
+#[hdk_extern]
+fn receive_group_membership(input: ReceiveGroupMembershipInput) -> ExternResult<()> {
+    receive_group_membership_handler(input)
+}
+
+#[hdk_extern]
+fn get_groups(_: ()) -> ExternResult<Vec<GroupData>> {
+    get_groups_handler()
+}
+
+#[hdk_extern]
+fn get_group(group_id: EntryHash) -> ExternResult<GroupData> {
+    get_group_data(group_id)
+}
+
+#[hdk_extern]
+fn send_group_message(message_input: GroupMessageInput) -> ExternResult<GroupMessageDelivery> {
+    send_group_message_handler(message_input)
+}
+
+#[hdk_extern]
+fn receive_group_message(input: ReceiveGroupMessageInput) -> ExternResult<P2PGroupMessageReceipt> {
+    receive_group_message_handler(input)
+}
+
+#[hdk_extern]
+fn read_group_messages(
+    read_group_message_input: ReadGroupMessageInput,
+) -> ExternResult<HashMap<String, P2PGroupMessageReceipt>> {
+    read_group_messages_handler(read_group_message_input)
+}
+
+#[hdk_extern]
+fn receive_group_receipt(
+    receipt: P2PGroupMessageReceipt,
+) -> ExternResult<HashMap<String, P2PGroupMessageReceipt>> {
+    receive_group_receipt_handler(receipt)
+}
+
+#[hdk_extern]
+fn get_latest_group_messages(batch_size: u8) -> ExternResult<GroupMessageHashTables> {
+    get_latest_group_messages_handler(batch_size)
+}
+
+#[hdk_extern]
+fn get_previous_group_messages(
+    filter: GroupMessageFilterBatch,
+) -> ExternResult<GroupMessageHashTables> {
+    get_previous_group_messages_handler(filter)
+}
+
+#[hdk_extern]
+fn enable_encryption(conversant: AgentPubKey) -> ExternResult<P2PEncryptionKey> {
+    enable_encryption_handler(conversant)
+}
+
+#[hdk_extern]
+fn exchange_keys(key: P2PEncryptionKey) -> ExternResult<P2PEncryptionKey> {
+    exchange_keys_handler(key)
+}
+
+#[hdk_extern]
+fn is_encrypted(conversant: AgentPubKey) -> ExternResult<bool> {
+    is_conversation_encrypted(conversant)
+}
@@ -24,4 +24,512 @@ enum EntryTypes {
         visibility = "private"
     )]
     P2PFileBytes(P2PFileBytes),
+    #[entry_def(
+        name = "p2pmessageoutbox",
+        required_validations = 5,
+        visibility = "private"
+    )]
+    P2PMessageOutbox(P2PMessageOutbox),
+    #[entry_def(
+        name = "p2pblockedagent",
+        required_validations = 5,
+        visibility = "private"
+    )]
+    P2PBlockedAgent(P2PBlockedAgent),
+    #[entry_def(
+        name = "p2pmessageedit",
+        required_validations = 5,
+        visibility = "private"
+    )]
+    P2PMessageEdit(P2PMessageEdit),
+    #[entry_def(
+        name = "p2pmessagedeletion",
+        required_validations = 5,
+        visibility = "private"
+    )]
+    P2PMessageDeletion(P2PMessageDeletion),
+    #[entry_def(
+        name = "p2pmessagereaction",
+        required_validations = 5,
+        visibility = "private"
+    )]
+    P2PMessageReaction(P2PMessageReaction),
+    #[entry_def(name = "p2pgroup", required_validations = 5, visibility = "private")]
+    P2PGroup(P2PGroup),
+    #[entry_def(
+        name = "p2pgroupmembership",
+        required_validations = 5,
+        visibility = "private"
+    )]
+    P2PGroupMembership(P2PGroupMembership),
+    #[entry_def(
+        name = "p2pgroupmessage",
+        required_validations = 5,
+        visibility = "private"
+    )]
+    P2PGroupMessage(P2PGroupMessage),
+    #[entry_def(
+        name = "p2pgroupmessagereceipt",
+        required_validations = 5,
+        visibility = "private"
+    )]
+    P2PGroupMessageReceipt(P2PGroupMessageReceipt),
+    #[entry_def(
+        name = "p2pencryptionkey",
+        required_validations = 5,
+        visibility = "private"
+    )]
+    P2PEncryptionKey(P2PEncryptionKey),
+    #[entry_def(
+        name = "p2psearchindex",
+        required_validations = 5,
+        visibility = "private"
+    )]
+    P2PSearchIndex(P2PSearchIndex),
+    #[entry_def(
+        name = "p2pchainindex",
+        required_validations = 5,
+        visibility = "private"
+    )]
+    P2PChainIndex(P2PChainIndex),
+    #[entry_def(
+        name = "p2pindexbucket",
+        required_validations = 5,
+        visibility = "private"
+    )]
+    P2PIndexBucket(P2PIndexBucket),
+    #[entry_def(
+        name = "p2preceiptoutbox",
+        required_validations = 5,
+        visibility = "private"
+    )]
+    P2PReceiptOutbox(P2PReceiptOutbox),
+    #[entry_def(
+        name = "p2pmessagerequest",
+        required_validations = 5,
+        visibility = "private"
+    )]
+    P2PMessageRequest(P2PMessageRequest),
+    #[entry_def(
+        name = "p2pqueuedmessage",
+        required_validations = 5,
+        visibility = "private"
+    )]
+    P2PQueuedMessage(P2PQueuedMessage),
+    #[entry_def(
+        name = "p2pspamreport",
+        required_validations = 5,
+        visibility = "private"
+    )]
+    P2PSpamReport(P2PSpamReport),
+    #[entry_def(
+        name = "p2ptypingstate",
+        required_validations = 5,
+        visibility = "private"
+    )]
+    P2PTypingState(P2PTypingState),
+    #[entry_def(
+        name = "p2pchangeoutbox",
+        required_validations = 5,
+        visibility = "private"
+    )]
+    P2PChangeOutbox(P2PChangeOutbox),
+}
+
+/*
+ * VALIDATION CALLBACK
+ * entries are private so only the StoreRecord op carries the app entry
+ */
+
+#[hdk_extern]
+pub fn validate(op: Op) -> ExternResult<ValidateCallbackResult> {
+    match op.flattened::<EntryTypes, ()>()? {
+        FlatOp::StoreRecord(OpRecord::CreateEntry { app_entry, action }) => {
+            validate_entry(app_entry, &action.author)
+        }
+        FlatOp::StoreRecord(OpRecord::UpdateEntry {
+            app_entry, action, ..
+        }) => validate_entry(app_entry, &action.author),
+        FlatOp::StoreEntry(OpEntry::CreateEntry { app_entry, action }) => {
+            validate_entry(app_entry, &action.author)
+        }
+        FlatOp::StoreEntry(OpEntry::UpdateEntry {
+            app_entry, action, ..
+        }) => validate_entry(app_entry, &action.author),
+        _ => Ok(ValidateCallbackResult::Valid),
+    }
+}
+
+fn validate_entry(
+    app_entry: EntryTypes,
+    author: &AgentPubKey,
+) -> ExternResult<ValidateCallbackResult> {
+    match app_entry {
+        EntryTypes::P2PMessage(message) => validate_message(message, author),
+        EntryTypes::P2PMessageReceipt(receipt) => validate_receipt(receipt),
+        EntryTypes::P2PMessagePin(pin) => validate_pin(pin),
+        EntryTypes::P2PFileBytes(file) => validate_file_bytes(file),
+        EntryTypes::P2PMessageOutbox(outbox) => validate_outbox(outbox, author),
+        EntryTypes::P2PBlockedAgent(blocked_agent) => validate_blocked_agent(blocked_agent, author),
+        EntryTypes::P2PMessageEdit(edit) => validate_edit(edit, author),
+        EntryTypes::P2PMessageDeletion(deletion) => validate_deletion(deletion, author),
+        EntryTypes::P2PMessageReaction(reaction) => validate_reaction(reaction, author),
+        EntryTypes::P2PGroup(group) => validate_group(group),
+        EntryTypes::P2PGroupMembership(membership) => validate_group_membership(membership),
+        EntryTypes::P2PGroupMessage(message) => validate_group_message(message),
+        EntryTypes::P2PGroupMessageReceipt(receipt) => validate_group_receipt(receipt),
+        EntryTypes::P2PEncryptionKey(_) => Ok(ValidateCallbackResult::Valid),
+        EntryTypes::P2PSearchIndex(index) => validate_search_index(index),
+        EntryTypes::P2PChainIndex(index) => validate_chain_index(index),
+        EntryTypes::P2PReceiptOutbox(outbox) => validate_receipt_outbox(outbox, author),
+        EntryTypes::P2PMessageRequest(request) => validate_message_request(request, author),
+        EntryTypes::P2PQueuedMessage(queued) => validate_queued_message(queued, author),
+        EntryTypes::P2PSpamReport(report) => validate_spam_report(report, author),
+        EntryTypes::P2PTypingState(state) => validate_typing_state(state, author),
+        EntryTypes::P2PChangeOutbox(outbox) => validate_change_outbox(outbox, author),
+        EntryTypes::P2PIndexBucket(bucket) => validate_index_bucket(bucket),
+    }
+}
+
+fn validate_message(
+    message: P2PMessage,
+    author: &AgentPubKey,
+) -> ExternResult<ValidateCallbackResult> {
+    // the sender commits the message and the receiver commits a copy of it
+    if message.author != *author && message.receiver != *author {
+        return invalid("P2PMessage author or receiver must be the agent committing it.");
+    }
+
+    match message.payload {
+        Payload::Text { ref payload } => {
+            if payload.trim().is_empty() {
+                return invalid("P2PMessage text payload cannot be empty.");
+            }
+        }
+        Payload::File { ref metadata, .. } => {
+            if metadata.file_name.trim().is_empty() {
+                return invalid("P2PMessage file name cannot be empty.");
+            }
+            if metadata.file_size > get_properties()?.max_file_size {
+                return invalid("P2PMessage file is larger than the maximum file size.");
+            }
+        }
+        // the content can only be checked by the conversants
+        Payload::Encrypted { .. } => (),
+    }
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+// whole files and chunks alike
+fn validate_file_bytes(file: P2PFileBytes) -> ExternResult<ValidateCallbackResult> {
+    if file.0.bytes().len() > get_properties()?.max_file_size {
+        return invalid("P2PFileBytes is larger than the maximum file size.");
+    }
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+fn validate_edit(
+    edit: P2PMessageEdit,
+    author: &AgentPubKey,
+) -> ExternResult<ValidateCallbackResult> {
+    // like the message itself, the edit is committed on both chains
+    if edit.author != *author && edit.receiver != *author {
+        return invalid("P2PMessageEdit author or receiver must be the agent committing it.");
+    }
+
+    match edit.payload {
+        Payload::Text { ref payload } => {
+            if payload.trim().is_empty() {
+                return invalid("P2PMessageEdit text payload cannot be empty.");
+            }
+        }
+        Payload::File { .. } => {
+            return invalid("P2PMessageEdit can only replace the text of a message.");
+        }
+        Payload::Encrypted { .. } => (),
+    }
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+fn validate_deletion(
+    deletion: P2PMessageDeletion,
+    author: &AgentPubKey,
+) -> ExternResult<ValidateCallbackResult> {
+    // only a retraction from the conversant is committed on behalf of another agent
+    if deletion.scope == DeleteScope::ForMe && deletion.deleted_by != *author {
+        return invalid("P2PMessageDeletion for me must be committed by the agent deleting it.");
+    }
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+fn validate_receipt(receipt: P2PMessageReceipt) -> ExternResult<ValidateCallbackResult> {
+    if receipt.id.is_empty() {
+        return invalid("P2PMessageReceipt must reference at least one message.");
+    }
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+fn validate_pin(pin: P2PMessagePin) -> ExternResult<ValidateCallbackResult> {
+    if pin.id.is_empty() {
+        return invalid("P2PMessagePin must reference at least one message.");
+    }
+
+    if pin.conversants.len() != 2 || pin.conversants[0] == pin.conversants[1] {
+        return invalid("P2PMessagePin conversants must be exactly two distinct agents.");
+    }
+
+    if let PinStatus::Pinned {
+        timestamp,
+        expires_at: Some(expires_at),
+    } = pin.status
+    {
+        if expires_at <= timestamp {
+            return invalid("P2PMessagePin must expire after it was pinned.");
+        }
+    }
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+fn validate_reaction(
+    reaction: P2PMessageReaction,
+    author: &AgentPubKey,
+) -> ExternResult<ValidateCallbackResult> {
+    // reactions are mirrored to the conversant the same way pins are
+    if !reaction.conversants.contains(author) || !reaction.conversants.contains(&reaction.reactor) {
+        return invalid("P2PMessageReaction must be between the conversants of the message.");
+    }
+
+    if reaction.emoji.trim().is_empty() || reaction.emoji.chars().count() > 16 {
+        return invalid("P2PMessageReaction emoji must be between 1 and 16 characters.");
+    }
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+fn validate_group(group: P2PGroup) -> ExternResult<ValidateCallbackResult> {
+    if group.name.trim().is_empty() {
+        return invalid("P2PGroup name cannot be empty.");
+    }
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+fn validate_group_membership(
+    membership: P2PGroupMembership,
+) -> ExternResult<ValidateCallbackResult> {
+    // members is the member list after the change was applied
+    match membership.change {
+        MembershipChange::Create => {
+            if !membership.members.contains(&membership.actor) {
+                return invalid("P2PGroupMembership creator must be a member of the group.");
+            }
+        }
+        MembershipChange::AddMember { ref member } => {
+            if !membership.members.contains(member) {
+                return invalid("P2PGroupMembership added member must be in the member list.");
+            }
+        }
+        MembershipChange::RemoveMember { ref member } => {
+            if membership.members.contains(member) || *member == membership.actor {
+                return invalid("P2PGroupMembership removed member cannot be in the member list.");
+            }
+        }
+        MembershipChange::Leave => {
+            if membership.members.contains(&membership.actor) {
+                return invalid("P2PGroupMembership leaving member cannot be in the member list.");
+            }
+        }
+    }
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+fn validate_group_message(message: P2PGroupMessage) -> ExternResult<ValidateCallbackResult> {
+    match message.payload {
+        Payload::Text { ref payload } => {
+            if payload.trim().is_empty() {
+                return invalid("P2PGroupMessage text payload cannot be empty.");
+            }
+        }
+        Payload::File { ref metadata, .. } => {
+            if metadata.file_name.trim().is_empty() {
+                return invalid("P2PGroupMessage file name cannot be empty.");
+            }
+            if metadata.file_size > get_properties()?.max_file_size {
+                return invalid("P2PGroupMessage file is larger than the maximum file size.");
+            }
+        }
+        Payload::Encrypted { .. } => {
+            return invalid("P2PGroupMessage payloads are not encrypted.");
+        }
+    }
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+fn validate_group_receipt(receipt: P2PGroupMessageReceipt) -> ExternResult<ValidateCallbackResult> {
+    if receipt.id.is_empty() {
+        return invalid("P2PGroupMessageReceipt must reference at least one message.");
+    }
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+fn validate_search_index(index: P2PSearchIndex) -> ExternResult<ValidateCallbackResult> {
+    if index.first_seq > index.last_seq {
+        return invalid("P2PSearchIndex must cover at least one action.");
+    }
+
+    let message_count = index.messages.len() as u32;
+    if !index
+        .tokens
+        .values()
+        .flatten()
+        .all(|position| *position < message_count)
+    {
+        return invalid("P2PSearchIndex words must point to messages it lists.");
+    }
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+fn validate_chain_index(index: P2PChainIndex) -> ExternResult<ValidateCallbackResult> {
+    let mut buckets: Vec<&EntryHash> = index.buckets.iter().map(|row| &row.bucket).collect();
+    buckets.sort();
+    buckets.dedup();
+    if buckets.len() != index.buckets.len() {
+        return invalid("P2PChainIndex cannot list a bucket twice.");
+    }
+    if buckets.iter().any(|bucket| index.replaced.contains(bucket)) {
+        return invalid("P2PChainIndex cannot replace a bucket it lists.");
+    }
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+fn validate_index_bucket(bucket: P2PIndexBucket) -> ExternResult<ValidateCallbackResult> {
+    if bucket.first_seq > bucket.last_seq {
+        return invalid("P2PIndexBucket must cover at least one action.");
+    }
+
+    let in_batch = |seq: u32| seq >= bucket.first_seq && seq <= bucket.last_seq;
+    if !bucket
+        .messages
+        .iter()
+        .all(|row| in_batch(row.seq) && row.conversant == bucket.conversant)
+        || !bucket.receipts.iter().all(|row| in_batch(row.seq))
+        || !bucket
+            .edits
+            .iter()
+            .chain(bucket.reactions.iter())
+            .chain(bucket.deletions.iter())
+            .chain(bucket.pins.iter())
+            .all(|row| in_batch(row.seq))
+    {
+        return invalid("P2PIndexBucket rows must belong to the actions it covers.");
+    }
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+fn validate_outbox(
+    outbox: P2PMessageOutbox,
+    author: &AgentPubKey,
+) -> ExternResult<ValidateCallbackResult> {
+    if outbox.receiver == *author {
+        return invalid("P2PMessageOutbox cannot queue a message to the agent committing it.");
+    }
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+fn validate_receipt_outbox(
+    outbox: P2PReceiptOutbox,
+    author: &AgentPubKey,
+) -> ExternResult<ValidateCallbackResult> {
+    if outbox.sender == *author {
+        return invalid("P2PReceiptOutbox cannot queue a receipt to the agent committing it.");
+    }
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+fn validate_message_request(
+    request: P2PMessageRequest,
+    author: &AgentPubKey,
+) -> ExternResult<ValidateCallbackResult> {
+    if request.sender == *author {
+        return invalid("P2PMessageRequest cannot be a request from the agent committing it.");
+    }
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+fn validate_queued_message(
+    queued: P2PQueuedMessage,
+    author: &AgentPubKey,
+) -> ExternResult<ValidateCallbackResult> {
+    if queued.message.receiver != *author || queued.message.author == *author {
+        return invalid("P2PQueuedMessage must hold a message from another agent to its receiver.");
+    }
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+fn validate_spam_report(
+    report: P2PSpamReport,
+    author: &AgentPubKey,
+) -> ExternResult<ValidateCallbackResult> {
+    if report.sender == *author {
+        return invalid("P2PSpamReport cannot report the agent committing it.");
+    }
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+fn validate_typing_state(
+    state: P2PTypingState,
+    author: &AgentPubKey,
+) -> ExternResult<ValidateCallbackResult> {
+    if state.typist != *author && state.receiver != *author {
+        return invalid("P2PTypingState typist or receiver must be the agent committing it.");
+    }
+
+    if state.expires_at < state.timestamp {
+        return invalid("P2PTypingState cannot expire before it was set.");
+    }
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+fn validate_change_outbox(
+    outbox: P2PChangeOutbox,
+    author: &AgentPubKey,
+) -> ExternResult<ValidateCallbackResult> {
+    if outbox.receiver == *author {
+        return invalid("P2PChangeOutbox cannot queue a change to the agent committing it.");
+    }
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+fn validate_blocked_agent(
+    blocked_agent: P2PBlockedAgent,
+    author: &AgentPubKey,
+) -> ExternResult<ValidateCallbackResult> {
+    if blocked_agent.agent == *author {
+        return invalid("P2PBlockedAgent cannot block the agent committing it.");
+    }
+
+    Ok(ValidateCallbackResult::Valid)
+}
+
+fn invalid(reason: &str) -> ExternResult<ValidateCallbackResult> {
+    Ok(ValidateCallbackResult::Invalid(String::from(reason)))
 }
@@ -31,14 +31,84 @@ pub struct ReadMessageInput {
     pub timestamp: Timestamp,
 }
 
+#[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug)]
+pub struct MarkConversationReadInput {
+    pub conversant: AgentPubKey,
+    pub up_to_timestamp: Timestamp,
+}
+
 #[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug)]
 pub struct PinMessageInput {
     pub message_hashes: Vec<EntryHash>,
     pub conversants: Vec<AgentPubKey>,
-    pub status: String,
+    pub status: PinStatus,
+}
+
+#[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug)]
+pub struct EditMessageInput {
+    pub message_hash: EntryHash,
+    pub payload: PayloadInput,
+}
+
+#[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug)]
+pub struct DeleteMessageInput {
+    pub message_hash: EntryHash,
+    pub scope: DeleteScope,
+}
+
+#[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug)]
+pub struct ReactionInput {
+    pub message_hash: EntryHash,
+    pub emoji: String,
+}
+
+#[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug)]
+pub struct RetryDeliveryInput {
+    #[serde(default)]
+    pub message_hashes: Vec<EntryHash>,
+    #[serde(default)]
+    pub receipt_hashes: Vec<EntryHash>,
+}
+
+// GROUP INPUT FROM THE FRONTEND
+#[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug)]
+pub struct CreateGroupInput {
+    pub name: String,
+    pub members: Vec<AgentPubKey>,
+}
+
+#[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug)]
+pub struct GroupMemberInput {
+    pub group_id: EntryHash,
+    pub member: AgentPubKey,
+}
+
+#[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug)]
+pub struct GroupMessageInput {
+    pub group_id: EntryHash,
+    pub payload: PayloadInput,
+    pub reply_to: Option<EntryHash>,
+}
+
+#[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug)]
+pub struct ReadGroupMessageInput {
+    pub group_id: EntryHash,
+    pub message_hashes: Vec<EntryHash>,
     pub timestamp: Timestamp,
 }
 
+#[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug)]
+pub struct ReceiveGroupMessageInput {
+    pub message: P2PGroupMessage,
+    pub file: Option<P2PFileBytes>,
+}
+
+#[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug)]
+pub struct ReceiveGroupMembershipInput {
+    pub group: P2PGroup,
+    pub membership: P2PGroupMembership,
+}
+
 #[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug)]
 pub struct CommitMessageInput {
     pub message_hash: EntryHash,
@@ -55,6 +125,17 @@ pub struct CommitReceiptInput {
 pub struct ReceiveMessageInput {
     pub message: P2PMessage,
     pub file: Option<P2PFileBytes>,
+    // set instead of file when the message is encrypted
+    #[serde(default)]
+    pub encrypted_file: Option<XSalsa20Poly1305EncryptedData>,
+}
+
+#[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug)]
+pub struct ReceiveFileChunkInput {
+    pub message_hash: EntryHash,
+    pub index: usize,
+    pub chunk: Option<P2PFileBytes>,
+    pub encrypted_chunk: Option<XSalsa20Poly1305EncryptedData>,
 }
 
 #[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug)]
@@ -91,33 +172,135 @@ pub enum PayloadInput {
     },
 }
 
-#[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug)]
-#[serde(tag = "type", rename_all = "SCREAMING_SNAKE_CASE", content = "payload")]
+// File covers Media and Other, Encrypted the messages this agent cannot decrypt
+#[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum PayloadType {
     Text,
     File,
     Media,
+    Other,
+    Encrypted,
     All,
 }
 
+// every field narrows the filter, a missing or empty field lets every message through
+#[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug, Default)]
+pub struct PayloadFilter {
+    #[serde(default)]
+    pub kinds: Vec<PayloadType>,
+    #[serde(default)]
+    pub mime_types: Vec<String>, // prefixes of the file type, e.g. "image/"
+    #[serde(default)]
+    pub has_reply: Option<bool>,
+    #[serde(default)]
+    pub from_me: Option<bool>,
+}
+
 // GET FILTERS
 #[derive(From, Into, Serialize, Deserialize, Clone, SerializedBytes, Debug)]
 pub struct P2PMessageFilterAgentTimestamp {
     pub conversant: AgentPubKey,
     pub date: Timestamp,
-    pub payload_type: String,
+    #[serde(default)]
+    pub payload_filter: PayloadFilter,
 }
 
 #[derive(From, Into, Serialize, Deserialize, Clone, SerializedBytes, Debug)]
 pub struct P2PMessageFilterBatch {
     pub conversant: AgentPubKey,
     pub batch_size: u8,
-    pub payload_type: String,
+    #[serde(default)]
+    pub payload_filter: PayloadFilter,
     pub last_fetched_timestamp: Option<Timestamp>, // header timestamp; oldest message in the last fetched message
     pub last_fetched_message_id: Option<EntryHash>,
 }
 
+#[derive(From, Into, Serialize, Deserialize, Clone, SerializedBytes, Debug)]
+pub struct P2PMessageFilterLatest {
+    pub batch_size: u8,
+    #[serde(default)]
+    pub exclude_blocked: bool, // leave out conversations with agents in the block list
+}
+
+#[derive(From, Into, Serialize, Deserialize, Clone, SerializedBytes, Debug)]
+pub struct FileChunkFilter {
+    pub file_hash: EntryHash,
+    pub index: usize,
+}
+
+#[derive(From, Into, Serialize, Deserialize, Clone, SerializedBytes, Debug)]
+pub struct P2PMessageFilterSearch {
+    pub query: String,
+    pub conversant: Option<AgentPubKey>,
+    #[serde(default)]
+    pub payload_filter: PayloadFilter,
+    pub from: Option<Timestamp>,  // microseconds since epoch, inclusive
+    pub until: Option<Timestamp>, // microseconds since epoch, inclusive
+}
+
+#[derive(From, Into, Serialize, Deserialize, Clone, SerializedBytes, Debug)]
+pub struct GroupMessageFilterBatch {
+    pub group_id: EntryHash,
+    pub batch_size: u8,
+    pub last_fetched_timestamp: Option<Timestamp>,
+    pub last_fetched_message_id: Option<EntryHash>,
+}
+
 // OUTPUT STRUCTURES
+// status is the latest outbox state, None for messages that never went through the outbox
+#[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct UndeliveredMessage {
+    pub message_hash: EntryHash,
+    pub receiver: AgentPubKey,
+    pub time_sent: Timestamp,
+    pub status: Option<OutboxStatus>,
+}
+
+#[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct UndeliveredReceipt {
+    pub receipt_hash: EntryHash,
+    pub sender: AgentPubKey,
+    pub status: OutboxStatus,
+}
+
+#[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug)]
+pub struct Undelivered {
+    pub messages: Vec<UndeliveredMessage>,
+    pub receipts: Vec<UndeliveredReceipt>,
+}
+
+// one entry of the conversation list, unread_count counts received messages without a read receipt
+#[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ConversationSummary {
+    pub conversant: AgentPubKey,
+    pub last_message: (EntryHash, P2PMessageData),
+    pub last_activity: Timestamp,
+    pub unread_count: usize,
+    pub pinned_count: usize,
+    pub last_outgoing_read: bool,
+}
+
+// the queued messages of an agent that is not in a conversation with the receiver yet
+#[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageRequest {
+    pub sender: AgentPubKey,
+    pub messages: Vec<(EntryHash, P2PMessageData)>,
+    pub last_received: Timestamp,
+}
+
+// keyed by message or receipt hash, errors carry the {"code": ..., "message": ...} of the failure
+#[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug)]
+#[serde(tag = "type", content = "payload", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum DeliveryResult {
+    Status(OutboxStatus),
+    Error(String),
+}
+
 #[derive(From, Into, Serialize, Deserialize, Clone, SerializedBytes, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct P2PMessageReplyTo {
@@ -137,6 +320,9 @@ pub struct P2PMessageData {
     pub payload: Payload,
     pub time_sent: Timestamp,
     pub reply_to: Option<P2PMessageReplyTo>,
+    pub edited: bool,
+    pub deleted: bool,
+    pub reactions: HashMap<String, Vec<AgentPubKey>>, // emoji to the agents who reacted with it
 }
 
 #[derive(From, Into, Serialize, Deserialize, Clone, SerializedBytes, Debug)]
@@ -146,6 +332,42 @@ pub struct P2PMessageHashTables(
     pub HashMap<String, P2PMessageReceipt>,             // ReceiptContents
 );
 
+#[derive(From, Into, Serialize, Deserialize, Clone, SerializedBytes, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupData {
+    pub group_id: EntryHash,
+    pub name: String,
+    pub creator: AgentPubKey,
+    pub members: Vec<AgentPubKey>,
+    pub time_created: Timestamp,
+}
+
+#[derive(From, Into, Serialize, Deserialize, Clone, SerializedBytes, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupMessageData {
+    pub group_id: EntryHash,
+    pub author: AgentPubKey,
+    pub payload: Payload,
+    pub time_sent: Timestamp,
+    pub reply_to: Option<EntryHash>,
+}
+
+#[derive(From, Into, Serialize, Deserialize, Clone, SerializedBytes, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupMessageDelivery {
+    pub message_hash: EntryHash,
+    pub message: GroupMessageData,
+    pub receipts: HashMap<String, P2PGroupMessageReceipt>, // one delivered receipt per member
+    pub undelivered: Vec<AgentPubKey>,                     // members who could not be reached
+}
+
+#[derive(From, Into, Serialize, Deserialize, Clone, SerializedBytes, Debug)]
+pub struct GroupMessageHashTables(
+    pub HashMap<String, Vec<String>>, // GroupMessages
+    pub HashMap<String, (GroupMessageData, Vec<String>)>, // MessageContents
+    pub HashMap<String, P2PGroupMessageReceipt>, // ReceiptContents
+);
+
 // SIGNAL STRUCTURES
 #[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug)]
 #[serde(tag = "type")]
@@ -154,8 +376,16 @@ pub enum Signal {
     P2PMessageReceipt(ReceiptSignal),
     P2PPinSignal(PinSignal),
     P2PTypingDetailSignal(TypingSignal),
+    P2PMessageEdit(EditSignal),
+    P2PMessageRetraction(RetractionSignal),
+    P2PReactionSignal(ReactionSignal),
+    P2PFileChunk(FileChunkSignal),
+    GroupMessage(GroupMessageSignal),
+    GroupMessageReceipt(GroupReceiptSignal),
+    GroupMembership(GroupMembershipSignal),
     ErrorMessage(ErrorMessage),
     ErrorReceipt(ErrorReceipt),
+    MessageRequest(MessageRequestSignal),
 }
 
 #[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug)]
@@ -169,6 +399,11 @@ pub struct MessageSignal {
     pub message: MessageDataAndReceipt,
 }
 
+#[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug)]
+pub struct MessageRequestSignal {
+    pub request: MessageRequest,
+}
+
 #[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug)]
 pub struct ReceiptSignal {
     pub receipt: HashMap<String, P2PMessageReceipt>,
@@ -179,10 +414,69 @@ pub struct PinSignal {
     pub pin: HashMap<String, P2PMessagePin>,
 }
 
+#[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug)]
+pub struct EditSignal {
+    pub edit: HashMap<String, P2PMessageEdit>,
+}
+
+#[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug)]
+pub struct RetractionSignal {
+    pub deletion: HashMap<String, P2PMessageDeletion>,
+}
+
+#[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug)]
+pub struct ReactionSignal {
+    pub reaction: HashMap<String, P2PMessageReaction>,
+}
+
+// what the receiver made of a file chunk, a file that fails its check is not sent again
+#[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum FileChunkStatus {
+    Received { received: usize, total: usize },
+    ValidationFailed { reason: String },
+}
+
+#[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug)]
+pub struct FileChunkSignal {
+    pub message_hash: EntryHash,
+    pub received: usize,
+    pub total: usize,
+}
+
+#[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug)]
+pub struct GroupMessageSignal {
+    pub message: (EntryHash, GroupMessageData),
+    pub receipt: (EntryHash, P2PGroupMessageReceipt),
+}
+
+#[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug)]
+pub struct GroupReceiptSignal {
+    pub receipt: HashMap<String, P2PGroupMessageReceipt>,
+}
+
+#[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug)]
+pub struct GroupMembershipSignal {
+    pub group: GroupData,
+}
+
 #[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug)]
 pub struct TypingSignal {
     pub agent: AgentPubKey,
     pub is_typing: bool,
+    pub timestamp: Timestamp,
+    // milliseconds after timestamp the indicator goes off without a new signal
+    pub ttl: u32,
+}
+
+// the typing indicator of a conversant, is_typing turns false once they stopped or it expired
+#[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TypingState {
+    pub agent: AgentPubKey,
+    pub is_typing: bool,
+    pub last_seen_typing: Option<Timestamp>,
+    pub expires_at: Timestamp,
 }
 
 #[derive(From, Into, Serialize, Deserialize, Clone, SerializedBytes, Debug)]
@@ -191,9 +485,40 @@ pub struct MessageDataAndReceipt(pub (EntryHash, P2PMessageData), pub (EntryHash
 #[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug)]
 pub struct ErrorMessage {
     pub message: P2PMessage,
+    pub reason: String,
 }
 
 #[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug)]
 pub struct ErrorReceipt {
     pub receipt: P2PMessageReceipt,
+    pub reason: String,
+}
+
+// ERROR STRUCTURES
+// every extern fails with one of these, sent to the frontend as {"code": ..., "message": ...}
+#[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug, PartialEq)]
+#[serde(tag = "code", content = "message", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum P2PMessageError {
+    ReceiverOffline(String),
+    Unauthorized(String),
+    NotFound(String),
+    ValidationFailed(String),
+    Blocked(String),
+    DecodeFailure(String),
+    Countersigning(String),
+    InvalidFile(String),
+    FileTooLarge(String),
+    InvalidPin(String),
+    PinLimitExceeded(String),
+    RateLimited(String),
+}
+
+// wasm errors only carry a string, so the error goes out as the json serde gives the enum
+impl std::fmt::Display for P2PMessageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match serde_json::to_string(self) {
+            Ok(json) => write!(f, "{}", json),
+            Err(_) => Err(std::fmt::Error),
+        }
+    }
 }
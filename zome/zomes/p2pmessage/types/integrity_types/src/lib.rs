@@ -1,5 +1,7 @@
 use derive_more::From;
 use hdi::prelude::{timestamp::Timestamp, *};
+use holo_hash::AgentPubKeyB64;
+use std::collections::BTreeMap;
 
 /*
  * ENTRY STRUCTURES
@@ -38,6 +40,283 @@ pub struct P2PMessagePin {
 #[hdk_entry_helper]
 pub struct P2PFileBytes(pub SerializedBytes);
 
+#[derive(Clone)]
+#[hdk_entry_helper]
+#[serde(rename_all = "camelCase")]
+pub struct P2PMessageOutbox {
+    pub message_hash: EntryHash,
+    pub receiver: AgentPubKey,
+    pub status: OutboxStatus,
+}
+
+// receipts on their way back to the sender of the messages, same states as the message outbox
+#[derive(Clone)]
+#[hdk_entry_helper]
+#[serde(rename_all = "camelCase")]
+pub struct P2PReceiptOutbox {
+    pub receipt_hash: EntryHash,
+    pub sender: AgentPubKey,
+    pub status: OutboxStatus,
+}
+
+// edits and retractions on their way to the receiver of a message, same states as the outbox
+#[derive(Clone)]
+#[hdk_entry_helper]
+#[serde(rename_all = "camelCase")]
+pub struct P2PChangeOutbox {
+    pub change_hash: EntryHash,
+    pub receiver: AgentPubKey,
+    pub status: OutboxStatus,
+}
+
+// messages of agents that are not in a conversation yet wait here until the receiver decides,
+// the latest accepted or declined entry of a sender closes the requests before it
+#[derive(Clone)]
+#[hdk_entry_helper]
+#[serde(rename_all = "camelCase")]
+pub struct P2PMessageRequest {
+    pub sender: AgentPubKey,
+    pub status: RequestStatus,
+}
+
+// the message of a pending request, only read when the request is listed or accepted
+#[derive(Clone)]
+#[hdk_entry_helper]
+#[serde(rename_all = "camelCase")]
+pub struct P2PQueuedMessage {
+    pub message: P2PMessage,
+    pub file: Option<P2PFileBytes>,
+}
+
+// kept locally when a sender goes over one of the rate limits
+#[derive(Clone)]
+#[hdk_entry_helper]
+#[serde(rename_all = "camelCase")]
+pub struct P2PSpamReport {
+    pub sender: AgentPubKey,
+    pub limit: RateLimit,
+    pub timestamp: Timestamp,
+}
+
+// a conversant starting or stopping to type, kept by the typist and by the receiver
+#[derive(Clone)]
+#[hdk_entry_helper]
+#[serde(rename_all = "camelCase")]
+pub struct P2PTypingState {
+    pub typist: AgentPubKey,
+    pub receiver: AgentPubKey,
+    pub is_typing: bool,
+    pub timestamp: Timestamp,
+    pub expires_at: Timestamp,
+}
+
+#[derive(Clone)]
+#[hdk_entry_helper]
+#[serde(rename_all = "camelCase")]
+pub struct P2PMessageEdit {
+    pub message_hash: EntryHash,
+    pub author: AgentPubKey,
+    pub receiver: AgentPubKey,
+    pub payload: Payload,
+    pub time_edited: Timestamp,
+}
+
+#[derive(Clone)]
+#[hdk_entry_helper]
+#[serde(rename_all = "camelCase")]
+pub struct P2PMessageDeletion {
+    pub message_hash: EntryHash,
+    pub deleted_by: AgentPubKey,
+    pub scope: DeleteScope,
+    pub time_deleted: Timestamp,
+}
+
+#[derive(Clone)]
+#[hdk_entry_helper]
+#[serde(rename_all = "camelCase")]
+pub struct P2PMessageReaction {
+    pub message_hash: EntryHash,
+    pub reactor: AgentPubKey,
+    pub conversants: Vec<AgentPubKey>,
+    pub emoji: String,
+    pub status: ReactionStatus,
+}
+
+#[derive(Clone)]
+#[hdk_entry_helper]
+#[serde(rename_all = "camelCase")]
+pub struct P2PBlockedAgent {
+    pub agent: AgentPubKey,
+    pub status: BlockStatus,
+}
+
+#[derive(Clone)]
+#[hdk_entry_helper]
+#[serde(rename_all = "camelCase")]
+pub struct P2PGroup {
+    pub creator: AgentPubKey,
+    pub name: String,
+    pub time_created: Timestamp,
+}
+
+#[derive(Clone)]
+#[hdk_entry_helper]
+#[serde(rename_all = "camelCase")]
+pub struct P2PGroupMembership {
+    pub group_id: EntryHash,
+    pub actor: AgentPubKey,
+    pub change: MembershipChange,
+    pub members: Vec<AgentPubKey>,
+    pub timestamp: Timestamp,
+}
+
+#[derive(Clone)]
+#[hdk_entry_helper]
+#[serde(rename_all = "camelCase")]
+pub struct P2PGroupMessage {
+    pub group_id: EntryHash,
+    pub author: AgentPubKey,
+    pub payload: Payload,
+    pub time_sent: Timestamp,
+    pub reply_to: Option<EntryHash>,
+}
+
+#[derive(Clone)]
+#[hdk_entry_helper]
+#[serde(rename_all = "camelCase")]
+pub struct P2PGroupMessageReceipt {
+    pub id: Vec<EntryHash>,
+    pub group_id: EntryHash,
+    pub member: AgentPubKey,
+    pub status: Status,
+}
+
+#[derive(Clone)]
+#[hdk_entry_helper]
+#[serde(rename_all = "camelCase")]
+pub struct P2PEncryptionKey {
+    pub agent: AgentPubKey,
+    pub key: X25519PubKey,
+    pub time_created: Timestamp,
+}
+
+/*
+ * words of a batch of messages and edits, each mapped to its positions in messages. the words
+ * are kept as keyed hashes of their first letters, so the index holds none of the text
+ */
+#[derive(Clone)]
+#[hdk_entry_helper]
+#[serde(rename_all = "camelCase")]
+pub struct P2PSearchIndex {
+    pub tokens: BTreeMap<String, Vec<u32>>,
+    pub messages: Vec<EntryHash>,
+    pub edits: Vec<IndexedEdit>,
+    pub deleted: Vec<EntryHash>,
+    // messages and edits that could not be decrypted yet, indexed again after a key exchange
+    pub encrypted: Vec<EntryHash>,
+    // the index of the same batch this one was indexed again from
+    pub replaced: Vec<EntryHash>,
+    pub first_seq: u32,
+    pub last_seq: u32,
+}
+
+#[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexedEdit {
+    pub message_hash: EntryHash,
+    pub edit_hash: EntryHash,
+    pub time_edited: Timestamp,
+}
+
+/*
+ * a directory of the chain index only lists the buckets it adds and the buckets they replace,
+ * all directories together cover every action up to the highest last_seq
+ */
+#[derive(Clone)]
+#[hdk_entry_helper]
+#[serde(rename_all = "camelCase")]
+pub struct P2PChainIndex {
+    pub last_seq: u32,
+    pub buckets: Vec<IndexBucketRef>,
+    pub replaced: Vec<EntryHash>,
+}
+
+// a conversation and day of time_sent can have a bucket in every batch of actions
+#[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexBucketRef {
+    pub conversant: AgentPubKey,
+    pub day: i64,
+    pub bucket: EntryHash,
+    // when the last message of the bucket was committed here
+    pub last_committed: Timestamp,
+    // the file hashes of the file messages of the bucket
+    pub files: Vec<EntryHash>,
+}
+
+// compact rows for the messages of one conversation and day and everything about those messages
+#[derive(Clone)]
+#[hdk_entry_helper]
+#[serde(rename_all = "camelCase")]
+pub struct P2PIndexBucket {
+    pub conversant: AgentPubKey,
+    pub day: i64,
+    pub first_seq: u32,
+    pub last_seq: u32,
+    pub messages: Vec<IndexedMessage>,
+    pub receipts: Vec<IndexedReceipt>,
+    pub edits: Vec<IndexedChange>,
+    pub reactions: Vec<IndexedChange>,
+    pub deletions: Vec<IndexedChange>,
+    pub pins: Vec<IndexedChange>,
+}
+
+/*
+ * mime_type is only set for files.
+ * size is the one rate limits count and time_committed is on the clock of this agent
+ */
+#[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexedMessage {
+    pub seq: u32,
+    pub hash: EntryHash,
+    pub conversant: AgentPubKey,
+    pub time_sent: Timestamp,
+    pub payload_type: IndexedPayloadKind,
+    pub mime_type: Option<String>,
+    pub has_reply: bool,
+    pub from_me: bool,
+    pub size: usize,
+    pub time_committed: Timestamp,
+    pub file_hash: Option<EntryHash>,
+}
+
+// Encrypted stays until the keys to read the payload are known
+#[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug, PartialEq)]
+pub enum IndexedPayloadKind {
+    Text,
+    Media,
+    Other,
+    Encrypted,
+}
+
+#[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexedReceipt {
+    pub seq: u32,
+    pub hash: EntryHash,
+    pub messages: Vec<EntryHash>,
+}
+
+// an edit, reaction, deletion or pin and the messages it is about
+#[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexedChange {
+    pub seq: u32,
+    pub hash: EntryHash,
+    pub messages: Vec<EntryHash>,
+}
+
 #[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug)]
 #[serde(tag = "status", rename_all = "camelCase")]
 pub enum Status {
@@ -49,8 +328,93 @@ pub enum Status {
 #[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug)]
 #[serde(tag = "pinstatus", rename_all = "camelCase")]
 pub enum PinStatus {
-    Pinned { timestamp: Timestamp },
-    Unpinned { timestamp: Timestamp },
+    Pinned {
+        timestamp: Timestamp,
+        // the pin lapses once this time has passed
+        #[serde(default)]
+        expires_at: Option<Timestamp>,
+    },
+    Unpinned {
+        timestamp: Timestamp,
+    },
+}
+
+#[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug)]
+#[serde(tag = "change", rename_all = "camelCase")]
+pub enum MembershipChange {
+    Create,
+    AddMember { member: AgentPubKey },
+    RemoveMember { member: AgentPubKey },
+    Leave,
+}
+
+#[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug)]
+#[serde(tag = "reactionstatus", rename_all = "camelCase")]
+pub enum ReactionStatus {
+    Reacted { timestamp: Timestamp },
+    Removed { timestamp: Timestamp },
+}
+
+#[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum DeleteScope {
+    ForMe,
+    ForEveryone,
+}
+
+#[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug)]
+#[serde(tag = "blockstatus", rename_all = "camelCase")]
+pub enum BlockStatus {
+    Blocked { timestamp: Timestamp },
+    Unblocked { timestamp: Timestamp },
+}
+
+#[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug)]
+#[serde(tag = "outboxstatus", rename_all = "camelCase")]
+pub enum OutboxStatus {
+    Pending {
+        attempts: u32,
+        timestamp: Timestamp,
+    },
+    Delivered {
+        timestamp: Timestamp,
+    },
+    // the receiver queued the message as a message request, it is delivered once accepted
+    Requested {
+        timestamp: Timestamp,
+    },
+    // the receiver was reached but the delivery did not go through
+    Failed {
+        reason: String,
+        attempts: u32,
+        timestamp: Timestamp,
+    },
+}
+
+#[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum RateLimit {
+    MessagesPerMinute,
+    BytesPerDay,
+}
+
+#[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug)]
+#[serde(tag = "requeststatus", rename_all = "camelCase")]
+pub enum RequestStatus {
+    Pending {
+        message_hash: EntryHash,
+        // the P2PQueuedMessage holding the message
+        queued_hash: EntryHash,
+        // the bytes that came with the message, counted by the rate limits
+        size: usize,
+        timestamp: Timestamp,
+    },
+    Accepted {
+        timestamp: Timestamp,
+    },
+    Declined {
+        timestamp: Timestamp,
+    },
 }
 
 #[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug)]
@@ -60,6 +424,9 @@ pub struct FileMetadata {
     pub file_size: usize,
     pub file_type: String,
     pub file_hash: EntryHash,
+    // hashes of the P2PFileBytes chunks in order, empty for files stored whole
+    #[serde(default)]
+    pub chunks: Vec<EntryHash>,
 }
 
 #[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug)]
@@ -80,4 +447,112 @@ pub enum Payload {
         metadata: FileMetadata,
         file_type: FileType,
     },
+    // a Text or File payload sealed for the two conversants
+    #[serde(rename_all = "camelCase")]
+    Encrypted {
+        sender_key: X25519PubKey,
+        receiver_key: X25519PubKey,
+        data: XSalsa20Poly1305EncryptedData,
+    },
+}
+
+/*
+ * DNA PROPERTIES
+ * set in dna.yaml, missing properties fall back to their defaults
+ */
+
+pub const DEFAULT_MAX_FILE_SIZE: usize = 50 * 1024 * 1024;
+pub const DEFAULT_MAX_PINS_PER_CONVERSATION: usize = 50;
+pub const DEFAULT_MESSAGES_PER_MINUTE: usize = 60;
+pub const DEFAULT_BYTES_PER_DAY: usize = 1024 * 1024 * 1024;
+
+#[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug)]
+pub struct P2PMessageProperties {
+    #[serde(default = "default_max_file_size")]
+    pub max_file_size: usize,
+    #[serde(default = "default_max_pins_per_conversation")]
+    pub max_pins_per_conversation: usize,
+    #[serde(default)]
+    pub cap_grant_policy: CapGrantPolicy,
+    // first messages of unknown agents wait in a request queue instead of the inbox
+    #[serde(default)]
+    pub message_requests: bool,
+    #[serde(default)]
+    pub rate_limits: RateLimits,
+}
+
+// what a single sender can make this agent commit or show
+#[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug)]
+pub struct RateLimits {
+    #[serde(default = "default_messages_per_minute")]
+    pub messages_per_minute: usize,
+    #[serde(default = "default_bytes_per_day")]
+    pub bytes_per_day: usize,
+    // keep a P2PSpamReport for every call over a limit
+    #[serde(default)]
+    pub report_spam: bool,
+}
+
+impl Default for RateLimits {
+    fn default() -> Self {
+        RateLimits {
+            messages_per_minute: default_messages_per_minute(),
+            bytes_per_day: default_bytes_per_day(),
+            report_spam: false,
+        }
+    }
+}
+
+fn default_messages_per_minute() -> usize {
+    DEFAULT_MESSAGES_PER_MINUTE
+}
+
+fn default_bytes_per_day() -> usize {
+    DEFAULT_BYTES_PER_DAY
+}
+
+// who may call the remote functions of the zome, every agent of the dna shares the policy
+#[derive(Serialize, Deserialize, SerializedBytes, Clone, Debug, Default)]
+#[serde(tag = "policy", rename_all = "camelCase")]
+pub enum CapGrantPolicy {
+    #[default]
+    Unrestricted,
+    Transferable { secret: String },
+    Assigned { contacts: Vec<AgentPubKeyB64> },
+}
+
+fn default_max_file_size() -> usize {
+    DEFAULT_MAX_FILE_SIZE
+}
+
+fn default_max_pins_per_conversation() -> usize {
+    DEFAULT_MAX_PINS_PER_CONVERSATION
+}
+
+impl Default for P2PMessageProperties {
+    fn default() -> Self {
+        P2PMessageProperties {
+            max_file_size: default_max_file_size(),
+            max_pins_per_conversation: default_max_pins_per_conversation(),
+            cap_grant_policy: CapGrantPolicy::default(),
+            message_requests: false,
+            rate_limits: RateLimits::default(),
+        }
+    }
+}
+
+// only a dna without properties gets the defaults, properties that do not parse are an error
+pub fn get_properties() -> ExternResult<P2PMessageProperties> {
+    let properties = dna_info()?.properties;
+    match properties.bytes().as_slice() {
+        // nothing set in dna.yaml, or a msgpack nil
+        [] | [0xc0] => return Ok(P2PMessageProperties::default()),
+        _ => (),
+    }
+    P2PMessageProperties::try_from(properties).map_err(|e| {
+        wasm_error!(WasmErrorInner::Guest(format!(
+            "Sorry. The properties of the dna could not be read: {}",
+            e
+        )))
+    })
 }